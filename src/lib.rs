@@ -0,0 +1,16 @@
+//! Building blocks for emulators: a configurable memory map and the buses built on top of it.
+//!
+//! The crate builds without the standard library when the default `std` feature is disabled;
+//! it only needs `alloc`. The `std` feature additionally enables loading images from files, such
+//! as [`memory::Rom::from_file`] and [`memory::MemoryMap::load_binary`], mapping regions backed
+//! by files with [`memory::MemoryMap::map_file_region`], and sharing a bus between threads with
+//! [`memory::SharedBus`].
+//!
+//! The crate doesn't log anything itself. Emulators that aggregate logs, e.g. with `tracing`,
+//! can forward the faults, unmapped accesses and watchpoint hits a map reports to the hook set
+//! with [`memory::MemoryMap::set_event_hook`], each tagged with a level to log it at.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod memory;
@@ -1,5 +1,3 @@
-pub mod memory;
-
 fn main() {
     println!("Hello, world!");
 }
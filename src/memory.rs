@@ -1,7 +1,103 @@
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::sync::{Arc, Mutex, MutexGuard};
+
+mod address;
+mod alignment;
+mod atomic;
+mod banked;
+mod builder;
+mod chained;
+mod endian;
+mod event;
+mod fault;
+#[cfg(feature = "std")]
+mod file;
+mod fixed;
+mod fork;
+mod gdb;
+mod granule;
+mod hexdump;
+mod ihex;
+mod index;
+mod info;
+mod ioport;
+mod latency;
+mod lazy;
+#[cfg(feature = "std")]
+mod load;
+mod mirror;
+mod optimize;
+mod peek;
+mod protect;
+mod ram;
+mod record;
+mod register;
+mod rom;
+#[cfg(feature = "std")]
+mod shared;
+mod slice;
+mod snapshot;
+mod spec;
+mod split;
+mod stats;
+mod trace;
+mod transaction;
+mod translate;
+mod unmapped;
+mod update;
+mod value;
+mod watch;
+mod words;
+
+pub use address::BusAddress;
+pub use alignment::AlignmentPolicy;
+pub use banked::{BankSelector, BankedRegion};
+pub use builder::MemoryMapBuilder;
+pub use chained::ChainedBus;
+pub use event::{BusEvent, EventHook, EventLevel};
+pub use fault::FaultHandler;
+#[cfg(feature = "std")]
+pub use file::FileRegion;
+pub use fixed::FixedMemoryMap;
+pub use ihex::IhexError;
+pub use info::RegionInfo;
+pub use ioport::{IoPins, IoPort};
+pub use latency::{ContentionFn, LatencyCost};
+pub use lazy::{LazyRam, PageAllocCallback};
+pub use protect::Permissions;
+pub use ram::Ram;
+pub use record::{RecordingBus, ReplayBus};
+pub use register::RegisterOverflow;
+pub use rom::Rom;
+#[cfg(feature = "std")]
+pub use shared::{BusBusy, SharedBus};
+pub use slice::SliceBus;
+pub use snapshot::{RegionSnapshot, Snapshot};
+pub use spec::{RegionLayout, RegionSpec};
+pub use split::SplitBus;
+pub use stats::RegionStats;
+pub use trace::{AccessOrigin, TraceEvent, TraceHook};
+pub use transaction::Txn;
+pub use translate::{TranslatingBus, Translation};
+pub use unmapped::UnmappedReadProvider;
+pub use value::{AccessWidth, BusValue};
+pub use watch::{AccessKind, WatchpointCallback, WatchpointId, WatchpointPredicate};
+
 pub type Address = u128;
 
-pub trait Bus {
-    fn select_address(&mut self, addr: Address);
+pub trait Bus<A: BusAddress = Address> {
+    fn select_address(&mut self, addr: A);
+
+    /// Whether something on this bus answers for `addr`, which lets buses be layered.
+    fn handles(&self, _addr: A) -> bool {
+        true
+    }
+
     fn read_u8(&mut self) -> u8;
     fn read_u16(&mut self) -> u16;
     fn read_u32(&mut self) -> u32;
@@ -12,53 +108,398 @@ pub trait Bus {
     fn write_u32(&mut self, data: u32);
     fn write_u64(&mut self, data: u64);
     fn write_u128(&mut self, data: u128);
+
+    /// Reads a value of any width, for code generic over it.
+    fn read<T: BusValue>(&mut self) -> T
+    where
+        Self: Sized,
+    {
+        T::read_from(self)
+    }
+
+    fn write<T: BusValue>(&mut self, data: T)
+    where
+        Self: Sized,
+    {
+        data.write_to(self)
+    }
+
+    /// Reads `width` bytes, zero-extended, for a width only known at run time.
+    fn read_dynamic(&mut self, width: AccessWidth) -> u128 {
+        match width {
+            AccessWidth::W8 => self.read_u8() as u128,
+            AccessWidth::W16 => self.read_u16() as u128,
+            AccessWidth::W32 => self.read_u32() as u128,
+            AccessWidth::W64 => self.read_u64() as u128,
+            AccessWidth::W128 => self.read_u128(),
+        }
+    }
+
+    /// Writes the low `width` bytes of `data`, discarding the rest.
+    fn write_dynamic(&mut self, width: AccessWidth, data: u128) {
+        match width {
+            AccessWidth::W8 => self.write_u8(data as u8),
+            AccessWidth::W16 => self.write_u16(data as u16),
+            AccessWidth::W32 => self.write_u32(data as u32),
+            AccessWidth::W64 => self.write_u64(data as u64),
+            AccessWidth::W128 => self.write_u128(data),
+        }
+    }
+
+    // Instruction fetches. A bus that treats them like data reads, the default, needn't
+    // implement these, while a memory map checks them against execute permissions.
+
+    fn fetch_u8(&mut self) -> u8 {
+        self.read_u8()
+    }
+
+    fn fetch_u16(&mut self) -> u16 {
+        self.read_u16()
+    }
+
+    fn fetch_u32(&mut self) -> u32 {
+        self.read_u32()
+    }
+
+    fn fetch_u64(&mut self) -> u64 {
+        self.read_u64()
+    }
+
+    fn fetch_u128(&mut self) -> u128 {
+        self.read_u128()
+    }
+
+    // Selecting the address and accessing it in one call. The address stays selected afterwards.
+
+    fn read_u8_at(&mut self, addr: A) -> u8 {
+        self.select_address(addr);
+        self.read_u8()
+    }
+
+    fn read_u16_at(&mut self, addr: A) -> u16 {
+        self.select_address(addr);
+        self.read_u16()
+    }
+
+    fn read_u32_at(&mut self, addr: A) -> u32 {
+        self.select_address(addr);
+        self.read_u32()
+    }
+
+    fn read_u64_at(&mut self, addr: A) -> u64 {
+        self.select_address(addr);
+        self.read_u64()
+    }
+
+    fn read_u128_at(&mut self, addr: A) -> u128 {
+        self.select_address(addr);
+        self.read_u128()
+    }
+
+    fn write_u8_at(&mut self, addr: A, data: u8) {
+        self.select_address(addr);
+        self.write_u8(data)
+    }
+
+    fn write_u16_at(&mut self, addr: A, data: u16) {
+        self.select_address(addr);
+        self.write_u16(data)
+    }
+
+    fn write_u32_at(&mut self, addr: A, data: u32) {
+        self.select_address(addr);
+        self.write_u32(data)
+    }
+
+    fn write_u64_at(&mut self, addr: A, data: u64) {
+        self.select_address(addr);
+        self.write_u64(data)
+    }
+
+    fn write_u128_at(&mut self, addr: A, data: u128) {
+        self.select_address(addr);
+        self.write_u128(data)
+    }
+
+    // Signed views of the unsigned accessors, reinterpreting the bits as two's complement.
+
+    fn read_i8(&mut self) -> i8 {
+        self.read_u8() as i8
+    }
+
+    fn read_i16(&mut self) -> i16 {
+        self.read_u16() as i16
+    }
+
+    fn read_i32(&mut self) -> i32 {
+        self.read_u32() as i32
+    }
+
+    fn read_i64(&mut self) -> i64 {
+        self.read_u64() as i64
+    }
+
+    fn read_i128(&mut self) -> i128 {
+        self.read_u128() as i128
+    }
+
+    fn write_i8(&mut self, data: i8) {
+        self.write_u8(data as u8)
+    }
+
+    fn write_i16(&mut self, data: i16) {
+        self.write_u16(data as u16)
+    }
+
+    fn write_i32(&mut self, data: i32) {
+        self.write_u32(data as u32)
+    }
+
+    fn write_i64(&mut self, data: i64) {
+        self.write_u64(data as u64)
+    }
+
+    fn write_i128(&mut self, data: i128) {
+        self.write_u128(data as u128)
+    }
+}
+
+/// A peripheral that owns its state and handles every access width through one interface.
+///
+/// `addr` is the absolute bus address, or the offset from the region's start when the region uses
+/// relative addressing, with the region's address mask applied. `width` is the size of the access
+/// in bytes. Accesses wider than `max_width` are never handed to the device; the memory map
+/// composes them from narrower ones in its configured byte order.
+pub trait Device<A: BusAddress = Address> {
+    fn read(&mut self, addr: A, width: u8) -> u128;
+    fn write(&mut self, addr: A, width: u8, data: u128);
+
+    /// Performs a read that may go unanswered, which the map calls in place of `read`. Devices
+    /// that can raise a bus error override this; the rest keep the default, which always
+    /// answers with `read`.
+    fn respond_read(&mut self, addr: A, width: u8) -> BusResponse {
+        BusResponse::Data(self.read(addr, width))
+    }
+
+    /// Performs a write that may go unanswered, which the map calls in place of `write`.
+    fn respond_write(&mut self, addr: A, width: u8, data: u128) -> BusResponse {
+        self.write(addr, width, data);
+        BusResponse::Ack
+    }
+
+    /// The widest access, in bytes, the device handles natively.
+    fn max_width(&self) -> u8 {
+        16
+    }
+
+    /// Captures the device's state for a save state, or `None` if it has none worth saving.
+    fn save_state(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Reloads state captured by `save_state`, returning whether it was accepted.
+    fn load_state(&mut self, _state: &[u8]) -> bool {
+        false
+    }
+
+    /// Reads the byte at `addr` without any side effects, for debuggers, or returns `None` if the
+    /// device can't.
+    fn peek(&self, _addr: A) -> Option<u8> {
+        None
+    }
+
+    /// Changes the byte at `addr` in the device's backing storage, even if it is read-only,
+    /// without any other side effects. Returns whether the device could.
+    fn poke(&mut self, _addr: A, _data: u8) -> bool {
+        false
+    }
+
+    /// An independent copy of the device and its state, for [`MemoryMap::try_clone`], or `None`
+    /// if the device can't be copied.
+    fn try_clone(&self) -> Option<Box<dyn Device<A> + Send>> {
+        None
+    }
+
+    /// The first address and length of the buffer backing the device, for devices that wrap
+    /// addresses into one, so [`MemoryMap::set_strict_backing`] can catch accesses outside it.
+    ///
+    /// The base is widened to a `u128` whatever the bus's address type, the way devices like
+    /// [`Ram`] keep it, and is compared with the widened addresses the device is handed.
+    fn backing(&self) -> Option<(u128, usize)> {
+        None
+    }
+
+    /// The buffer backing the device, for [`MemoryMap::region_bytes`], or `None` if it has none
+    /// to lend out.
+    fn bytes(&self) -> Option<&[u8]> {
+        None
+    }
+
+    /// The backing buffer for changing in place, for [`MemoryMap::region_bytes_mut`].
+    fn bytes_mut(&mut self) -> Option<&mut [u8]> {
+        None
+    }
+
+    /// The device as plain [`Ram`], so [`MemoryMap::optimize`] can merge adjacent RAM regions
+    /// into one. Only `Ram` itself returns `Some`.
+    fn as_ram_mut(&mut self) -> Option<&mut Ram> {
+        None
+    }
+}
+
+/// How a device answered an access.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BusResponse {
+    /// The device answered a read with this data.
+    Data(u128),
+    /// The device acknowledged a write.
+    Ack,
+    /// Nothing acknowledged the access, like a 68000 bus error after DTACK times out. The
+    /// fallible API fails with `BusError`, unlike an access to unmapped space, which is answered
+    /// with that space's default.
+    Error,
+}
+
+/// Lets a device be mapped while its owner keeps a handle to it. Requires the `std` feature.
+#[cfg(feature = "std")]
+impl<A: BusAddress, D: Device<A> + ?Sized> Device<A> for Arc<Mutex<D>> {
+    fn read(&mut self, addr: A, width: u8) -> u128 {
+        lock(self).read(addr, width)
+    }
+
+    fn write(&mut self, addr: A, width: u8, data: u128) {
+        lock(self).write(addr, width, data)
+    }
+
+    fn respond_read(&mut self, addr: A, width: u8) -> BusResponse {
+        lock(self).respond_read(addr, width)
+    }
+
+    fn respond_write(&mut self, addr: A, width: u8, data: u128) -> BusResponse {
+        lock(self).respond_write(addr, width, data)
+    }
+
+    fn max_width(&self) -> u8 {
+        lock(self).max_width()
+    }
+
+    fn save_state(&self) -> Option<Vec<u8>> {
+        lock(self).save_state()
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> bool {
+        lock(self).load_state(state)
+    }
+
+    fn peek(&self, addr: A) -> Option<u8> {
+        lock(self).peek(addr)
+    }
+
+    fn poke(&mut self, addr: A, data: u8) -> bool {
+        lock(self).poke(addr, data)
+    }
+
+    /// Copies the device out from behind the lock, so the copy is no longer shared.
+    fn try_clone(&self) -> Option<Box<dyn Device<A> + Send>> {
+        lock(self).try_clone()
+    }
+
+    fn backing(&self) -> Option<(u128, usize)> {
+        lock(self).backing()
+    }
 }
 
+/// Locks a shared device or bus. A panic while it was held leaves it poisoned, which is treated
+/// as fatal like a broken map.
+#[cfg(feature = "std")]
+fn lock<T: ?Sized>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .expect("A shared device or bus was poisoned by a panic.")
+}
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum MemoryMapEntryType {
     UnmappedLow,
     UnmappedHigh,
+    /// Unmapped, reading back whatever value the last completed access put on the bus.
+    UnmappedOpenBus,
     Read,
     Write,
     ReadWrite,
 }
 
-type ReadU8Delegate = Option<fn(Address) -> u8>;
-type ReadU16Delegate = Option<fn(Address) -> u16>;
-type ReadU32Delegate = Option<fn(Address) -> u32>;
-type ReadU64Delegate = Option<fn(Address) -> u64>;
-type ReadU128Delegate = Option<fn(Address) -> u128>;
+type ReadU8Delegate<A> = Option<Box<dyn FnMut(A) -> u8 + Send>>;
+type ReadU16Delegate<A> = Option<Box<dyn FnMut(A) -> u16 + Send>>;
+type ReadU32Delegate<A> = Option<Box<dyn FnMut(A) -> u32 + Send>>;
+type ReadU64Delegate<A> = Option<Box<dyn FnMut(A) -> u64 + Send>>;
+type ReadU128Delegate<A> = Option<Box<dyn FnMut(A) -> u128 + Send>>;
 
-type WriteU8Delegate = Option<fn(Address, u8)>;
-type WriteU16Delegate = Option<fn(Address, u16)>;
-type WriteU32Delegate = Option<fn(Address, u32)>;
-type WriteU64Delegate = Option<fn(Address, u64)>;
-type WriteU128Delegate = Option<fn(Address, u128)>;
+type WriteU8Delegate<A> = Option<Box<dyn FnMut(A, u8) + Send>>;
+type WriteU16Delegate<A> = Option<Box<dyn FnMut(A, u16) + Send>>;
+type WriteU32Delegate<A> = Option<Box<dyn FnMut(A, u32) + Send>>;
+type WriteU64Delegate<A> = Option<Box<dyn FnMut(A, u64) + Send>>;
+type WriteU128Delegate<A> = Option<Box<dyn FnMut(A, u128) + Send>>;
 
-#[derive(Clone, Copy, Debug)]
-pub struct MemoryMapEntry {
-    start: Address,
-    end: Address,
+pub struct MemoryMapEntry<A: BusAddress = Address> {
+    start: A,
+    end: A,
     entry_type: MemoryMapEntryType,
-    func_read_u8: ReadU8Delegate,
-    func_read_u16: ReadU16Delegate,
-    func_read_u32: ReadU32Delegate,
-    func_read_u64: ReadU64Delegate,
-    func_read_u128: ReadU128Delegate,
-    func_write_u8: WriteU8Delegate,
-    func_write_u16: WriteU16Delegate,
-    func_write_u32: WriteU32Delegate,
-    func_write_u64: WriteU64Delegate,
-    func_write_u128: WriteU128Delegate,
+    func_read_u8: ReadU8Delegate<A>,
+    func_read_u16: ReadU16Delegate<A>,
+    func_read_u32: ReadU32Delegate<A>,
+    func_read_u64: ReadU64Delegate<A>,
+    func_read_u128: ReadU128Delegate<A>,
+    func_write_u8: WriteU8Delegate<A>,
+    func_write_u16: WriteU16Delegate<A>,
+    func_write_u32: WriteU32Delegate<A>,
+    func_write_u64: WriteU64Delegate<A>,
+    func_write_u128: WriteU128Delegate<A>,
+    device: Option<Box<dyn Device<A> + Send>>,
+    addr_mask: A,
+    /// Masks overriding `addr_mask` for reads and for writes.
+    read_mask: Option<A>,
+    write_mask: Option<A>,
+    /// The stride and target window start of a region mirroring one window over and over.
+    mirror: Option<(A, A)>,
+    /// Where regions overlap, the one with the highest priority answers.
+    priority: i32,
+    relative: bool,
+    /// Where the region's first address is redirected to, if the region aliases another.
+    alias: Option<A>,
+    allow_width_synthesis: bool,
+    /// The narrowest access, in bytes, the region's handlers are called with.
+    granularity: u8,
+    /// The width, in bytes, of the registers making up the region and how wider writes fit them.
+    register: Option<(u8, RegisterOverflow)>,
+    name: Option<&'static str>,
+    latency: u32,
+    /// Extra cycles an access costs depending on when it happens.
+    contention: Option<ContentionFn<A>>,
+    reads: [u64; 5],
+    writes: [u64; 5],
 }
 
 impl MemoryMapEntry {
     pub fn new() -> MemoryMapEntry {
+        MemoryMapEntry::default()
+    }
+
+    /// An entry covering `start..=end` with nothing attached, usable in a `const`.
+    ///
+    /// Panics if `start` is past `end`.
+    pub const fn with(
+        start: Address,
+        end: Address,
+        entry_type: MemoryMapEntryType,
+    ) -> MemoryMapEntry {
+        if start > end {
+            panic!("Memory region start is past its end.");
+        }
         MemoryMapEntry {
-            start: 0,
-            end: std::u128::MAX,
-            entry_type: MemoryMapEntryType::UnmappedLow,
+            start,
+            end,
+            entry_type,
             func_read_u8: None,
             func_read_u16: None,
             func_read_u32: None,
@@ -69,180 +510,3325 @@ impl MemoryMapEntry {
             func_write_u32: None,
             func_write_u64: None,
             func_write_u128: None,
+            device: None,
+            addr_mask: Address::MAX,
+            read_mask: None,
+            write_mask: None,
+            mirror: None,
+            priority: 0,
+            relative: false,
+            alias: None,
+            allow_width_synthesis: true,
+            granularity: 1,
+            register: None,
+            name: None,
+            latency: 0,
+            contention: None,
+            reads: [0; 5],
+            writes: [0; 5],
         }
     }
 }
 
-pub struct MemoryMap {
-    entries: Vec<MemoryMapEntry>,
-    current_addr: Address,
-    global_addr_mask: Address,
-}
+impl<A: BusAddress> MemoryMapEntry<A> {
+    /// An entry covering the `len` bytes from `start`, so `len` 0x100 at 0x1000 ends at 0x10ff.
+    ///
+    /// Panics if `len` is zero or the region would run past the top of the address space.
+    pub fn with_base_len(start: A, len: A) -> MemoryMapEntry<A> {
+        if len == A::ZERO {
+            panic!("Memory region must be at least one byte long.");
+        }
+        let end = match start.checked_add(len - A::ONE) {
+            Some(end) => end,
+            None => panic!(
+                "Memory region of {:#x} bytes at {:#x} runs past the end of the address space.",
+                len, start
+            ),
+        };
+        let mut entry = MemoryMapEntry::default();
+        entry.set_bounds(start, end);
+        entry
+    }
 
-#[derive(Debug, PartialEq)]
-pub enum MemoryMapError {
-    NoEntriesFound { addr: Address },
-}
+    /// Sets the region to cover `start..=end`. Both bounds are inclusive, so a region can reach
+    /// the top of the address space; a fresh entry spans the whole of it.
+    pub fn set_bounds(&mut self, start: A, end: A) {
+        check_bounds(start, end);
+        self.start = start;
+        self.end = end;
+    }
 
-impl MemoryMap {
-    fn addr(&self) -> Address {
-        self.current_addr & self.global_addr_mask
+    pub fn entry_type(&self) -> MemoryMapEntryType {
+        self.entry_type
     }
-}
 
-fn search_entries(map: &mut MemoryMap) -> MemoryMapEntry {
-    let mut entry_count = 0;
-    for (i, entry) in map.entries.iter().enumerate() {
-        if (entry.start <= map.addr()) && (entry.end >= map.addr()) {
-            return *entry;
-        }
-        entry_count = i;
+    /// Changes what the region allows. Making it `Read` write-protects it, so writes are dropped
+    /// before reaching its handlers, or refused in strict mode.
+    pub fn set_entry_type(&mut self, entry_type: MemoryMapEntryType) {
+        self.entry_type = entry_type;
     }
-    map.entries.push(MemoryMapEntry::new());
-    map.entries[entry_count + 1]
-}
 
+    /// Where this region overlaps others, the one with the highest priority answers, and
+    /// priority only falls back to the usual precedence among equals. Defaults to 0.
+    pub fn set_priority(&mut self, priority: i32) {
+        self.priority = priority;
+    }
 
-impl MemoryMap {
-    pub fn new() -> MemoryMap {
-        MemoryMap {
-            entries: vec![MemoryMapEntry::new(); 1],
-            current_addr: 0,
-            global_addr_mask: std::u128::MAX,
-        }
+    /// Labels the region for diagnostics, such as errors and the map's `Display` output.
+    pub fn set_name(&mut self, name: &'static str) {
+        self.name = Some(name);
     }
-}
 
-impl Bus for MemoryMap {
-    fn select_address(&mut self, addr: Address) {
-        self.current_addr = addr & self.global_addr_mask;
+    /// Masks the address handed to the region's device or delegates, which mirrors a small device
+    /// across a larger window. Defaults to all ones.
+    pub fn set_addr_mask(&mut self, mask: A) {
+        self.addr_mask = mask;
     }
-    fn read_u8(&mut self) -> u8 {
-        let entry = search_entries(self);
-        match entry.func_read_u8 {
-            Some(func) => func(self.current_addr),
-            None => {
-                match entry.entry_type {
-                    MemoryMapEntryType::UnmappedLow => 0,
-                    MemoryMapEntryType::UnmappedHigh => 0xff,
-                    _ => panic!("Your memory map is broken. Please fix it!"),
+
+    /// Masks the address handlers see for reads only, in place of the address mask, for
+    /// hardware that decodes reads and writes differently.
+    pub fn set_read_mask(&mut self, mask: A) {
+        self.read_mask = Some(mask);
+    }
+
+    /// Like [`MemoryMapEntry::set_read_mask`], for writes.
+    pub fn set_write_mask(&mut self, mask: A) {
+        self.write_mask = Some(mask);
+    }
+
+    /// By default handlers receive the absolute bus address. With relative addressing they
+    /// receive the offset from the region's start instead. Either way the address mask is applied
+    /// afterwards.
+    pub fn set_relative_addressing(&mut self, relative: bool) {
+        self.relative = relative;
+    }
+
+    /// By default an access wider than anything in the region handles is composed from narrower
+    /// ones, ordered by the map's endianness. Registers that behave differently per access width
+    /// can turn this off, which makes such an access fail with `MissingDelegate` instead.
+    pub fn set_allow_width_synthesis(&mut self, allow: bool) {
+        self.allow_width_synthesis = allow;
+    }
+
+    /// Where an access to `addr` is redirected if the region is an alias.
+    fn alias_target(&self, addr: A) -> Option<A> {
+        self.alias
+            .map(|target| target.wrapping_add(addr - self.start))
+    }
+
+    /// The address a handler in this region sees for a `kind` access to `addr`.
+    fn handler_addr(&self, addr: A, kind: AccessKind) -> A {
+        let addr = match self.mirror {
+            Some((stride, target)) => {
+                let offset = A::from_u128((addr - self.start).to_u128() % stride.to_u128());
+                if self.relative {
+                    offset
+                } else {
+                    target + offset
                 }
             }
-        }
+            None if self.relative => addr - self.start,
+            None => addr,
+        };
+        let mask = match kind {
+            AccessKind::Write => self.write_mask,
+            _ => self.read_mask,
+        };
+        addr & mask.unwrap_or(self.addr_mask)
     }
 
-    fn read_u16(&mut self) -> u16 {
-        let entry = search_entries(self);
-        match entry.func_read_u16 {
-            Some(func) => func(self.current_addr),
-            None => self.read_u8() as u16,
-        }
+    /// Upgrades the entry type so it is at least readable.
+    fn mark_readable(&mut self) {
+        self.entry_type = match self.entry_type {
+            MemoryMapEntryType::Write | MemoryMapEntryType::ReadWrite => {
+                MemoryMapEntryType::ReadWrite
+            }
+            _ => MemoryMapEntryType::Read,
+        };
     }
 
-    fn read_u32(&mut self) -> u32 {
-        let entry = search_entries(self);
-        match entry.func_read_u32 {
-            Some(func) => func(self.current_addr),
-            None => self.read_u16() as u32,
-        }
+    fn has_read_handler(&self) -> bool {
+        self.device.is_some()
+            || self.func_read_u8.is_some()
+            || self.func_read_u16.is_some()
+            || self.func_read_u32.is_some()
+            || self.func_read_u64.is_some()
+            || self.func_read_u128.is_some()
     }
 
-    fn read_u64(&mut self) -> u64 {
-        let entry = search_entries(self);
-        match entry.func_read_u64 {
-            Some(func) => func(self.current_addr),
-            None => self.read_u32() as u64,
-        }
+    fn has_write_handler(&self) -> bool {
+        self.device.is_some()
+            || self.func_write_u8.is_some()
+            || self.func_write_u16.is_some()
+            || self.func_write_u32.is_some()
+            || self.func_write_u64.is_some()
+            || self.func_write_u128.is_some()
     }
 
-    fn read_u128(&mut self) -> u128 {
-        let entry = search_entries(self);
-        match entry.func_read_u128 {
-            Some(func) => func(self.current_addr),
-            None => self.read_u64() as u128,
+    fn is_readable(&self) -> bool {
+        matches!(
+            self.entry_type,
+            MemoryMapEntryType::Read | MemoryMapEntryType::ReadWrite
+        )
+    }
+
+    fn is_writable(&self) -> bool {
+        matches!(
+            self.entry_type,
+            MemoryMapEntryType::Write | MemoryMapEntryType::ReadWrite
+        )
+    }
+
+    /// Upgrades the entry type so it is at least writable.
+    fn mark_writable(&mut self) {
+        self.entry_type = match self.entry_type {
+            MemoryMapEntryType::Read | MemoryMapEntryType::ReadWrite => {
+                MemoryMapEntryType::ReadWrite
+            }
+            _ => MemoryMapEntryType::Write,
+        };
+    }
+
+    pub fn set_read_u8<F>(&mut self, func: F)
+    where
+        F: FnMut(A) -> u8 + Send + 'static,
+    {
+        self.func_read_u8 = Some(Box::new(func));
+        self.mark_readable();
+    }
+
+    pub fn set_read_u16<F>(&mut self, func: F)
+    where
+        F: FnMut(A) -> u16 + Send + 'static,
+    {
+        self.func_read_u16 = Some(Box::new(func));
+        self.mark_readable();
+    }
+
+    pub fn set_read_u32<F>(&mut self, func: F)
+    where
+        F: FnMut(A) -> u32 + Send + 'static,
+    {
+        self.func_read_u32 = Some(Box::new(func));
+        self.mark_readable();
+    }
+
+    pub fn set_read_u64<F>(&mut self, func: F)
+    where
+        F: FnMut(A) -> u64 + Send + 'static,
+    {
+        self.func_read_u64 = Some(Box::new(func));
+        self.mark_readable();
+    }
+
+    pub fn set_read_u128<F>(&mut self, func: F)
+    where
+        F: FnMut(A) -> u128 + Send + 'static,
+    {
+        self.func_read_u128 = Some(Box::new(func));
+        self.mark_readable();
+    }
+
+    pub fn set_write_u8<F>(&mut self, func: F)
+    where
+        F: FnMut(A, u8) + Send + 'static,
+    {
+        self.func_write_u8 = Some(Box::new(func));
+        self.mark_writable();
+    }
+
+    pub fn set_write_u16<F>(&mut self, func: F)
+    where
+        F: FnMut(A, u16) + Send + 'static,
+    {
+        self.func_write_u16 = Some(Box::new(func));
+        self.mark_writable();
+    }
+
+    pub fn set_write_u32<F>(&mut self, func: F)
+    where
+        F: FnMut(A, u32) + Send + 'static,
+    {
+        self.func_write_u32 = Some(Box::new(func));
+        self.mark_writable();
+    }
+
+    pub fn set_write_u64<F>(&mut self, func: F)
+    where
+        F: FnMut(A, u64) + Send + 'static,
+    {
+        self.func_write_u64 = Some(Box::new(func));
+        self.mark_writable();
+    }
+
+    pub fn set_write_u128<F>(&mut self, func: F)
+    where
+        F: FnMut(A, u128) + Send + 'static,
+    {
+        self.func_write_u128 = Some(Box::new(func));
+        self.mark_writable();
+    }
+
+    /// Sets all five read delegates to copies of `func`, which gets the width of each access in
+    /// bytes and whose result is truncated to it.
+    pub fn set_uniform_read<F>(&mut self, func: F)
+    where
+        F: FnMut(A, u8) -> u128 + Clone + Send + 'static,
+    {
+        let mut read = func.clone();
+        self.set_read_u8(move |addr| read(addr, 1) as u8);
+        let mut read = func.clone();
+        self.set_read_u16(move |addr| read(addr, 2) as u16);
+        let mut read = func.clone();
+        self.set_read_u32(move |addr| read(addr, 4) as u32);
+        let mut read = func.clone();
+        self.set_read_u64(move |addr| read(addr, 8) as u64);
+        let mut read = func;
+        self.set_read_u128(move |addr| read(addr, 16));
+    }
+
+    /// Sets all five write delegates to copies of `func`, which gets the width of each access in
+    /// bytes along with the value widened to a `u128`.
+    pub fn set_uniform_write<F>(&mut self, func: F)
+    where
+        F: FnMut(A, u8, u128) + Clone + Send + 'static,
+    {
+        let mut write = func.clone();
+        self.set_write_u8(move |addr, data| write(addr, 1, data as u128));
+        let mut write = func.clone();
+        self.set_write_u16(move |addr, data| write(addr, 2, data as u128));
+        let mut write = func.clone();
+        self.set_write_u32(move |addr, data| write(addr, 4, data as u128));
+        let mut write = func.clone();
+        self.set_write_u64(move |addr, data| write(addr, 8, data as u128));
+        let mut write = func;
+        self.set_write_u128(move |addr, data| write(addr, 16, data));
+    }
+
+    /// Routes every access to `device`, taking precedence over any delegates.
+    pub fn set_device<D>(&mut self, device: D)
+    where
+        D: Device<A> + Send + 'static,
+    {
+        self.device = Some(Box::new(device));
+        self.mark_readable();
+        self.mark_writable();
+    }
+
+    /// Hands a `width`-byte read to whatever in the region handles that width, if anything does.
+    fn dispatch_read(&mut self, addr: A, width: u8) -> Result<Option<u128>, MemoryMapError> {
+        let bus_addr = addr;
+        let addr = self.handler_addr(addr, AccessKind::Read);
+        match self.device.as_mut() {
+            Some(device) if width <= device.max_width() => match device.respond_read(addr, width) {
+                BusResponse::Data(data) => Ok(Some(data)),
+                BusResponse::Ack => Ok(Some(0)),
+                BusResponse::Error => Err(MemoryMapError::BusError {
+                    addr: bus_addr.to_u128(),
+                }),
+            },
+            Some(_) => Ok(None),
+            None => Ok(self.call_read(addr, width)),
         }
     }
 
-    fn write_u8(&mut self, data: u8) {
-        let entry = search_entries(self);
-        match entry.func_write_u8 {
-            Some(func) => func(self.current_addr, data),
-            None => return,
+    /// Hands a `width`-byte write to whatever in the region handles that width, returning whether
+    /// anything did.
+    fn dispatch_write(&mut self, addr: A, width: u8, data: u128) -> Result<bool, MemoryMapError> {
+        let bus_addr = addr;
+        let addr = self.handler_addr(addr, AccessKind::Write);
+        match self.device.as_mut() {
+            Some(device) if width <= device.max_width() => {
+                match device.respond_write(addr, width, data) {
+                    BusResponse::Error => Err(MemoryMapError::BusError {
+                        addr: bus_addr.to_u128(),
+                    }),
+                    _ => Ok(true),
+                }
+            }
+            Some(_) => Ok(false),
+            None => Ok(self.call_write(addr, width, data)),
         }
     }
 
-    fn write_u16(&mut self, data: u16) {
-        let entry = search_entries(self);
-        match entry.func_write_u16 {
-            Some(func) => func(self.current_addr, data),
-            None => return,
+    /// Reads a byte, answering with the region's unmapped value if nothing handles it.
+    fn read_byte(&mut self, addr: A) -> Result<u8, MemoryMapError> {
+        if let Some(data) = self.dispatch_read(addr, 1)? {
+            return Ok(data as u8);
+        }
+        match self.entry_type {
+            // Open bus reads are answered by the map, which holds the bus latch.
+            MemoryMapEntryType::UnmappedLow | MemoryMapEntryType::UnmappedOpenBus => Ok(0),
+            MemoryMapEntryType::UnmappedHigh => Ok(0xff),
+            _ => Err(MemoryMapError::MissingDelegate {
+                addr: addr.to_u128(),
+                width: 1,
+                region: self.name,
+            }),
         }
     }
 
-    fn write_u32(&mut self, data: u32) {
-        let entry = search_entries(self);
-        match entry.func_write_u32 {
-            Some(func) => func(self.current_addr, data),
-            None => return,
+    /// Writes a byte, dropping it if nothing handles it and the region isn't meant to be writable.
+    fn write_byte(&mut self, addr: A, data: u8) -> Result<(), MemoryMapError> {
+        if self.dispatch_write(addr, 1, data as u128)? {
+            return Ok(());
+        }
+        match self.entry_type {
+            MemoryMapEntryType::Write | MemoryMapEntryType::ReadWrite => {
+                Err(MemoryMapError::MissingDelegate {
+                    addr: addr.to_u128(),
+                    width: 1,
+                    region: self.name,
+                })
+            }
+            _ => Ok(()),
         }
     }
 
-    fn write_u64(&mut self, data: u64) {
-        let entry = search_entries(self);
-        match entry.func_write_u64 {
-            Some(func) => func(self.current_addr, data),
-            None => return,
+    /// Calls the read delegate for a `width`-byte access, if there is one.
+    fn call_read(&mut self, addr: A, width: u8) -> Option<u128> {
+        match width {
+            1 => self.func_read_u8.as_mut().map(|func| func(addr) as u128),
+            2 => self.func_read_u16.as_mut().map(|func| func(addr) as u128),
+            4 => self.func_read_u32.as_mut().map(|func| func(addr) as u128),
+            8 => self.func_read_u64.as_mut().map(|func| func(addr) as u128),
+            _ => self.func_read_u128.as_mut().map(|func| func(addr)),
         }
     }
 
-    fn write_u128(&mut self, data: u128) {
-        let entry = search_entries(self);
-        match entry.func_write_u128 {
-            Some(func) => func(self.current_addr, data),
-            None => return,
+    /// Calls the write delegate for a `width`-byte access, returning whether there was one.
+    fn call_write(&mut self, addr: A, width: u8, data: u128) -> bool {
+        match width {
+            1 => self
+                .func_write_u8
+                .as_mut()
+                .map(|func| func(addr, data as u8)),
+            2 => self
+                .func_write_u16
+                .as_mut()
+                .map(|func| func(addr, data as u16)),
+            4 => self
+                .func_write_u32
+                .as_mut()
+                .map(|func| func(addr, data as u32)),
+            8 => self
+                .func_write_u64
+                .as_mut()
+                .map(|func| func(addr, data as u64)),
+            _ => self.func_write_u128.as_mut().map(|func| func(addr, data)),
         }
+        .is_some()
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    pub fn on_device<D>(&mut self, device: D) -> &mut MemoryMapEntry<A>
+    where
+        D: Device<A> + Send + 'static,
+    {
+        self.set_device(device);
+        self
+    }
 
-    #[test]
-    fn test_no_memory_map_entries() {
-        let mut map = MemoryMap::new();
-        map.select_address(5);
-        assert!(search_entries(&mut map).func_read_u8.is_none());
+    pub fn on_read_u8<F>(&mut self, func: F) -> &mut MemoryMapEntry<A>
+    where
+        F: FnMut(A) -> u8 + Send + 'static,
+    {
+        self.set_read_u8(func);
+        self
     }
 
-    #[test]
-    fn test_reading_unmapped() {
-        let mut map = MemoryMap::new();
-        map.select_address(0);
-        assert_eq!(map.read_u8(), 0);
-        map.entries[0].entry_type = MemoryMapEntryType::UnmappedHigh;
-        assert_eq!(map.read_u8(), 0xFF);
+    pub fn on_read_u16<F>(&mut self, func: F) -> &mut MemoryMapEntry<A>
+    where
+        F: FnMut(A) -> u16 + Send + 'static,
+    {
+        self.set_read_u16(func);
+        self
     }
 
-    #[test]
-    fn test_writing_unmapped() {
-        let mut map = MemoryMap::new();
-        map.select_address(0);
-        map.entries[0].entry_type = MemoryMapEntryType::UnmappedHigh;
-        map.write_u8(0x55);
-        assert_eq!(map.read_u8(), 0xFF);
+    pub fn on_read_u32<F>(&mut self, func: F) -> &mut MemoryMapEntry<A>
+    where
+        F: FnMut(A) -> u32 + Send + 'static,
+    {
+        self.set_read_u32(func);
+        self
     }
 
-    #[test]
-    fn test_global_address_mask() {
-        let mut map = MemoryMap::new();
-        map.global_addr_mask = 1;
-        map.select_address(5);
-        assert_eq!(map.current_addr, 1);
-        map.global_addr_mask = 7;
-        map.select_address(5);
-        assert_eq!(map.current_addr, 5);
-        map.select_address(8);
-        assert_eq!(map.current_addr, 0);
+    pub fn on_read_u64<F>(&mut self, func: F) -> &mut MemoryMapEntry<A>
+    where
+        F: FnMut(A) -> u64 + Send + 'static,
+    {
+        self.set_read_u64(func);
+        self
+    }
+
+    pub fn on_read_u128<F>(&mut self, func: F) -> &mut MemoryMapEntry<A>
+    where
+        F: FnMut(A) -> u128 + Send + 'static,
+    {
+        self.set_read_u128(func);
+        self
+    }
+
+    pub fn on_write_u8<F>(&mut self, func: F) -> &mut MemoryMapEntry<A>
+    where
+        F: FnMut(A, u8) + Send + 'static,
+    {
+        self.set_write_u8(func);
+        self
+    }
+
+    pub fn on_write_u16<F>(&mut self, func: F) -> &mut MemoryMapEntry<A>
+    where
+        F: FnMut(A, u16) + Send + 'static,
+    {
+        self.set_write_u16(func);
+        self
+    }
+
+    pub fn on_write_u32<F>(&mut self, func: F) -> &mut MemoryMapEntry<A>
+    where
+        F: FnMut(A, u32) + Send + 'static,
+    {
+        self.set_write_u32(func);
+        self
+    }
+
+    pub fn on_write_u64<F>(&mut self, func: F) -> &mut MemoryMapEntry<A>
+    where
+        F: FnMut(A, u64) + Send + 'static,
+    {
+        self.set_write_u64(func);
+        self
+    }
+
+    pub fn on_write_u128<F>(&mut self, func: F) -> &mut MemoryMapEntry<A>
+    where
+        F: FnMut(A, u128) + Send + 'static,
+    {
+        self.set_write_u128(func);
+        self
+    }
+
+    pub fn on_uniform_read<F>(&mut self, func: F) -> &mut MemoryMapEntry<A>
+    where
+        F: FnMut(A, u8) -> u128 + Clone + Send + 'static,
+    {
+        self.set_uniform_read(func);
+        self
     }
-}
\ No newline at end of file
+
+    pub fn on_uniform_write<F>(&mut self, func: F) -> &mut MemoryMapEntry<A>
+    where
+        F: FnMut(A, u8, u128) + Clone + Send + 'static,
+    {
+        self.set_uniform_write(func);
+        self
+    }
+}
+
+impl<A: BusAddress> fmt::Debug for MemoryMapEntry<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MemoryMapEntry")
+            .field("start", &self.start)
+            .field("end", &self.end)
+            .field("entry_type", &self.entry_type)
+            .field("func_read_u8", &self.func_read_u8.is_some())
+            .field("func_read_u16", &self.func_read_u16.is_some())
+            .field("func_read_u32", &self.func_read_u32.is_some())
+            .field("func_read_u64", &self.func_read_u64.is_some())
+            .field("func_read_u128", &self.func_read_u128.is_some())
+            .field("func_write_u8", &self.func_write_u8.is_some())
+            .field("func_write_u16", &self.func_write_u16.is_some())
+            .field("func_write_u32", &self.func_write_u32.is_some())
+            .field("func_write_u64", &self.func_write_u64.is_some())
+            .field("func_write_u128", &self.func_write_u128.is_some())
+            .field("device", &self.device.is_some())
+            .field("addr_mask", &self.addr_mask)
+            .field("relative", &self.relative)
+            .field("alias", &self.alias)
+            .field("allow_width_synthesis", &self.allow_width_synthesis)
+            .field("granularity", &self.granularity)
+            .field("register", &self.register)
+            .field("name", &self.name)
+            .field("latency", &self.latency)
+            .field("contention", &self.contention.is_some())
+            .finish()
+    }
+}
+
+impl<A: BusAddress> Default for MemoryMapEntry<A> {
+    fn default() -> Self {
+        MemoryMapEntry {
+            start: A::ZERO,
+            end: A::MAX,
+            entry_type: MemoryMapEntryType::UnmappedLow,
+            func_read_u8: None,
+            func_read_u16: None,
+            func_read_u32: None,
+            func_read_u64: None,
+            func_read_u128: None,
+            func_write_u8: None,
+            func_write_u16: None,
+            func_write_u32: None,
+            func_write_u64: None,
+            func_write_u128: None,
+            device: None,
+            addr_mask: A::MAX,
+            read_mask: None,
+            write_mask: None,
+            mirror: None,
+            priority: 0,
+            relative: false,
+            alias: None,
+            allow_width_synthesis: true,
+            granularity: 1,
+            register: None,
+            name: None,
+            latency: 0,
+            contention: None,
+            reads: [0; 5],
+            writes: [0; 5],
+        }
+    }
+}
+
+/// Refuses bounds with `start` past `end`. A region of a single address has them equal.
+fn ordered_bounds<A: BusAddress>(start: A, end: A) -> Result<(), MemoryMapError> {
+    if start > end {
+        return Err(MemoryMapError::InvertedRegion {
+            start: start.to_u128(),
+            end: end.to_u128(),
+        });
+    }
+    Ok(())
+}
+
+fn check_bounds<A: BusAddress>(start: A, end: A) {
+    if start > end {
+        panic!(
+            "Memory region start {:#x} is past its end {:#x}.",
+            start, end
+        );
+    }
+}
+
+/// Byte order used when a multi-byte access is composed from narrower ones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+/// What happens to a multi-byte access whose bytes don't all belong to the same region.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CrossRegionPolicy {
+    /// Compose the access from narrower ones, each answered by its own region. The default.
+    #[default]
+    Split,
+    /// Refuse the access with `MemoryMapError::CrossesRegion`.
+    Fault,
+}
+
+/// What happens to the bytes of a multi-byte or block access that would lie past the top of the
+/// address space, the global address mask.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Carry on from address zero. The default.
+    #[default]
+    Wrap,
+    /// Stop at the top. Bytes past it read as the default entry's unmapped value and writes to
+    /// them are dropped.
+    Clamp,
+}
+
+/// `entries[0]` is the default entry answering for any address no region claims. The regions
+/// after it are kept sorted by `start` so lookups can binary search. Regions may overlap; the
+/// containing region with the highest priority wins, then the one with the greatest `start`,
+/// and among equal starts the most recently mapped one.
+pub struct MemoryMap<A: BusAddress = Address> {
+    entries: Vec<MemoryMapEntry<A>>,
+    /// Set whenever a region is handed out mutably, since its bounds may have changed.
+    needs_sort: bool,
+    /// Whether any two regions overlap, in which case lookups may need to look further back.
+    overlapping: bool,
+    /// Refuse to map regions that overlap an existing one.
+    reject_overlaps: bool,
+    /// Report accesses a region's type forbids instead of quietly ignoring them.
+    strict_permissions: bool,
+    strict_backing: bool,
+    current_addr: A,
+    global_addr_mask: A,
+    endianness: Endianness,
+    watchpoints: Vec<watch::Watchpoint<A>>,
+    next_watchpoint_id: usize,
+    trace: Option<RefCell<TraceHook<A>>>,
+    stats_enabled: bool,
+    timing_enabled: bool,
+    latency_cost: LatencyCost,
+    /// Cycles consumed by accesses while timing is enabled.
+    cycles: u64,
+    alignment: AlignmentPolicy,
+    cross_region: CrossRegionPolicy,
+    overflow: OverflowPolicy,
+    unmapped_read: Option<UnmappedReadProvider<A>>,
+    /// The value of the last completed access, read back from open bus regions.
+    bus_latch: u128,
+    protections: Vec<protect::Protection<A>>,
+    fault_handler: Option<FaultHandler>,
+    event_hook: Option<EventHook<A>>,
+    report_unmapped_writes: bool,
+    debug_alignment: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum MemoryMapError {
+    NoEntriesFound {
+        addr: Address,
+    },
+    /// The region at `addr` is mapped as accessible but has no delegate for a `width`-byte access.
+    MissingDelegate {
+        addr: Address,
+        width: u8,
+        /// The region's name, if it was given one.
+        region: Option<&'static str>,
+    },
+    /// A bank switch asked for `bank` but only `banks` banks exist.
+    BankOutOfRange {
+        bank: usize,
+        banks: usize,
+    },
+    /// A new region was refused because it overlaps the mapped region `start..=end`.
+    RegionOverlap {
+        start: Address,
+        end: Address,
+    },
+    /// A `width`-byte write of `data` to `addr` landed in unmapped space and was dropped. Only
+    /// handed to the fault handler, and only once reporting such writes is turned on.
+    UnmappedWrite {
+        addr: Address,
+        width: u8,
+        data: u128,
+    },
+    /// A region was given a `start` past its `end`.
+    InvertedRegion {
+        start: Address,
+        end: Address,
+    },
+    /// The writable region `start..=end` keeps its state outside the map, e.g. in delegates, so it
+    /// can't be part of a snapshot.
+    NotSnapshottable {
+        start: Address,
+        end: Address,
+    },
+    /// A snapshot holds state for `start..=end` but no matching region accepted it.
+    SnapshotMismatch {
+        start: Address,
+        end: Address,
+    },
+    /// Bytes handed to `Snapshot::from_bytes` aren't an encoded snapshot.
+    InvalidSnapshot,
+    /// Bytes handed to `RegionLayout::decode` aren't an encoded layout.
+    InvalidLayout,
+    /// The region at `addr` is read-only. Only reported by `try_fill`, `try_write_block` or in
+    /// strict mode.
+    WriteToReadOnly {
+        addr: Address,
+        region: Option<&'static str>,
+    },
+    /// The region at `addr` is write-only. Only reported in strict mode.
+    ReadFromWriteOnly {
+        addr: Address,
+        region: Option<&'static str>,
+    },
+    /// A `width`-byte access at `addr` isn't naturally aligned and the map refuses those.
+    Unaligned {
+        addr: Address,
+        width: u8,
+    },
+    /// A `width`-byte access at `addr` spans more than one region and the map refuses those.
+    CrossesRegion {
+        addr: Address,
+        width: u8,
+    },
+    /// The device at `addr` signalled a bus error for the access.
+    BusError {
+        addr: Address,
+    },
+    /// The byte at `addr` lacks the `needed` permissions.
+    Protection {
+        addr: Address,
+        needed: Permissions,
+    },
+    /// A write of `data` to the `width`-byte register at `addr` doesn't fit it. Only reported in
+    /// strict mode.
+    RegisterOverflow {
+        addr: Address,
+        width: u8,
+        data: u128,
+    },
+    /// The access at `addr` falls in the region starting at `region_start`, but past the
+    /// `region_len` bytes backing it. Only reported once the map checks backing bounds.
+    BackingOutOfBounds {
+        addr: Address,
+        region_start: Address,
+        region_len: usize,
+    },
+}
+
+/// Formats the region `name`, if any, to follow the word "region" in a message.
+struct RegionName(Option<&'static str>);
+
+impl fmt::Display for RegionName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(name) => write!(f, " '{}'", name),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for MemoryMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            MemoryMapError::NoEntriesFound { addr } => {
+                write!(f, "no region is mapped at {:#x}", addr)
+            }
+            MemoryMapError::MissingDelegate {
+                addr,
+                width,
+                region,
+            } => write!(
+                f,
+                "region{} has no handler for a {}-byte access at {:#x}",
+                RegionName(region),
+                width,
+                addr
+            ),
+            MemoryMapError::BankOutOfRange { bank, banks } => {
+                write!(f, "bank {} selected but only {} exist", bank, banks)
+            }
+            MemoryMapError::RegionOverlap { start, end } => {
+                write!(f, "overlaps the region at {:#x}-{:#x}", start, end)
+            }
+            MemoryMapError::UnmappedWrite { addr, width, data } => write!(
+                f,
+                "{}-byte write of {:#x} to unmapped address {:#x}",
+                width, data, addr
+            ),
+            MemoryMapError::InvertedRegion { start, end } => {
+                write!(f, "region start {:#x} is past its end {:#x}", start, end)
+            }
+            MemoryMapError::NotSnapshottable { start, end } => write!(
+                f,
+                "the region at {:#x}-{:#x} has no state to snapshot",
+                start, end
+            ),
+            MemoryMapError::SnapshotMismatch { start, end } => write!(
+                f,
+                "no region accepts the snapshot of {:#x}-{:#x}",
+                start, end
+            ),
+            MemoryMapError::InvalidSnapshot => write!(f, "not an encoded snapshot"),
+            MemoryMapError::InvalidLayout => write!(f, "not an encoded layout"),
+            MemoryMapError::WriteToReadOnly { addr, region } => write!(
+                f,
+                "write to read-only region{} at {:#x}",
+                RegionName(region),
+                addr
+            ),
+            MemoryMapError::ReadFromWriteOnly { addr, region } => write!(
+                f,
+                "read from write-only region{} at {:#x}",
+                RegionName(region),
+                addr
+            ),
+            MemoryMapError::Unaligned { addr, width } => {
+                write!(f, "unaligned {}-byte access at {:#x}", width, addr)
+            }
+            MemoryMapError::CrossesRegion { addr, width } => write!(
+                f,
+                "{}-byte access at {:#x} crosses a region boundary",
+                width, addr
+            ),
+            MemoryMapError::BusError { addr } => write!(f, "bus error at {:#x}", addr),
+            MemoryMapError::Protection { addr, needed } => {
+                write!(f, "{:?} permission missing at {:#x}", needed, addr)
+            }
+            MemoryMapError::RegisterOverflow { addr, width, data } => write!(
+                f,
+                "{:#x} doesn't fit the {}-byte register at {:#x}",
+                data, width, addr
+            ),
+            MemoryMapError::BackingOutOfBounds {
+                addr,
+                region_start,
+                region_len,
+            } => write!(
+                f,
+                "{:#x} lies past the {} bytes backing the region at {:#x}",
+                addr, region_len, region_start
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MemoryMapError {}
+
+/// Two mapped regions claiming some of the same addresses, each given as `(start, end)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Overlap<A: BusAddress = Address> {
+    pub first: (A, A),
+    pub second: (A, A),
+}
+
+impl<A: BusAddress> MemoryMap<A> {
+    fn addr(&self) -> A {
+        self.current_addr & self.global_addr_mask
+    }
+
+    /// `addr` advanced by `offset` bytes, or `None` if that runs past the top of the address space
+    /// while the overflow policy clamps.
+    fn advance(&self, addr: A, offset: u128) -> Option<A> {
+        let addr = addr & self.global_addr_mask;
+        if self.overflow == OverflowPolicy::Clamp
+            && offset > (self.global_addr_mask - addr).to_u128()
+        {
+            return None;
+        }
+        Some(addr.wrapping_add(A::from_u128(offset)) & self.global_addr_mask)
+    }
+
+    /// What a `width`-byte read past the top of the address space yields under `Clamp`.
+    fn past_top(&self, width: u8) -> u128 {
+        let data = match self.entries[0].entry_type {
+            MemoryMapEntryType::UnmappedHigh => u128::MAX,
+            MemoryMapEntryType::UnmappedOpenBus => self.bus_latch,
+            _ => 0,
+        };
+        if width == 16 {
+            data
+        } else {
+            data & ((1 << (width as u32 * 8)) - 1)
+        }
+    }
+
+    /// Runs `func` with `offset` added to the selected address, restoring the selection afterwards.
+    /// Returns `None` without running it if the address is past the top of the address space.
+    fn at_offset<T, F>(&mut self, offset: u8, func: F) -> Option<T>
+    where
+        F: FnOnce(&mut MemoryMap<A>) -> T,
+    {
+        let addr = self.advance(self.current_addr, offset as u128)?;
+        Some(self.at_address(addr, func))
+    }
+
+    /// Runs `func` with `addr` selected, restoring the selection afterwards.
+    fn at_address<T, F>(&mut self, addr: A, func: F) -> T
+    where
+        F: FnOnce(&mut MemoryMap<A>) -> T,
+    {
+        let selected = self.current_addr;
+        self.current_addr = addr & self.global_addr_mask;
+        let result = func(self);
+        self.current_addr = selected;
+        result
+    }
+
+    /// Joins two halves read from consecutive addresses according to the map's byte order.
+    fn join_halves(&self, first: u128, second: u128, half_bits: u32) -> u128 {
+        match self.endianness {
+            Endianness::Little => first | (second << half_bits),
+            Endianness::Big => (first << half_bits) | second,
+        }
+    }
+
+    /// Splits `data` into the halves to be written to consecutive addresses.
+    fn split_halves(&self, data: u128, half_bits: u32) -> (u128, u128) {
+        let low = data & ((1 << half_bits) - 1);
+        let high = data >> half_bits;
+        match self.endianness {
+            Endianness::Little => (low, high),
+            Endianness::Big => (high, low),
+        }
+    }
+}
+
+/// Returns the index of the entry that answers for the current address, falling back to the
+/// default entry when no region claims it.
+fn search_entries<A: BusAddress>(map: &mut MemoryMap<A>) -> usize {
+    if map.needs_sort {
+        map.sort_entries();
+    }
+    map.find_region(map.addr()).unwrap_or(0)
+}
+
+impl MemoryMap {
+    /// Creates a map with `Address`-wide addresses. Narrower buses can use e.g.
+    /// `MemoryMap::<u16>::default()` instead.
+    pub fn new() -> MemoryMap {
+        MemoryMap::default()
+    }
+}
+
+impl<A: BusAddress> MemoryMap<A> {
+    pub fn set_endianness(&mut self, endianness: Endianness) {
+        self.endianness = endianness;
+    }
+
+    /// The selected address, with the global address mask applied.
+    pub fn current_address(&self) -> A {
+        self.addr()
+    }
+
+    pub fn address_mask(&self) -> A {
+        self.global_addr_mask
+    }
+
+    /// Masks every address selected from now on, and the one selected already, which mirrors the
+    /// map across a bus with fewer address lines. Defaults to all ones.
+    ///
+    /// Multi-byte and block accesses step through addresses within the mask, so with a 24-bit
+    /// mask a `read_u16` at 0xffffff reads its second byte from 0. Under
+    /// [`OverflowPolicy::Clamp`] they stop at the top of the masked space instead.
+    pub fn set_address_mask(&mut self, mask: A) {
+        self.global_addr_mask = mask;
+        self.current_addr = self.current_addr & mask;
+    }
+
+    pub fn set_cross_region_policy(&mut self, policy: CrossRegionPolicy) {
+        self.cross_region = policy;
+    }
+
+    pub fn set_overflow_policy(&mut self, policy: OverflowPolicy) {
+        self.overflow = policy;
+    }
+
+    /// When set, mapping a region that overlaps an existing one is refused instead of letting the
+    /// newer region shadow part of the older one.
+    pub fn set_reject_overlaps(&mut self, reject: bool) {
+        self.reject_overlaps = reject;
+    }
+
+    /// By default writes to a `Read` region are dropped like writes to unmapped space, and reads
+    /// of a `Write` region with nothing to answer them see the open bus. In strict mode the
+    /// fallible API reports them as `WriteToReadOnly` and `ReadFromWriteOnly` instead, and
+    /// values too big for a narrow register as `RegisterOverflow`.
+    pub fn set_strict_permissions(&mut self, strict: bool) {
+        self.strict_permissions = strict;
+    }
+
+    /// [`Ram`] and [`Rom`] wrap addresses past the end of their buffers around, mirroring them
+    /// across a larger region. With strict backing on, the fallible API reports such accesses
+    /// as `BackingOutOfBounds` instead, to catch a region mapped bigger than its device or a
+    /// device given the wrong base. Off by default.
+    pub fn set_strict_backing(&mut self, strict: bool) {
+        self.strict_backing = strict;
+    }
+
+    /// Refuses a read of the entry at `index` if strict mode forbids it.
+    fn check_read(&self, index: usize, addr: A) -> Result<(), MemoryMapError> {
+        if self.strict_permissions && self.entries[index].entry_type == MemoryMapEntryType::Write {
+            return Err(MemoryMapError::ReadFromWriteOnly {
+                addr: addr.to_u128(),
+                region: self.entries[index].name,
+            });
+        }
+        self.check_backing(index, addr, AccessKind::Read)
+    }
+
+    /// Whether writes to the entry at `index` go nowhere because it is write-protected.
+    fn is_read_only(&self, index: usize) -> bool {
+        self.entries[index].entry_type == MemoryMapEntryType::Read
+    }
+
+    /// Refuses a write to the entry at `index` if strict mode forbids it.
+    fn check_write(&self, index: usize, addr: A) -> Result<(), MemoryMapError> {
+        if self.strict_permissions && self.entries[index].entry_type == MemoryMapEntryType::Read {
+            return Err(MemoryMapError::WriteToReadOnly {
+                addr: addr.to_u128(),
+                region: self.entries[index].name,
+            });
+        }
+        self.check_backing(index, addr, AccessKind::Write)
+    }
+
+    /// Refuses an access to `addr` in the entry at `index` that its device would have to wrap
+    /// into its buffer, if strict backing asks for that.
+    fn check_backing(&self, index: usize, addr: A, kind: AccessKind) -> Result<(), MemoryMapError> {
+        let entry = &self.entries[index];
+        let backing = match entry.device.as_ref() {
+            Some(device) if self.strict_backing => device.backing(),
+            _ => None,
+        };
+        let (base, len) = match backing {
+            Some(backing) => backing,
+            None => return Ok(()),
+        };
+        let device_addr = entry.handler_addr(addr, kind).to_u128();
+        if device_addr.wrapping_sub(base) >= len as u128 {
+            return Err(MemoryMapError::BackingOutOfBounds {
+                addr: addr.to_u128(),
+                region_start: entry.start.to_u128(),
+                region_len: len,
+            });
+        }
+        Ok(())
+    }
+
+    /// Registers a region covering `start..=end`, with `end` inclusive, and returns it so
+    /// delegates can be attached.
+    ///
+    /// Panics if the region overlaps an existing one while overlaps are rejected.
+    pub fn map_region(
+        &mut self,
+        start: A,
+        end: A,
+        entry_type: MemoryMapEntryType,
+    ) -> &mut MemoryMapEntry<A> {
+        check_bounds(start, end);
+        match self.try_map_region(start, end, entry_type) {
+            Ok(entry) => entry,
+            Err(err) => panic!("Could not map {:#x}-{:#x}: {:?}", start, end, err),
+        }
+    }
+
+    pub fn try_map_region(
+        &mut self,
+        start: A,
+        end: A,
+        entry_type: MemoryMapEntryType,
+    ) -> Result<&mut MemoryMapEntry<A>, MemoryMapError> {
+        ordered_bounds(start, end)?;
+        let mut entry = MemoryMapEntry::default();
+        entry.set_bounds(start, end);
+        entry.set_entry_type(entry_type);
+        self.check_overlap(start, end, None)?;
+        self.entries.push(entry);
+        self.needs_sort = true;
+        Ok(self.entries.last_mut().unwrap())
+    }
+
+    /// While overlaps are rejected, refuses `start..=end` if it overlaps a region other than the
+    /// one at `skip`.
+    fn check_overlap(&self, start: A, end: A, skip: Option<usize>) -> Result<(), MemoryMapError> {
+        if !self.reject_overlaps {
+            return Ok(());
+        }
+        let existing = self
+            .entries
+            .iter()
+            .enumerate()
+            .skip(1)
+            .find(|&(i, existing)| {
+                Some(i) != skip && existing.start <= end && existing.end >= start
+            });
+        match existing {
+            Some((_, existing)) => Err(MemoryMapError::RegionOverlap {
+                start: existing.start.to_u128(),
+                end: existing.end.to_u128(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// The index of the region starting at `start`, preferring the most recently mapped one.
+    fn region_starting_at(&mut self, start: A) -> Option<usize> {
+        if self.needs_sort {
+            self.sort_entries();
+        }
+        let after = self.entries[1..].partition_point(|entry| entry.start <= start);
+        if after > 0 && self.entries[after].start == start {
+            Some(after)
+        } else {
+            None
+        }
+    }
+
+    /// Removes the region starting at `start` and returns it, so the addresses it covered fall
+    /// through to whatever lies beneath. Among regions sharing a start, the most recently mapped
+    /// one goes.
+    pub fn unmap_region(&mut self, start: A) -> Option<MemoryMapEntry<A>> {
+        let index = self.region_starting_at(start)?;
+        let entry = self.entries.remove(index);
+        // Removal keeps the order, but an overlap may have gone with the region.
+        self.sort_entries();
+        Some(entry)
+    }
+
+    /// Moves the region starting at `old_start` to cover `new_start..=new_end`, keeping its
+    /// handlers. Fails with `NoEntriesFound` if no region starts at `old_start`.
+    pub fn remap_region(
+        &mut self,
+        old_start: A,
+        new_start: A,
+        new_end: A,
+    ) -> Result<&mut MemoryMapEntry<A>, MemoryMapError> {
+        let index = self
+            .region_starting_at(old_start)
+            .ok_or(MemoryMapError::NoEntriesFound {
+                addr: old_start.to_u128(),
+            })?;
+        ordered_bounds(new_start, new_end)?;
+        self.check_overlap(new_start, new_end, Some(index))?;
+        self.entries[index].set_bounds(new_start, new_end);
+        self.needs_sort = true;
+        Ok(&mut self.entries[index])
+    }
+
+    /// The type of the region starting at `start`, if one does.
+    pub fn region_type(&mut self, start: A) -> Option<MemoryMapEntryType> {
+        let index = self.region_starting_at(start)?;
+        Some(self.entries[index].entry_type)
+    }
+
+    /// Changes the type of the region starting at `start`, e.g. to lock a shadowed ROM once the
+    /// boot code has filled it: a `ReadWrite` region made `Read` drops writes from then on, or
+    /// refuses them in strict mode. Fails with `NoEntriesFound` if no region starts at `start`.
+    pub fn set_region_type(
+        &mut self,
+        start: A,
+        entry_type: MemoryMapEntryType,
+    ) -> Result<(), MemoryMapError> {
+        let index = self
+            .region_starting_at(start)
+            .ok_or(MemoryMapError::NoEntriesFound {
+                addr: start.to_u128(),
+            })?;
+        self.entries[index].set_entry_type(entry_type);
+        Ok(())
+    }
+
+    /// Sets the priority of the region starting at `start`, e.g. lowering that of a boot ROM
+    /// overlay to reveal the RAM beneath. Fails with `NoEntriesFound` if no region starts there.
+    pub fn set_region_priority(&mut self, start: A, priority: i32) -> Result<(), MemoryMapError> {
+        let index = self
+            .region_starting_at(start)
+            .ok_or(MemoryMapError::NoEntriesFound {
+                addr: start.to_u128(),
+            })?;
+        self.entries[index].set_priority(priority);
+        Ok(())
+    }
+
+    /// Mirrors whatever answers at `target_start` onwards into `alias_start..=alias_end`, so an
+    /// access at `addr` in the alias lands on `target_start + (addr - alias_start)`. Both windows
+    /// see the same data. An alias must not lead back into itself.
+    pub fn add_alias(&mut self, alias_start: A, alias_end: A, target_start: A) {
+        let entry = self.map_region(alias_start, alias_end, MemoryMapEntryType::ReadWrite);
+        entry.alias = Some(target_start);
+    }
+
+    /// Finds the entry answering for `addr` and the last of the consecutive addresses from `addr`
+    /// it answers for before another region takes over or the masked address space ends.
+    fn run_at(&mut self, addr: A) -> (usize, A) {
+        if self.needs_sort {
+            self.sort_entries();
+        }
+        let index = self.find_region(addr).unwrap_or(0);
+        let regions = &self.entries[1..];
+        let next = regions.partition_point(|entry| entry.start <= addr);
+        let mut last = self.global_addr_mask;
+        if index != 0 {
+            last = last.min(self.entries[index].end);
+        }
+        if let Some(entry) = regions.get(next) {
+            last = last.min(entry.start - A::ONE);
+        }
+        (index, last)
+    }
+
+    /// How many of the `remaining` bytes from `addr` fit in a run ending at `last`.
+    fn run_len(addr: A, last: A, remaining: usize) -> usize {
+        let len = (last - addr).to_u128().saturating_add(1);
+        len.min(remaining as u128) as usize
+    }
+
+    /// Fills `buf` from consecutive addresses starting at `start`, resolving each region once
+    /// rather than once per byte. Panics on a broken map, like `read_u8`.
+    ///
+    /// Returns how many bytes came from readable regions. The rest of `buf` holds what unmapped
+    /// space answered, so a count short of its length means the transfer ran into a gap.
+    pub fn read_block(&mut self, start: A, buf: &mut [u8]) -> usize {
+        self.try_read_block(start, buf)
+            .expect("Your memory map is broken. Please fix it!")
+    }
+
+    /// Like [`MemoryMap::read_block`], but stops at the first byte that can't be read and returns
+    /// the error instead of panicking. The bytes before it have been read.
+    pub fn try_read_block(&mut self, start: A, buf: &mut [u8]) -> Result<usize, MemoryMapError> {
+        let mut done = 0;
+        let mut transferred = 0;
+        while done < buf.len() {
+            let addr = match self.advance(start, done as u128) {
+                Some(addr) => addr,
+                None => {
+                    let past_top = self.past_top(1) as u8;
+                    buf[done..].fill(past_top);
+                    return Ok(transferred);
+                }
+            };
+            let (index, last) = self.run_at(addr);
+            let len = Self::run_len(addr, last, buf.len() - done);
+            if self.entries[index].is_readable() {
+                transferred += len;
+            }
+            for (i, byte) in buf[done..done + len].iter_mut().enumerate() {
+                let addr = addr + A::from_u128(i as u128);
+                *byte = self.read_byte_from(index, addr)?;
+                if self.accounting() {
+                    self.count_access_in(index, addr, 1, AccessKind::Read);
+                }
+                self.finish_access(addr, 1, AccessKind::Read, *byte as u128, AccessOrigin::Dma);
+            }
+            done += len;
+        }
+        Ok(transferred)
+    }
+
+    /// Writes `data` to consecutive addresses starting at `start`, resolving each region once
+    /// rather than once per byte. Broken writes are dropped, like `write_u8`.
+    ///
+    /// Returns how many bytes landed in writable regions, so a count short of the length of
+    /// `data` means some went to unmapped or read-only space or were dropped.
+    pub fn write_block(&mut self, start: A, data: &[u8]) -> usize {
+        let mut done = 0;
+        let mut transferred = 0;
+        while done < data.len() {
+            let addr = match self.advance(start, done as u128) {
+                Some(addr) => addr,
+                None => return transferred,
+            };
+            let (index, last) = self.run_at(addr);
+            let len = Self::run_len(addr, last, data.len() - done);
+            for (i, byte) in data[done..done + len].iter().enumerate() {
+                if self.write_byte_in(index, addr + A::from_u128(i as u128), *byte) {
+                    transferred += 1;
+                }
+            }
+            done += len;
+        }
+        transferred
+    }
+
+    /// Like [`MemoryMap::write_block`], but stops at the first byte that can't be written and
+    /// returns an error instead of dropping the rest: `NoEntriesFound` for unmapped space,
+    /// `WriteToReadOnly` for a read-only region, or whatever the write itself failed with. The
+    /// bytes before it have been written.
+    ///
+    /// A block running past the top of the address space stops there, returning how many bytes
+    /// were written.
+    pub fn try_write_block(&mut self, start: A, data: &[u8]) -> Result<usize, MemoryMapError> {
+        let mut done = 0;
+        while done < data.len() {
+            let addr = match self.advance(start, done as u128) {
+                Some(addr) => addr,
+                None => return Ok(done),
+            };
+            let (index, last) = self.run_at(addr);
+            if index == 0 {
+                return Err(MemoryMapError::NoEntriesFound {
+                    addr: addr.to_u128(),
+                });
+            }
+            if !self.entries[index].is_writable() {
+                return Err(MemoryMapError::WriteToReadOnly {
+                    addr: addr.to_u128(),
+                    region: self.entries[index].name,
+                });
+            }
+            let len = Self::run_len(addr, last, data.len() - done);
+            for (i, byte) in data[done..done + len].iter().enumerate() {
+                self.try_write_byte_in(index, addr + A::from_u128(i as u128), *byte)?;
+            }
+            done += len;
+        }
+        Ok(done)
+    }
+
+    /// Writes `src` through a scatter list, as a DMA controller working through its
+    /// descriptors would: each `(addr, len)` destination takes the next `len` bytes, written
+    /// like [`MemoryMap::write_block`] writes them. Descriptors past the end of `src` get what
+    /// is left, if anything.
+    ///
+    /// Returns how many bytes landed in writable regions across all destinations.
+    pub fn dma_scatter(&mut self, src: &[u8], descriptors: &[(A, usize)]) -> usize {
+        let mut rest = src;
+        let mut transferred = 0;
+        for &(addr, len) in descriptors {
+            let (chunk, after) = rest.split_at(len.min(rest.len()));
+            transferred += self.write_block(addr, chunk);
+            rest = after;
+        }
+        transferred
+    }
+
+    /// Writes one byte of a block operation to the entry at `index`, dropping it if it's broken.
+    /// Returns whether a writable region took it.
+    fn write_byte_in(&mut self, index: usize, addr: A, data: u8) -> bool {
+        self.try_write_byte_in(index, addr, data).is_ok() && self.entries[index].is_writable()
+    }
+
+    fn try_write_byte_in(&mut self, index: usize, addr: A, data: u8) -> Result<(), MemoryMapError> {
+        self.write_byte_to(index, addr, data)?;
+        if self.accounting() {
+            self.count_access_in(index, addr, 1, AccessKind::Write);
+        }
+        self.finish_access(addr, 1, AccessKind::Write, data as u128, AccessOrigin::Dma);
+        Ok(())
+    }
+
+    /// Reads one byte from the entry at `index`, following aliases and the unmapped read policy.
+    fn read_byte_from(&mut self, index: usize, addr: A) -> Result<u8, MemoryMapError> {
+        self.check_read(index, addr)?;
+        if let Some(target) = self.entries[index].alias_target(addr) {
+            return self.at_address(target, |map| map.read_width(1).map(|data| data as u8));
+        }
+        if let Some(data) = self.read_unmapped(index, addr, 1) {
+            return Ok(data as u8);
+        }
+        if let Some(data) = self.read_granule(index, addr, 1)? {
+            return Ok(data as u8);
+        }
+        self.entries[index].read_byte(addr)
+    }
+
+    /// Writes one byte to the entry at `index`, following aliases.
+    fn write_byte_to(&mut self, index: usize, addr: A, data: u8) -> Result<(), MemoryMapError> {
+        self.check_write(index, addr)?;
+        if let Some(target) = self.entries[index].alias_target(addr) {
+            return self.at_address(target, |map| map.write_width(1, data as u128));
+        }
+        if self.drops_unmapped_write(index, addr, 1, data as u128) || self.is_read_only(index) {
+            return Ok(());
+        }
+        if self.write_granule(index, addr, 1, data as u128)? {
+            return Ok(());
+        }
+        self.entries[index].write_byte(addr, data)
+    }
+
+    /// Splits `start..=end` into `(index, first, last)` runs each answered by a single entry.
+    fn runs(&mut self, start: A, end: A) -> Vec<(usize, A, A)> {
+        let mut runs = Vec::new();
+        let mut addr = start;
+        loop {
+            let (index, last) = self.run_at(addr);
+            let last = last.min(end);
+            runs.push((index, addr, last));
+            if last == end {
+                return runs;
+            }
+            addr = last + A::ONE;
+        }
+    }
+
+    /// Splits `start..=end` into the runs its masked addresses fall in, wrapping round the top
+    /// of the masked space like block accesses do.
+    fn masked_runs(&mut self, start: A, end: A) -> Vec<(usize, A, A)> {
+        let mask = self.global_addr_mask;
+        if (end - start).to_u128() >= mask.to_u128() {
+            return self.runs(A::ZERO, mask);
+        }
+        let (start, end) = (start & mask, end & mask);
+        if start <= end {
+            return self.runs(start, end);
+        }
+        let mut runs = self.runs(start, mask);
+        runs.extend(self.runs(A::ZERO, end));
+        runs
+    }
+
+    /// Sets every byte in `start..=end` that lies in a writable region to `value`, skipping
+    /// unmapped and read-only parts. The addresses are masked like any other, so a range that
+    /// runs past the top of the masked space wraps round to its bottom.
+    ///
+    /// Panics if `start` is past `end`.
+    pub fn fill(&mut self, start: A, end: A, value: u8) {
+        check_bounds(start, end);
+        for (index, first, last) in self.masked_runs(start, end) {
+            if !self.entries[index].is_writable() {
+                continue;
+            }
+            let mut addr = first;
+            loop {
+                self.write_byte_in(index, addr, value);
+                if addr == last {
+                    break;
+                }
+                addr = addr + A::ONE;
+            }
+        }
+    }
+
+    /// Like `fill`, but refuses to write anything if part of the range is unmapped or read-only,
+    /// reporting the first such address, and fails with `InvertedRegion` if `start` is past
+    /// `end`.
+    pub fn try_fill(&mut self, start: A, end: A, value: u8) -> Result<(), MemoryMapError> {
+        ordered_bounds(start, end)?;
+        for (index, first, _) in self.masked_runs(start, end) {
+            let addr = first.to_u128();
+            if index == 0 {
+                return Err(MemoryMapError::NoEntriesFound { addr });
+            }
+            if !self.entries[index].is_writable() {
+                return Err(MemoryMapError::WriteToReadOnly {
+                    addr,
+                    region: self.entries[index].name,
+                });
+            }
+        }
+        self.fill(start, end, value);
+        Ok(())
+    }
+
+    /// Reports every pair of mapped regions whose ranges overlap. Adjacent regions are fine.
+    ///
+    /// Inverted regions aren't reported, as there can't be any: mapping a region and
+    /// [`MemoryMapEntry::set_bounds`] both refuse a start past the end.
+    pub fn validate(&self) -> Result<(), Vec<Overlap<A>>> {
+        let regions: Vec<_> = self.regions().collect();
+        let mut overlaps = Vec::new();
+        for (i, &(start, end, _)) in regions.iter().enumerate() {
+            for &(other_start, other_end, _) in &regions[i + 1..] {
+                if other_start > end {
+                    break;
+                }
+                overlaps.push(Overlap {
+                    first: (start, end),
+                    second: (other_start, other_end),
+                });
+            }
+        }
+        if overlaps.is_empty() {
+            Ok(())
+        } else {
+            Err(overlaps)
+        }
+    }
+
+    /// The number of mapped regions, aliases included. Like [`MemoryMap::regions`] this leaves out
+    /// the default entry answering for unmapped addresses, so a fresh map has none.
+    pub fn len(&self) -> usize {
+        self.entries.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the map to the state `new` leaves it in: every region is unmapped and settings,
+    /// watchpoints and hooks are back to their defaults. Devices, RAM included, are dropped along
+    /// with their regions rather than zeroed.
+    pub fn clear(&mut self) {
+        *self = MemoryMap::default();
+    }
+
+    /// Lists the `(start, end, type)` of every mapped region in address order. The default entry
+    /// answering for unmapped addresses is not included.
+    pub fn regions(&self) -> impl Iterator<Item = (A, A, MemoryMapEntryType)> {
+        let mut regions: Vec<_> = self.entries[1..]
+            .iter()
+            .map(|entry| (entry.start, entry.end, entry.entry_type))
+            .collect();
+        if self.needs_sort {
+            regions.sort_by_key(|&(start, _, _)| start);
+        }
+        regions.into_iter()
+    }
+
+    /// The index of the region answering for `addr`, after the address mask, for finding a
+    /// region by address and then reconfiguring it through [`MemoryMap::entry_mut`]. Regions are
+    /// numbered from 0 in address order, like [`MemoryMap::regions`] lists them, and the index
+    /// stays valid until a region is mapped, removed or given new bounds.
+    pub fn entry_index(&mut self, addr: A) -> Option<usize> {
+        if self.needs_sort {
+            self.sort_entries();
+        }
+        self.find_region(addr & self.global_addr_mask)
+            .map(|index| index - 1)
+    }
+
+    /// The region numbered `index` by [`MemoryMap::entry_index`].
+    pub fn entry_mut(&mut self, index: usize) -> Option<&mut MemoryMapEntry<A>> {
+        let entry = self.entries[1..].get_mut(index)?;
+        // The caller may move the region with `set_bounds`.
+        self.needs_sort = true;
+        Some(entry)
+    }
+
+    /// Returns the index of the region containing `addr`, or `None` if no region does.
+    fn find_region(&self, addr: A) -> Option<usize> {
+        let regions = &self.entries[1..];
+        let contains = |entry: &MemoryMapEntry<A>| entry.start <= addr && entry.end >= addr;
+        if self.needs_sort {
+            // Bounds may have changed since the last sort, so scan with the same precedence.
+            return regions
+                .iter()
+                .enumerate()
+                .filter(|(_, entry)| contains(entry))
+                .max_by_key(|(i, entry)| (entry.priority, entry.start, *i))
+                .map(|(i, _)| i + 1);
+        }
+        let candidates = regions.partition_point(|entry| entry.start <= addr);
+        let mut found: Option<usize> = None;
+        // Candidates come in order of precedence, so only a higher priority beats the first hit.
+        for (i, entry) in regions[..candidates].iter().enumerate().rev() {
+            let beats = found.is_none_or(|found| entry.priority > regions[found].priority);
+            if entry.end >= addr && beats {
+                found = Some(i);
+            }
+            if !self.overlapping {
+                break;
+            }
+        }
+        found.map(|i| i + 1)
+    }
+
+    /// The region that answers for `addr` once aliases are followed, or `None` if no region
+    /// claims it or the alias's target.
+    fn resolve_alias(&self, addr: A) -> Option<usize> {
+        let addr = addr & self.global_addr_mask;
+        let index = self.find_region(addr)?;
+        match self.entries[index].alias_target(addr) {
+            Some(target) => self.resolve_alias(target),
+            None => Some(index),
+        }
+    }
+
+    fn sort_entries(&mut self) {
+        // The sort is stable, so regions sharing a start stay in the order they were mapped.
+        self.entries[1..].sort_by_key(|entry| entry.start);
+        self.overlapping = false;
+        let mut highest_end = None;
+        for entry in &self.entries[1..] {
+            if highest_end.is_some_and(|end| entry.start <= end) {
+                self.overlapping = true;
+            }
+            highest_end = highest_end.max(Some(entry.end));
+        }
+        self.needs_sort = false;
+    }
+}
+
+impl<A: BusAddress> Default for MemoryMap<A> {
+    fn default() -> Self {
+        MemoryMap {
+            entries: vec![MemoryMapEntry::default()],
+            needs_sort: false,
+            overlapping: false,
+            reject_overlaps: false,
+            strict_permissions: false,
+            strict_backing: false,
+            current_addr: A::ZERO,
+            global_addr_mask: A::MAX,
+            endianness: Endianness::Little,
+            watchpoints: Vec::new(),
+            next_watchpoint_id: 0,
+            trace: None,
+            stats_enabled: false,
+            timing_enabled: false,
+            latency_cost: latency::per_access,
+            cycles: 0,
+            alignment: AlignmentPolicy::Allow,
+            cross_region: CrossRegionPolicy::Split,
+            overflow: OverflowPolicy::Wrap,
+            unmapped_read: None,
+            bus_latch: 0,
+            protections: Vec::new(),
+            fault_handler: None,
+            event_hook: None,
+            report_unmapped_writes: false,
+            debug_alignment: false,
+        }
+    }
+}
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// Runs the hooks that observe a completed access.
+    fn finish_access(
+        &mut self,
+        addr: A,
+        width: u8,
+        kind: AccessKind,
+        data: u128,
+        origin: AccessOrigin,
+    ) {
+        self.bus_latch = data;
+        if !self.watchpoints.is_empty() {
+            self.fire_watchpoints(addr, width, kind, data);
+        }
+        self.trace_access(addr, width, kind, data, origin);
+    }
+
+    fn try_read_width(&mut self, width: u8) -> Result<u128, MemoryMapError> {
+        self.try_read_width_as(width, Permissions::READ)
+    }
+
+    /// Performs a read needing the `needed` permissions, `EXECUTE` for a fetch and `READ` for
+    /// anything else.
+    fn try_read_width_as(
+        &mut self,
+        width: u8,
+        needed: Permissions,
+    ) -> Result<u128, MemoryMapError> {
+        let result = self.read_checked(width, needed);
+        self.report_access(width, AccessKind::Read, result.as_ref().err());
+        result
+    }
+
+    fn read_checked(&mut self, width: u8, needed: Permissions) -> Result<u128, MemoryMapError> {
+        self.check_permissions(width, needed)?;
+        let data = match self.align_read(width) {
+            Some(result) => result?,
+            None => self.read_width(width)?,
+        };
+        if self.accounting() {
+            self.count_access(width, AccessKind::Read);
+        }
+        self.finish_access(
+            self.current_addr,
+            width,
+            AccessKind::Read,
+            data,
+            AccessOrigin::Guest,
+        );
+        Ok(data)
+    }
+
+    fn try_write_width(&mut self, width: u8, data: u128) -> Result<(), MemoryMapError> {
+        let result = self.write_checked(width, data);
+        self.report_access(width, AccessKind::Write, result.as_ref().err());
+        result
+    }
+
+    fn write_checked(&mut self, width: u8, data: u128) -> Result<(), MemoryMapError> {
+        self.check_permissions(width, Permissions::WRITE)?;
+        match self.align_write(width, data) {
+            Some(result) => result?,
+            None => self.write_width(width, data)?,
+        }
+        if self.accounting() {
+            self.count_access(width, AccessKind::Write);
+        }
+        self.finish_access(
+            self.current_addr,
+            width,
+            AccessKind::Write,
+            data,
+            AccessOrigin::Guest,
+        );
+        Ok(())
+    }
+
+    /// Finds the entry answering for a `width`-byte access at the selected address and whether
+    /// the whole access lies within it, applying the cross-region policy if it doesn't.
+    fn resolve(&mut self, width: u8) -> Result<(usize, bool), MemoryMapError> {
+        if width == 1 {
+            return Ok((search_entries(self), true));
+        }
+        let addr = self.current_addr;
+        let (index, last) = self.run_at(addr);
+        if (last - addr).to_u128() >= width as u128 - 1 {
+            return Ok((index, true));
+        }
+        match self.cross_region {
+            CrossRegionPolicy::Split => Ok((index, false)),
+            CrossRegionPolicy::Fault => Err(MemoryMapError::CrossesRegion {
+                addr: addr.to_u128(),
+                width,
+            }),
+        }
+    }
+
+    /// Performs a `width`-byte read, composing it from two halves when nothing in the region
+    /// handles that width directly or the access runs into another region.
+    fn read_width(&mut self, width: u8) -> Result<u128, MemoryMapError> {
+        let (index, contained) = self.resolve(width)?;
+        self.read_width_in(index, contained, width)
+    }
+
+    /// Performs a `width`-byte read already resolved to the entry at `index`.
+    fn read_width_in(
+        &mut self,
+        index: usize,
+        contained: bool,
+        width: u8,
+    ) -> Result<u128, MemoryMapError> {
+        let addr = self.current_addr;
+        if width == 1 {
+            return self.read_byte_from(index, addr).map(|data| data as u128);
+        }
+        self.check_read(index, addr)?;
+        if contained {
+            if let Some(target) = self.entries[index].alias_target(addr) {
+                return self.at_address(target, |map| map.read_width(width));
+            }
+            if let Some(data) = self.read_unmapped(index, addr, width) {
+                return Ok(data);
+            }
+            if width < self.entries[index].granularity {
+                if let Some(data) = self.read_granule(index, addr, width)? {
+                    return Ok(data);
+                }
+            } else if let Some(data) = self.entries[index].dispatch_read(addr, width)? {
+                return Ok(data);
+            }
+            if !self.entries[index].allow_width_synthesis {
+                return Err(MemoryMapError::MissingDelegate {
+                    addr: addr.to_u128(),
+                    width,
+                    region: self.entries[index].name,
+                });
+            }
+        }
+        let half = width / 2;
+        let first = self.read_width(half)?;
+        let second = match self.at_offset(half, |map| map.read_width(half)) {
+            Some(second) => second?,
+            None => self.past_top(half),
+        };
+        Ok(self.join_halves(first, second, half as u32 * 8))
+    }
+
+    /// Performs a `width`-byte write, splitting it into two halves when nothing in the region
+    /// handles that width directly or the access runs into another region.
+    fn write_width(&mut self, width: u8, data: u128) -> Result<(), MemoryMapError> {
+        let (index, contained) = self.resolve(width)?;
+        self.write_width_in(index, contained, width, data)
+    }
+
+    /// Performs a `width`-byte write already resolved to the entry at `index`.
+    fn write_width_in(
+        &mut self,
+        index: usize,
+        contained: bool,
+        width: u8,
+        data: u128,
+    ) -> Result<(), MemoryMapError> {
+        let addr = self.current_addr;
+        if width == 1 {
+            return self.write_byte_to(index, addr, data as u8);
+        }
+        self.check_write(index, addr)?;
+        if contained {
+            if let Some(target) = self.entries[index].alias_target(addr) {
+                return self.at_address(target, |map| map.write_width(width, data));
+            }
+            if self.drops_unmapped_write(index, addr, width, data) || self.is_read_only(index) {
+                return Ok(());
+            }
+            if let Some((register_width, data)) = self.narrow_register_write(index, width, data)? {
+                return self.write_width_in(index, true, register_width, data);
+            }
+            let handled = if width < self.entries[index].granularity {
+                self.write_granule(index, addr, width, data)?
+            } else {
+                self.entries[index].dispatch_write(addr, width, data)?
+            };
+            if handled {
+                return Ok(());
+            }
+            let entry = &self.entries[index];
+            if !entry.allow_width_synthesis && entry.is_writable() {
+                return Err(MemoryMapError::MissingDelegate {
+                    addr: addr.to_u128(),
+                    width,
+                    region: entry.name,
+                });
+            }
+        }
+        let half = width / 2;
+        let (first, second) = self.split_halves(data, half as u32 * 8);
+        self.write_width(half, first)?;
+        self.at_offset(half, |map| map.write_width(half, second))
+            .unwrap_or(Ok(()))
+    }
+
+    pub fn try_read<T: BusValue>(&mut self) -> Result<T, MemoryMapError> {
+        self.try_read_width(T::WIDTH).map(T::narrow)
+    }
+
+    pub fn try_write<T: BusValue>(&mut self, data: T) -> Result<(), MemoryMapError> {
+        self.try_write_width(T::WIDTH, data.widen())
+    }
+
+    /// Fetches an instruction, which needs `EXECUTE` rather than `READ` permission but is
+    /// otherwise an ordinary read.
+    pub fn try_fetch<T: BusValue>(&mut self) -> Result<T, MemoryMapError> {
+        self.try_read_width_as(T::WIDTH, Permissions::EXECUTE)
+            .map(T::narrow)
+    }
+
+    /// Performs a read for the infallible interface, needing the `needed` permissions.
+    fn read_as<T: BusValue>(&mut self, needed: Permissions) -> T {
+        let result = self.try_read_width_as(T::WIDTH, needed);
+        T::narrow(self.unwrap_read(result, T::WIDTH))
+    }
+
+    pub fn try_read_u8(&mut self) -> Result<u8, MemoryMapError> {
+        self.try_read()
+    }
+
+    pub fn try_read_u16(&mut self) -> Result<u16, MemoryMapError> {
+        self.try_read()
+    }
+
+    pub fn try_read_u32(&mut self) -> Result<u32, MemoryMapError> {
+        self.try_read()
+    }
+
+    pub fn try_read_u64(&mut self) -> Result<u64, MemoryMapError> {
+        self.try_read()
+    }
+
+    pub fn try_read_u128(&mut self) -> Result<u128, MemoryMapError> {
+        self.try_read()
+    }
+
+    pub fn try_fetch_u8(&mut self) -> Result<u8, MemoryMapError> {
+        self.try_fetch()
+    }
+
+    pub fn try_fetch_u16(&mut self) -> Result<u16, MemoryMapError> {
+        self.try_fetch()
+    }
+
+    pub fn try_fetch_u32(&mut self) -> Result<u32, MemoryMapError> {
+        self.try_fetch()
+    }
+
+    pub fn try_fetch_u64(&mut self) -> Result<u64, MemoryMapError> {
+        self.try_fetch()
+    }
+
+    pub fn try_fetch_u128(&mut self) -> Result<u128, MemoryMapError> {
+        self.try_fetch()
+    }
+
+    pub fn try_write_u8(&mut self, data: u8) -> Result<(), MemoryMapError> {
+        self.try_write(data)
+    }
+
+    pub fn try_write_u16(&mut self, data: u16) -> Result<(), MemoryMapError> {
+        self.try_write(data)
+    }
+
+    pub fn try_write_u32(&mut self, data: u32) -> Result<(), MemoryMapError> {
+        self.try_write(data)
+    }
+
+    pub fn try_write_u64(&mut self, data: u64) -> Result<(), MemoryMapError> {
+        self.try_write(data)
+    }
+
+    pub fn try_write_u128(&mut self, data: u128) -> Result<(), MemoryMapError> {
+        self.try_write(data)
+    }
+}
+
+/// The infallible accessors panic on a broken map when reading and drop broken writes, matching
+/// how unmapped writes already behave. Use the `try_` variants to observe the error instead.
+impl<A: BusAddress> Bus<A> for MemoryMap<A> {
+    fn select_address(&mut self, addr: A) {
+        self.current_addr = addr & self.global_addr_mask;
+    }
+
+    /// A memory map handles the addresses claimed by one of its regions.
+    fn handles(&self, addr: A) -> bool {
+        self.find_region(addr & self.global_addr_mask).is_some()
+    }
+
+    fn read_u8(&mut self) -> u8 {
+        self.read_as(Permissions::READ)
+    }
+
+    fn read_u16(&mut self) -> u16 {
+        self.read_as(Permissions::READ)
+    }
+
+    fn read_u32(&mut self) -> u32 {
+        self.read_as(Permissions::READ)
+    }
+
+    fn read_u64(&mut self) -> u64 {
+        self.read_as(Permissions::READ)
+    }
+
+    fn read_u128(&mut self) -> u128 {
+        self.read_as(Permissions::READ)
+    }
+
+    fn write_u8(&mut self, data: u8) {
+        self.try_write_u8(data).ok();
+    }
+
+    fn write_u16(&mut self, data: u16) {
+        self.try_write_u16(data).ok();
+    }
+
+    fn write_u32(&mut self, data: u32) {
+        self.try_write_u32(data).ok();
+    }
+
+    fn write_u64(&mut self, data: u64) {
+        self.try_write_u64(data).ok();
+    }
+
+    fn write_u128(&mut self, data: u128) {
+        self.try_write_u128(data).ok();
+    }
+
+    fn fetch_u8(&mut self) -> u8 {
+        self.read_as(Permissions::EXECUTE)
+    }
+
+    fn fetch_u16(&mut self) -> u16 {
+        self.read_as(Permissions::EXECUTE)
+    }
+
+    fn fetch_u32(&mut self) -> u32 {
+        self.read_as(Permissions::EXECUTE)
+    }
+
+    fn fetch_u64(&mut self) -> u64 {
+        self.read_as(Permissions::EXECUTE)
+    }
+
+    fn fetch_u128(&mut self) -> u128 {
+        self.read_as(Permissions::EXECUTE)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_no_memory_map_entries() {
+        let mut map = MemoryMap::new();
+        map.select_address(5);
+        let index = search_entries(&mut map);
+        assert!(map.entries[index].func_read_u8.is_none());
+    }
+
+    #[test]
+    fn test_reading_unmapped() {
+        let mut map = MemoryMap::new();
+        map.select_address(0);
+        assert_eq!(map.read_u8(), 0);
+        map.entries[0].entry_type = MemoryMapEntryType::UnmappedHigh;
+        assert_eq!(map.read_u8(), 0xFF);
+    }
+
+    #[test]
+    fn test_writing_unmapped() {
+        let mut map = MemoryMap::new();
+        map.select_address(0);
+        map.entries[0].entry_type = MemoryMapEntryType::UnmappedHigh;
+        map.write_u8(0x55);
+        assert_eq!(map.read_u8(), 0xFF);
+    }
+
+    #[test]
+    fn test_global_address_mask() {
+        let mut map = MemoryMap::new();
+        map.global_addr_mask = 1;
+        map.select_address(5);
+        assert_eq!(map.current_addr, 1);
+        map.global_addr_mask = 7;
+        map.select_address(5);
+        assert_eq!(map.current_addr, 5);
+        map.select_address(8);
+        assert_eq!(map.current_addr, 0);
+    }
+
+    #[test]
+    fn test_signed_access() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x00ff, Ram::new(0x100));
+        map.select_address(0x0000);
+        map.write_u8(0xff);
+        assert_eq!(map.read_i8(), -1);
+        map.write_i8(i8::MIN);
+        assert_eq!(map.read_u8(), 0x80);
+        map.write_u8(0x7f);
+        assert_eq!(map.read_i8(), i8::MAX);
+
+        map.write_i16(-2);
+        assert_eq!(map.read_u16(), 0xfffe);
+        map.write_u32(0x8000_0000);
+        assert_eq!(map.read_i32(), i32::MIN);
+        map.write_i64(i64::MAX);
+        assert_eq!(map.read_u64(), 0x7fff_ffff_ffff_ffff);
+        map.write_i128(-1);
+        assert_eq!(map.read_u128(), u128::MAX);
+        assert_eq!(map.read_i16() as i32, -1);
+    }
+
+    #[test]
+    fn test_address_accessors() {
+        let mut map = MemoryMap::new();
+        assert_eq!(map.address_mask(), u128::MAX);
+        map.select_address(0x1_2345);
+        map.set_address_mask(0xffff);
+        assert_eq!(map.address_mask(), 0xffff);
+        assert_eq!(map.current_address(), 0x2345);
+        map.select_address(0xabcd_ef01);
+        assert_eq!(map.current_address(), 0xef01);
+    }
+
+    #[test]
+    fn test_map_region() {
+        fn read(addr: Address) -> u8 {
+            addr as u8 + 1
+        }
+        let mut map = MemoryMap::new();
+        map.map_region(0x10, 0x1f, MemoryMapEntryType::Read)
+            .on_read_u8(read);
+        map.select_address(0x12);
+        assert_eq!(map.read_u8(), 0x13);
+        map.select_address(0x20);
+        assert_eq!(map.read_u8(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "is past its end")]
+    fn test_map_region_inverted() {
+        let mut map = MemoryMap::new();
+        map.map_region(0x20, 0x1f, MemoryMapEntryType::Read);
+    }
+
+    #[test]
+    #[should_panic(expected = "is past its end")]
+    fn test_set_bounds_inverted() {
+        let mut map = MemoryMap::new();
+        map.map_region(0x10, 0x1f, MemoryMapEntryType::Read);
+        let index = map.entry_index(0x10).unwrap();
+        map.entry_mut(index).unwrap().set_bounds(0x20, 0x1f);
+    }
+
+    #[test]
+    fn test_inverted_and_single_address_regions() {
+        let mut map = MemoryMap::new();
+        assert_eq!(
+            map.try_map_region(0x20, 0x1f, MemoryMapEntryType::Read)
+                .err(),
+            Some(MemoryMapError::InvertedRegion {
+                start: 0x20,
+                end: 0x1f
+            })
+        );
+        assert!(map.is_empty());
+
+        map.map_region(0x20, 0x20, MemoryMapEntryType::Read)
+            .on_read_u8(|_| 0x5a);
+        map.map_region(0x21, 0x21, MemoryMapEntryType::UnmappedHigh);
+        assert_eq!(map.validate(), Ok(()));
+        assert_eq!(map.read_u16_at(0x20), 0xff5a);
+        assert_eq!(map.read_u8_at(0x1f), 0x00);
+        assert_eq!(map.read_u8_at(0x22), 0x00);
+        assert_eq!(
+            map.remap_region(0x20, 0x30, 0x2f).err(),
+            Some(MemoryMapError::InvertedRegion {
+                start: 0x30,
+                end: 0x2f
+            })
+        );
+        assert_eq!(map.region_at(0x20).unwrap().end, 0x20);
+    }
+
+    #[test]
+    fn test_setters_update_entry_type() {
+        fn read(_addr: Address) -> u8 {
+            0x5a
+        }
+        fn write(_addr: Address, _data: u8) {}
+        let mut entry = MemoryMapEntry::new();
+        entry.set_bounds(4, 8);
+        assert_eq!((entry.start, entry.end), (4, 8));
+        entry.set_read_u8(read);
+        assert!(matches!(entry.entry_type, MemoryMapEntryType::Read));
+        entry.set_write_u8(write);
+        assert!(matches!(entry.entry_type, MemoryMapEntryType::ReadWrite));
+
+        let mut entry = MemoryMapEntry::new();
+        entry.set_write_u16(|_, _| {});
+        assert!(matches!(entry.entry_type, MemoryMapEntryType::Write));
+        entry.set_read_u16(|_| 0);
+        assert!(matches!(entry.entry_type, MemoryMapEntryType::ReadWrite));
+    }
+
+    #[test]
+    fn test_stateful_delegates() {
+        use std::sync::Arc;
+        use std::sync::Mutex;
+
+        let register = Arc::new(Mutex::new(0u8));
+        let mut map = MemoryMap::new();
+        let read_register = register.clone();
+        let write_register = register.clone();
+        map.map_region(0, 0, MemoryMapEntryType::ReadWrite)
+            .on_read_u8(move |_| *read_register.lock().unwrap())
+            .on_write_u8(move |_, data| *write_register.lock().unwrap() = data);
+        map.select_address(0);
+        map.write_u8(0x42);
+        assert_eq!(map.read_u8(), 0x42);
+        assert_eq!(*register.lock().unwrap(), 0x42);
+
+        let mut reads = 0;
+        map.map_region(1, 1, MemoryMapEntryType::Read)
+            .on_read_u8(move |_| {
+                reads += 1;
+                reads
+            });
+        map.select_address(1);
+        assert_eq!(map.read_u8(), 1);
+        assert_eq!(map.read_u8(), 2);
+    }
+
+    #[test]
+    fn test_missing_delegate() {
+        let mut map = MemoryMap::new();
+        map.map_region(0x10, 0x1f, MemoryMapEntryType::ReadWrite);
+        map.select_address(0x14);
+        assert_eq!(
+            map.try_read_u8(),
+            Err(MemoryMapError::MissingDelegate {
+                addr: 0x14,
+                width: 1,
+                region: None
+            })
+        );
+        assert_eq!(
+            map.try_read_u32(),
+            Err(MemoryMapError::MissingDelegate {
+                addr: 0x14,
+                width: 1,
+                region: None
+            })
+        );
+        assert_eq!(
+            map.try_write_u8(0x55),
+            Err(MemoryMapError::MissingDelegate {
+                addr: 0x14,
+                width: 1,
+                region: None
+            })
+        );
+        map.write_u8(0x55);
+        map.select_address(0x20);
+        assert_eq!(map.try_read_u8(), Ok(0));
+        assert_eq!(map.try_write_u8(0x55), Ok(()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Your memory map is broken")]
+    fn test_missing_delegate_panics() {
+        let mut map = MemoryMap::new();
+        map.map_region(0x10, 0x1f, MemoryMapEntryType::Read);
+        map.select_address(0x10);
+        map.read_u8();
+    }
+
+    #[test]
+    fn test_endianness() {
+        use std::sync::Arc;
+        use std::sync::Mutex;
+
+        let bytes = Arc::new(Mutex::new([0u8; 4]));
+        let read_bytes = bytes.clone();
+        let write_bytes = bytes.clone();
+        let mut map = MemoryMap::new();
+        map.map_region(0, 3, MemoryMapEntryType::ReadWrite)
+            .on_read_u8(move |addr| read_bytes.lock().unwrap()[addr as usize])
+            .on_write_u8(move |addr, data| write_bytes.lock().unwrap()[addr as usize] = data);
+
+        map.select_address(0);
+        map.write_u16(0x1234);
+        assert_eq!(*bytes.lock().unwrap(), [0x34, 0x12, 0, 0]);
+        assert_eq!(map.read_u16(), 0x1234);
+        assert_eq!(map.current_addr, 0);
+
+        map.set_endianness(Endianness::Big);
+        assert_eq!(map.read_u16(), 0x3412);
+        map.select_address(2);
+        map.write_u16(0xabcd);
+        assert_eq!(*bytes.lock().unwrap(), [0x34, 0x12, 0xab, 0xcd]);
+        assert_eq!(map.read_u16(), 0xabcd);
+    }
+
+    #[test]
+    fn test_full_width_fallback() {
+        use std::sync::Arc;
+        use std::sync::Mutex;
+
+        let bytes = Arc::new(Mutex::new([0u8; 32]));
+        for (i, byte) in bytes.lock().unwrap().iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let read_bytes = bytes.clone();
+        let write_bytes = bytes.clone();
+        let mut map = MemoryMap::new();
+        map.map_region(0, 31, MemoryMapEntryType::ReadWrite)
+            .on_read_u8(move |addr| read_bytes.lock().unwrap()[addr as usize])
+            .on_write_u8(move |addr, data| write_bytes.lock().unwrap()[addr as usize] = data);
+
+        map.select_address(4);
+        assert_eq!(map.read_u32(), 0x07060504);
+        assert_eq!(map.read_u64(), 0x0b0a090807060504);
+        assert_eq!(map.read_u128(), 0x131211100f0e0d0c0b0a090807060504);
+        assert_eq!(map.current_addr, 4);
+
+        map.set_endianness(Endianness::Big);
+        assert_eq!(map.read_u32(), 0x04050607);
+        assert_eq!(map.read_u128(), 0x0405060708090a0b0c0d0e0f10111213);
+
+        map.select_address(16);
+        map.write_u64(0x0102030405060708);
+        assert_eq!(bytes.lock().unwrap()[16..24], [1, 2, 3, 4, 5, 6, 7, 8]);
+        map.set_endianness(Endianness::Little);
+        map.write_u32(0xdeadbeef);
+        assert_eq!(bytes.lock().unwrap()[16..20], [0xef, 0xbe, 0xad, 0xde]);
+    }
+
+    #[test]
+    fn test_fallback_uses_narrower_delegate() {
+        let mut map = MemoryMap::new();
+        map.map_region(0, 0xff, MemoryMapEntryType::Read)
+            .on_read_u16(|addr| addr as u16 | 0x100);
+        map.select_address(0x10);
+        assert_eq!(map.read_u32(), 0x0112_0110);
+    }
+
+    #[test]
+    fn test_width_synthesis_disabled() {
+        let mut map = MemoryMap::new();
+        let entry = map
+            .map_region(0, 0xff, MemoryMapEntryType::ReadWrite)
+            .on_read_u8(|addr| addr as u8)
+            .on_write_u8(|_, _| {})
+            .on_read_u32(|addr| addr as u32);
+        entry.set_allow_width_synthesis(false);
+        map.select_address(0x10);
+        assert_eq!(map.try_read_u8(), Ok(0x10));
+        assert_eq!(map.try_read_u32(), Ok(0x10));
+        assert_eq!(
+            map.try_read_u16(),
+            Err(MemoryMapError::MissingDelegate {
+                addr: 0x10,
+                width: 2,
+                region: None
+            })
+        );
+        assert_eq!(
+            map.try_write_u32(0),
+            Err(MemoryMapError::MissingDelegate {
+                addr: 0x10,
+                width: 4,
+                region: None
+            })
+        );
+    }
+
+    #[test]
+    fn test_many_regions() {
+        let mut map = MemoryMap::new();
+        for i in 0..1000u128 {
+            // Map out of order so the lookup has to sort.
+            let region = (i * 7919) % 1000;
+            map.map_region(region * 0x10, region * 0x10 + 0xf, MemoryMapEntryType::Read)
+                .on_read_u8(move |_| region as u8);
+        }
+        for region in 0..1000u128 {
+            map.select_address(region * 0x10 + 0x8);
+            assert_eq!(map.read_u8(), region as u8);
+            let index = search_entries(&mut map);
+            assert_eq!(map.entries[index].start, region * 0x10);
+        }
+        map.select_address(0x10000);
+        assert_eq!(search_entries(&mut map), 0);
+    }
+
+    #[test]
+    fn test_overlapping_regions() {
+        let mut map = MemoryMap::new();
+        map.map_region(0x00, 0xff, MemoryMapEntryType::Read)
+            .on_read_u8(|_| 1);
+        map.map_region(0x10, 0x1f, MemoryMapEntryType::Read)
+            .on_read_u8(|_| 2);
+        map.map_region(0x10, 0x17, MemoryMapEntryType::Read)
+            .on_read_u8(|_| 3);
+        map.select_address(0x12);
+        assert_eq!(map.read_u8(), 3);
+        map.select_address(0x18);
+        assert_eq!(map.read_u8(), 2);
+        map.select_address(0x80);
+        assert_eq!(map.read_u8(), 1);
+        map.select_address(0x100);
+        assert_eq!(map.read_u8(), 0);
+    }
+
+    #[test]
+    fn test_unmapped_reads_do_not_grow_map() {
+        let mut map = MemoryMap::new();
+        map.map_region(0, 0xf, MemoryMapEntryType::Read)
+            .on_read_u8(|_| 1);
+        let len = map.entries.len();
+        map.select_address(0x1234);
+        for _ in 0..10000 {
+            assert_eq!(map.read_u8(), 0);
+            assert_eq!(map.entries.len(), len);
+        }
+        assert_eq!(map.find_region(0x1234), None);
+        assert_eq!(map.find_region(0x4), Some(1));
+    }
+
+    #[test]
+    fn test_find_region_before_sort() {
+        let mut map = MemoryMap::new();
+        map.map_region(0x20, 0x2f, MemoryMapEntryType::Read);
+        map.map_region(0x00, 0x0f, MemoryMapEntryType::Read);
+        assert!(map.needs_sort);
+        assert_eq!(map.find_region(0x04), Some(2));
+        assert_eq!(map.find_region(0x10), None);
+        map.sort_entries();
+        assert_eq!(map.find_region(0x04), Some(1));
+        assert_eq!(map.find_region(0x24), Some(2));
+    }
+
+    #[test]
+    fn test_device() {
+        struct Timer {
+            counter: u32,
+        }
+
+        impl Device for Timer {
+            fn read(&mut self, _addr: Address, width: u8) -> u128 {
+                self.counter += 1;
+                self.counter as u128 * width as u128
+            }
+
+            fn write(&mut self, _addr: Address, _width: u8, data: u128) {
+                self.counter = data as u32;
+            }
+
+            fn max_width(&self) -> u8 {
+                4
+            }
+        }
+
+        let mut map = MemoryMap::new();
+        map.map_region(0x40, 0x4f, MemoryMapEntryType::UnmappedLow)
+            .on_read_u8(|_| 0xee)
+            .on_device(Timer { counter: 0 });
+        map.select_address(0x40);
+        assert_eq!(map.read_u8(), 1);
+        assert_eq!(map.read_u32(), 8);
+        // Too wide for the timer, so it is composed from two 32-bit reads.
+        assert_eq!(map.read_u64(), (16 << 32) | 12);
+        map.write_u16(0x1234);
+        assert_eq!(map.read_u16(), 0x1235 * 2);
+
+        let index = search_entries(&mut map);
+        assert!(matches!(
+            map.entries[index].entry_type,
+            MemoryMapEntryType::ReadWrite
+        ));
+    }
+
+    #[test]
+    fn test_regions() {
+        let mut map = MemoryMap::new();
+        assert_eq!(map.regions().count(), 0);
+        assert_eq!(map.len(), 0);
+        assert!(map.is_empty());
+        map.map_region(0x8000, 0xffff, MemoryMapEntryType::Read);
+        map.map_ram(0x0000, 0x07ff, Ram::new(0x800));
+        map.map_region(0x2000, 0x2007, MemoryMapEntryType::UnmappedHigh);
+        let regions: Vec<_> = map.regions().collect();
+        assert_eq!(
+            regions,
+            [
+                (0x0000, 0x07ff, MemoryMapEntryType::ReadWrite),
+                (0x2000, 0x2007, MemoryMapEntryType::UnmappedHigh),
+                (0x8000, 0xffff, MemoryMapEntryType::Read),
+            ]
+        );
+        assert_eq!(map.len(), 3);
+        assert!(!map.is_empty());
+        map.unmap_region(0x2000);
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut map = MemoryMap::new();
+        map.map_region(0x00, 0x0f, MemoryMapEntryType::Read);
+        map.map_region(0x10, 0x1f, MemoryMapEntryType::Read);
+        assert_eq!(map.validate(), Ok(()));
+
+        map.map_region(0x10, 0x1f, MemoryMapEntryType::Write);
+        map.map_region(0x18, 0x27, MemoryMapEntryType::Write);
+        assert_eq!(
+            map.validate(),
+            Err(vec![
+                Overlap {
+                    first: (0x10, 0x1f),
+                    second: (0x10, 0x1f),
+                },
+                Overlap {
+                    first: (0x10, 0x1f),
+                    second: (0x18, 0x27),
+                },
+                Overlap {
+                    first: (0x10, 0x1f),
+                    second: (0x18, 0x27),
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_reject_overlaps() {
+        let mut map = MemoryMap::new();
+        map.set_reject_overlaps(true);
+        map.map_region(0x10, 0x1f, MemoryMapEntryType::Read);
+        assert!(map
+            .try_map_region(0x20, 0x2f, MemoryMapEntryType::Read)
+            .is_ok());
+        assert!(map
+            .try_map_region(0x00, 0x0f, MemoryMapEntryType::Read)
+            .is_ok());
+        assert_eq!(
+            map.try_map_region(0x10, 0x1f, MemoryMapEntryType::Read)
+                .unwrap_err(),
+            MemoryMapError::RegionOverlap {
+                start: 0x10,
+                end: 0x1f
+            }
+        );
+        assert_eq!(
+            map.try_map_region(0x28, 0x37, MemoryMapEntryType::Read)
+                .unwrap_err(),
+            MemoryMapError::RegionOverlap {
+                start: 0x20,
+                end: 0x2f
+            }
+        );
+        assert_eq!(map.regions().count(), 3);
+        assert_eq!(map.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_region_addr_mask() {
+        use std::sync::Arc;
+        use std::sync::Mutex;
+
+        let registers = Arc::new(Mutex::new([0u8; 4]));
+        let read_registers = registers.clone();
+        let write_registers = registers.clone();
+        let mut map = MemoryMap::new();
+        let entry = map
+            .map_region(0x40, 0x7f, MemoryMapEntryType::ReadWrite)
+            .on_read_u8(move |addr| read_registers.lock().unwrap()[addr as usize - 0x40])
+            .on_write_u8(move |addr, data| {
+                write_registers.lock().unwrap()[addr as usize - 0x40] = data
+            });
+        entry.set_addr_mask(!0x3c);
+
+        map.select_address(0x42);
+        map.write_u8(0x99);
+        for mirror in (0x42..0x80).step_by(4) {
+            map.select_address(mirror);
+            assert_eq!(map.read_u8(), 0x99);
+        }
+        map.select_address(0x7c);
+        map.write_u32(0x44332211);
+        assert_eq!(*registers.lock().unwrap(), [0x11, 0x22, 0x33, 0x44]);
+        map.select_address(0x80);
+        assert_eq!(map.read_u8(), 0);
+    }
+
+    #[test]
+    fn test_separate_read_write_masks() {
+        use std::sync::Arc;
+        use std::sync::Mutex;
+
+        // Reads see the status register at offset 0, writes go to the control register at 1.
+        let offsets = Arc::new(Mutex::new(Vec::new()));
+        let read_offsets = offsets.clone();
+        let write_offsets = offsets.clone();
+        let mut map = MemoryMap::new();
+        let entry = map
+            .map_region(0x40, 0x41, MemoryMapEntryType::ReadWrite)
+            .on_read_u8(move |addr| {
+                read_offsets.lock().unwrap().push(addr);
+                0
+            })
+            .on_write_u8(move |addr, _| write_offsets.lock().unwrap().push(addr));
+        entry.set_relative_addressing(true);
+        entry.set_read_mask(0x0);
+        entry.set_write_mask(0x1);
+        map.read_u8_at(0x41);
+        map.write_u8_at(0x41, 0x80);
+        map.write_u8_at(0x40, 0x80);
+        assert_eq!(*offsets.lock().unwrap(), [0x0, 0x1, 0x0]);
+
+        // The address mask still applies to writes when only the read mask is set.
+        let index = map.find_region(0x40).unwrap();
+        map.entries[index].write_mask = None;
+        map.entries[index].set_addr_mask(0x0);
+        map.write_u8_at(0x41, 0x80);
+        assert_eq!(offsets.lock().unwrap().last(), Some(&0x0));
+    }
+
+    #[test]
+    fn test_relative_addressing() {
+        let mut map = MemoryMap::new();
+        let entry = map
+            .map_region(0x1000, 0x1fff, MemoryMapEntryType::Read)
+            .on_read_u16(|offset| offset as u16);
+        entry.set_relative_addressing(true);
+        map.select_address(0x1234);
+        assert_eq!(map.read_u16(), 0x234);
+
+        let index = search_entries(&mut map);
+        map.entries[index].set_addr_mask(0xff);
+        assert_eq!(map.read_u16(), 0x34);
+        map.entries[index].set_relative_addressing(false);
+        map.entries[index].set_addr_mask(u128::MAX);
+        assert_eq!(map.read_u16(), 0x1234);
+    }
+
+    #[test]
+    fn test_uniform_delegates() {
+        use std::sync::Arc;
+        use std::sync::Mutex;
+
+        let writes = Arc::new(Mutex::new(Vec::new()));
+        let log = writes.clone();
+        let mut map = MemoryMap::new();
+        map.map_region(0x1000, 0x1fff, MemoryMapEntryType::ReadWrite)
+            .on_uniform_read(|addr, width| (width as u128) << 120 | addr)
+            .on_uniform_write(move |addr, width, data| {
+                log.lock().unwrap().push((addr, width, data))
+            });
+
+        assert_eq!(map.read_u8_at(0x1234), 0x34);
+        assert_eq!(map.read_u16_at(0x1234), 0x1234);
+        assert_eq!(map.read_u32_at(0x1234), 0x1234);
+        assert_eq!(map.read_u64_at(0x1234), 0x1234);
+        assert_eq!(map.read_u128_at(0x1234), 16 << 120 | 0x1234);
+        map.write_u8_at(0x1000, 0x11);
+        map.write_u16_at(0x1000, 0x2222);
+        map.write_u32_at(0x1000, 0x3333_3333);
+        map.write_u64_at(0x1000, u64::MAX);
+        map.write_u128_at(0x1000, u128::MAX);
+        assert_eq!(
+            *writes.lock().unwrap(),
+            [
+                (0x1000, 1, 0x11),
+                (0x1000, 2, 0x2222),
+                (0x1000, 4, 0x3333_3333),
+                (0x1000, 8, u64::MAX as u128),
+                (0x1000, 16, u128::MAX),
+            ]
+        );
+    }
+
+    /// Maps a bit of everything, including regions that make accesses fail.
+    fn build_fuzz_map() -> MemoryMap {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x1000, 0x1fff, Ram::new(0x1000));
+        map.map_rom(Rom::from_bytes(0x2000, vec![0xaa; 0x100]));
+        map.add_alias(0x3000, 0x30ff, 0x1f80);
+        map.map_region(0x4000, 0x40ff, MemoryMapEntryType::Read);
+        map.map_region(0x4100, 0x41ff, MemoryMapEntryType::Write);
+        map.map_region(0x4200, 0x42ff, MemoryMapEntryType::ReadWrite)
+            .set_allow_width_synthesis(false);
+        map.map_region(0x5000, 0x5fff, MemoryMapEntryType::UnmappedOpenBus);
+        map.map_region(0x6000, 0x6fff, MemoryMapEntryType::UnmappedHigh);
+        map.map_banked(0x7000, crate::memory::BankedRegion::new(4, 0x100));
+        map.map_ram(u128::MAX - 0xff, u128::MAX, Ram::new(0x100));
+        map.set_permissions(0x1800, 0x18ff, Permissions::READ);
+        map
+    }
+
+    #[test]
+    fn test_arbitrary_accesses_never_panic() {
+        // A fixed xorshift sequence keeps failures reproducible.
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let mut map = build_fuzz_map();
+        map.set_fault_handler(Box::new(|_| {}));
+        for _ in 0..20_000 {
+            let roll = next();
+            let addr = match roll % 4 {
+                0 => next() as u128 | (next() as u128) << 64,
+                1 => u128::MAX - (next() % 0x20) as u128,
+                _ => (next() % 0x8000) as u128,
+            };
+            let width = 1u8 << (next() % 5);
+            let data = next() as u128 | (next() as u128) << 64;
+            map.select_address(addr);
+            match (roll >> 8) % 12 {
+                0 => map.set_alignment_policy(match next() % 3 {
+                    0 => AlignmentPolicy::Allow,
+                    1 => AlignmentPolicy::Fault,
+                    _ => AlignmentPolicy::Wrap,
+                }),
+                1 => map.set_cross_region_policy(match next() % 2 {
+                    0 => CrossRegionPolicy::Split,
+                    _ => CrossRegionPolicy::Fault,
+                }),
+                2 => map.set_overflow_policy(match next() % 2 {
+                    0 => OverflowPolicy::Wrap,
+                    _ => OverflowPolicy::Clamp,
+                }),
+                3 => map.set_strict_permissions(next() % 2 == 0),
+                4 => {
+                    let mut buf = [0; 0x20];
+                    let _ = map.try_read_block(addr, &mut buf);
+                    map.write_block(addr, &buf[..(next() % 0x20) as usize]);
+                }
+                5 => {
+                    let _ = map.try_fetch::<u32>();
+                    let _ = map.fetch_u16();
+                }
+                6 | 7 => {
+                    let _ = map.try_write_width(width, data);
+                }
+                8 => {
+                    let _ = map.read_u64();
+                    map.write_u128(data);
+                }
+                _ => {
+                    let _ = map.try_read_width(width);
+                }
+            }
+        }
+
+        // RAM still behaves like RAM once the policies are back to their defaults.
+        map.set_alignment_policy(AlignmentPolicy::Allow);
+        map.set_cross_region_policy(CrossRegionPolicy::Split);
+        map.set_overflow_policy(OverflowPolicy::Wrap);
+        map.set_strict_permissions(false);
+        for _ in 0..1_000 {
+            let addr = 0x1000 + (next() % 0x7f0) as u128;
+            let data = next() as u128 | (next() as u128) << 64;
+            map.select_address(addr);
+            assert_eq!(map.try_write_u128(data), Ok(()));
+            assert_eq!(map.try_read_u128(), Ok(data));
+        }
+    }
+
+    /// Answers only in the lower half of its range, like a board with half its sockets empty.
+    struct HalfPopulated;
+
+    impl Device for HalfPopulated {
+        fn read(&mut self, addr: Address, _width: u8) -> u128 {
+            addr & 0xff
+        }
+
+        fn write(&mut self, _addr: Address, _width: u8, _data: u128) {}
+
+        fn respond_read(&mut self, addr: Address, width: u8) -> BusResponse {
+            if addr + width as Address > 0x80 {
+                return BusResponse::Error;
+            }
+            BusResponse::Data(self.read(addr, width))
+        }
+
+        fn respond_write(&mut self, addr: Address, width: u8, _data: u128) -> BusResponse {
+            if addr + width as Address > 0x80 {
+                return BusResponse::Error;
+            }
+            BusResponse::Ack
+        }
+    }
+
+    #[test]
+    fn test_bus_error() {
+        let mut map = MemoryMap::new();
+        map.map_region(0x1000, 0x10ff, MemoryMapEntryType::ReadWrite)
+            .on_device(HalfPopulated)
+            .set_relative_addressing(true);
+        map.select_address(0x1010);
+        assert_eq!(map.try_read_u8(), Ok(0x10));
+        assert_eq!(map.try_write_u8(0), Ok(()));
+        map.select_address(0x1080);
+        assert_eq!(
+            map.try_read_u8(),
+            Err(MemoryMapError::BusError { addr: 0x1080 })
+        );
+        assert_eq!(
+            map.try_write_u8(0),
+            Err(MemoryMapError::BusError { addr: 0x1080 })
+        );
+        // An access reaching into the empty half fails as a whole.
+        map.select_address(0x107f);
+        assert_eq!(
+            map.try_read_u16(),
+            Err(MemoryMapError::BusError { addr: 0x107f })
+        );
+        // Unmapped space answers with its default instead.
+        map.select_address(0x2000);
+        assert_eq!(map.try_read_u8(), Ok(0));
+    }
+
+    #[test]
+    fn test_dma_scatter() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x1000, 0x10ff, Ram::new(0x100));
+        map.map_ram(0x8000, 0x80ff, Ram::new(0x100));
+        let src: Vec<u8> = (1..=10).collect();
+
+        let descriptors = [(0x1010, 4), (0x80fe, 4), (0x1000, 8)];
+        // Two bytes run off the end of the second RAM, and the last descriptor runs out of data.
+        assert_eq!(map.dma_scatter(&src, &descriptors), 8);
+        let mut buf = [0; 4];
+        map.read_block(0x1010, &mut buf);
+        assert_eq!(buf, [1, 2, 3, 4]);
+        map.read_block(0x80fe, &mut buf[..2]);
+        assert_eq!(buf[..2], [5, 6]);
+        map.read_block(0x1000, &mut buf);
+        assert_eq!(buf, [9, 10, 0, 0]);
+    }
+
+    #[test]
+    fn test_entry_index() {
+        let mut map = MemoryMap::new();
+        map.map_region(0x2000, 0x2fff, MemoryMapEntryType::ReadWrite);
+        map.map_ram(0x0000, 0x0fff, Ram::new(0x1000));
+        assert_eq!(map.entry_index(0x0800), Some(0));
+        assert_eq!(map.entry_index(0x2800), Some(1));
+        assert_eq!(map.entry_index(0x1800), None);
+
+        let index = map.entry_index(0x2800).unwrap();
+        map.entry_mut(index).unwrap().on_read_u8(|addr| addr as u8);
+        assert_eq!(map.read_u8_at(0x2842), 0x42);
+        map.entry_mut(index)
+            .unwrap()
+            .set_entry_type(MemoryMapEntryType::Read);
+        assert_eq!(map.region_type(0x2000), Some(MemoryMapEntryType::Read));
+        assert!(map.entry_mut(2).is_none());
+
+        // Moving a region renumbers the regions around it.
+        map.entry_mut(0).unwrap().set_bounds(0x4000, 0x4fff);
+        assert_eq!(map.entry_index(0x4000), Some(1));
+        assert_eq!(map.read_u8_at(0x2000), 0x00);
+    }
+
+    #[test]
+    fn test_region_priority() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x0fff, Ram::new(0x1000));
+        map.write_u8_at(0x0010, 0x42);
+        map.map_rom(Rom::from_bytes(0x0000, vec![0xaa; 0x100]))
+            .set_priority(1);
+        map.map_region(0x0080, 0x008f, MemoryMapEntryType::UnmappedHigh);
+        // The boot ROM shadows the RAM, and the region mapped later in the middle of both.
+        assert_eq!(map.read_u8_at(0x0010), 0xaa);
+        assert_eq!(map.read_u8_at(0x0080), 0xaa);
+        assert_eq!(map.read_u16_at(0x00ff), 0x00aa);
+
+        map.set_region_priority(0x0000, -1).unwrap();
+        assert_eq!(map.read_u8_at(0x0010), 0x42);
+        assert_eq!(map.read_u8_at(0x0080), 0xff);
+        assert_eq!(
+            map.set_region_priority(0x0001, 0),
+            Err(MemoryMapError::NoEntriesFound { addr: 0x0001 })
+        );
+    }
+
+    #[test]
+    fn test_set_region_type() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x8000, 0x8fff, Ram::new(0x1000));
+        map.write_u16_at(0x8000, 0xbeef);
+        assert_eq!(map.region_type(0x8000), Some(MemoryMapEntryType::ReadWrite));
+        assert_eq!(
+            map.set_region_type(0x8001, MemoryMapEntryType::Read),
+            Err(MemoryMapError::NoEntriesFound { addr: 0x8001 })
+        );
+
+        map.set_region_type(0x8000, MemoryMapEntryType::Read)
+            .unwrap();
+        assert_eq!(map.region_type(0x8000), Some(MemoryMapEntryType::Read));
+        map.write_u16_at(0x8000, 0x1234);
+        map.write_u8_at(0x8001, 0x56);
+        map.write_block(0x8000, &[0x78]);
+        assert_eq!(map.read_u16_at(0x8000), 0xbeef);
+        map.set_strict_permissions(true);
+        assert_eq!(
+            map.try_write_u8(0x9a),
+            Err(MemoryMapError::WriteToReadOnly {
+                addr: 0x8000,
+                region: None
+            })
+        );
+
+        map.set_region_type(0x8000, MemoryMapEntryType::ReadWrite)
+            .unwrap();
+        map.write_u16_at(0x8000, 0x1234);
+        assert_eq!(map.read_u16_at(0x8000), 0x1234);
+    }
+
+    #[test]
+    fn test_block_transfers() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x00, 0x0f, Ram::new(0x10));
+        map.map_region(0x10, 0x17, MemoryMapEntryType::UnmappedHigh);
+        map.map_rom(Rom::from_bytes(0x20, vec![0xa0, 0xa1, 0xa2, 0xa3]));
+
+        let data: Vec<u8> = (1..=0x24).collect();
+        map.write_block(0x00, &data);
+        let mut buf = [0u8; 0x26];
+        map.read_block(0x00, &mut buf);
+        let mut expected = Vec::new();
+        expected.extend(1..=0x10);
+        expected.extend([0xff; 8]);
+        expected.extend([0x00; 8]);
+        expected.extend([0xa0, 0xa1, 0xa2, 0xa3]);
+        expected.extend([0x00; 2]);
+        assert_eq!(buf.to_vec(), expected);
+
+        map.select_address(0x0c);
+        assert_eq!(map.read_u32(), 0x100f0e0d);
+    }
+
+    #[test]
+    fn test_block_transfer_counts() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x1000, 0x10ff, Ram::new(0x100));
+        map.map_rom(Rom::from_bytes(0x2000, vec![0xaa; 0x10]));
+        assert_eq!(map.write_block(0x1080, &[0x55; 0x100]), 0x80);
+        assert_eq!(map.write_block(0x1000, &[0x11; 0x10]), 0x10);
+        assert_eq!(map.write_block(0x2000, &[0x11; 0x10]), 0);
+
+        let mut buf = [0; 0x20];
+        assert_eq!(map.read_block(0x10f0, &mut buf), 0x10);
+        assert_eq!(buf[..0x10], [0x55; 0x10]);
+        assert_eq!(buf[0x10..], [0x00; 0x10]);
+        assert_eq!(map.read_block(0x1ff8, &mut buf), 0x10);
+
+        map.set_overflow_policy(OverflowPolicy::Clamp);
+        assert_eq!(map.write_block(u128::MAX, &[0x11; 4]), 0);
+    }
+
+    #[test]
+    fn test_try_write_block() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x1000, 0x10ff, Ram::new(0x100));
+        map.map_rom(Rom::from_bytes(0x2000, vec![0xaa; 0x10]));
+        assert_eq!(map.try_write_block(0x1000, &[0x11; 0x10]), Ok(0x10));
+        // Runs off the end of the RAM into unmapped space.
+        assert_eq!(
+            map.try_write_block(0x10f8, &[0x22; 0x10]),
+            Err(MemoryMapError::NoEntriesFound { addr: 0x1100 })
+        );
+        assert_eq!(map.read_u64_at(0x10f8), 0x2222_2222_2222_2222);
+        assert_eq!(
+            map.try_write_block(0x2000, &[0x33; 4]),
+            Err(MemoryMapError::WriteToReadOnly {
+                addr: 0x2000,
+                region: None
+            })
+        );
+        assert_eq!(map.read_u8_at(0x2000), 0xaa);
+
+        map.set_overflow_policy(OverflowPolicy::Clamp);
+        map.map_ram(u128::MAX - 1, u128::MAX, Ram::new(2));
+        assert_eq!(map.try_write_block(u128::MAX - 1, &[0x44; 4]), Ok(2));
+    }
+
+    #[test]
+    fn test_block_wraps_at_mask() {
+        let mut map = MemoryMap::new();
+        map.global_addr_mask = 0xff;
+        map.map_ram(0x00, 0xff, Ram::new(0x100));
+        map.write_block(0xfe, &[1, 2, 3, 4]);
+        let mut buf = [0u8; 2];
+        map.read_block(0x00, &mut buf);
+        assert_eq!(buf, [3, 4]);
+        map.read_block(0xfe, &mut buf);
+        assert_eq!(buf, [1, 2]);
+    }
+
+    #[test]
+    fn test_24_bit_wrap() {
+        let mut map = MemoryMap::new();
+        map.set_address_mask(0xff_ffff);
+        map.map_ram(0x00_0000, 0x00_ffff, Ram::new(0x1_0000));
+        map.map_ram(0xff_0000, 0xff_ffff, Ram::new(0x1_0000));
+        map.write_u8_at(0x00_0000, 0x12);
+        map.write_u8_at(0xff_ffff, 0x34);
+        assert_eq!(map.read_u16_at(0xff_ffff), 0x1234);
+        map.write_u32_at(0xff_fffe, 0xdeadbeef);
+        assert_eq!(map.read_u16_at(0x00_0000), 0xdead);
+
+        let mut buf = [0; 4];
+        map.read_block(0xff_fffe, &mut buf);
+        assert_eq!(buf, [0xef, 0xbe, 0xad, 0xde]);
+        map.write_block(0xff_ffff, &[0x01, 0x02]);
+        assert_eq!(map.read_u8_at(0x00_0000), 0x02);
+        // Addresses above the 24 bits are masked off before anything else.
+        assert_eq!(map.read_u16_at(0x1ff_ffff), 0x0201);
+    }
+
+    #[test]
+    fn test_overflow_policy() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0, 0xff, Ram::new(0x100));
+        map.map_ram(u128::MAX - 0xff, u128::MAX, Ram::new(0x100));
+        map.write_u8_at(0, 0x11);
+        map.write_u8_at(u128::MAX, 0x22);
+        assert_eq!(map.read_u16_at(u128::MAX), 0x1122);
+
+        map.set_overflow_policy(OverflowPolicy::Clamp);
+        assert_eq!(map.read_u16_at(u128::MAX), 0x0022);
+        map.write_u16_at(u128::MAX, 0x3344);
+        assert_eq!(map.read_u8_at(0), 0x11);
+        assert_eq!(map.read_u8_at(u128::MAX), 0x44);
+        let mut buf = [0xaa; 3];
+        map.read_block(u128::MAX - 1, &mut buf);
+        assert_eq!(buf, [0x00, 0x44, 0x00]);
+        map.write_block(u128::MAX, &[0x55, 0x66]);
+        assert_eq!(map.read_u8_at(0), 0x11);
+
+        map.entries[0].entry_type = MemoryMapEntryType::UnmappedHigh;
+        assert_eq!(map.read_u32_at(u128::MAX), 0xffff_ff55);
+    }
+
+    #[test]
+    fn test_narrow_address_type() {
+        let mut map = MemoryMap::<u16>::default();
+        map.map_ram(0xff00, 0xffff, Ram::new(0x100));
+        map.map_region(0x0000, 0x00ff, MemoryMapEntryType::ReadWrite)
+            .on_device(Ram::new(0x100));
+        // A 32-bit access at the top of the space wraps to the bottom like the address does.
+        map.select_address(0xfffe);
+        map.write_u32(0x44332211);
+        assert_eq!(map.read_u32(), 0x44332211);
+        map.select_address(0x0000);
+        assert_eq!(map.read_u16(), 0x4433);
+        assert_eq!(
+            map.regions().last(),
+            Some((0xff00, 0xffff, MemoryMapEntryType::ReadWrite))
+        );
+
+        map.select_address(0x1234);
+        assert_eq!(map.try_read_u8(), Ok(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "past the end of the address space")]
+    fn test_rom_too_wide_for_address_type() {
+        let mut map = MemoryMap::<u16>::default();
+        map.map_rom(Rom::from_bytes(0xfff0, vec![0; 0x20]));
+    }
+
+    #[test]
+    fn test_fill() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x1000, 0x1fff, Ram::new(0x1000));
+        map.map_rom(Rom::from_bytes(0x2000, vec![0x11; 0x10]));
+        map.map_ram(0x3000, 0x30ff, Ram::new(0x100));
+        map.fill(0x1800, 0x30ff, 0xa5);
+        for &addr in &[0x1800, 0x1abc, 0x1fff, 0x3000, 0x3080, 0x30ff] {
+            map.select_address(addr);
+            assert_eq!(map.read_u8(), 0xa5, "at {:#x}", addr);
+        }
+        map.select_address(0x17ff);
+        assert_eq!(map.read_u8(), 0x00);
+        map.select_address(0x2008);
+        assert_eq!(map.read_u8(), 0x11);
+
+        assert_eq!(
+            map.try_fill(0x1000, 0x2003, 0xff),
+            Err(MemoryMapError::WriteToReadOnly {
+                addr: 0x2000,
+                region: None
+            })
+        );
+        assert_eq!(
+            map.try_fill(0x3000, 0x3100, 0xff),
+            Err(MemoryMapError::NoEntriesFound { addr: 0x3100 })
+        );
+        map.select_address(0x1000);
+        assert_eq!(map.read_u8(), 0x00);
+        assert_eq!(map.try_fill(0x1000, 0x1003, 0xff), Ok(()));
+        assert_eq!(map.read_u32(), 0xffffffff);
+        assert_eq!(
+            map.try_fill(0x1020, 0x1010, 0xff),
+            Err(MemoryMapError::InvertedRegion {
+                start: 0x1020,
+                end: 0x1010
+            })
+        );
+    }
+
+    #[test]
+    fn test_fill_wraps_masked_range() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0xffff, Ram::new(0x1_0000));
+        map.set_address_mask(0xffff);
+        map.fill(0xfffe, 0x1_0001, 0x5a);
+        assert_eq!(map.read_u16_at(0xfffe), 0x5a5a);
+        assert_eq!(map.read_u16_at(0x0000), 0x5a5a);
+        assert_eq!(map.read_u8_at(0x0002), 0x00);
+        assert_eq!(map.try_fill(0x1_0000, 0x2_ffff, 0xa5), Ok(()));
+        assert_eq!(map.read_u8_at(0x8000), 0xa5);
+    }
+
+    #[test]
+    #[should_panic(expected = "is past its end")]
+    fn test_fill_inverted() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x00ff, Ram::new(0x100));
+        map.fill(0x20, 0x10, 0x00);
+    }
+
+    #[test]
+    fn test_access_at() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x00ff, Ram::new(0x100));
+        map.write_u32_at(0x10, 0x44332211);
+        map.write_u8_at(0x20, 0x99);
+        assert_eq!(map.read_u16_at(0x12), 0x4433);
+        assert_eq!(map.read_u8_at(0x20), 0x99);
+        // The address stays selected for the stateful accessors.
+        assert_eq!(map.read_u8(), 0x99);
+        map.write_u128_at(0x30, u128::MAX);
+        assert_eq!(map.read_u64_at(0x38), u64::MAX);
+    }
+
+    /// Answers every access natively with the same byte repeated.
+    struct Pattern(u8);
+
+    impl Device for Pattern {
+        fn read(&mut self, _addr: Address, width: u8) -> u128 {
+            (0..width).fold(0, |data, _| (data << 8) | self.0 as u128)
+        }
+
+        fn write(&mut self, _addr: Address, _width: u8, _data: u128) {}
+    }
+
+    #[test]
+    fn test_access_across_regions() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0, 0x3, Ram::new(4));
+        map.map_region(0x4, 0x7, MemoryMapEntryType::ReadWrite)
+            .on_device(Pattern(0xaa));
+        map.map_region(0x8, 0xb, MemoryMapEntryType::ReadWrite)
+            .on_device(Pattern(0xbb));
+        map.write_u16_at(0x2, 0x2211);
+        assert_eq!(map.read_u32_at(0x2), 0xaaaa2211);
+        // A device able to answer the full width must still only answer for its own bytes.
+        assert_eq!(map.read_u32_at(0x6), 0xbbbbaaaa);
+        assert_eq!(map.read_u16_at(0x7), 0xbbaa);
+
+        map.set_cross_region_policy(CrossRegionPolicy::Fault);
+        map.select_address(0x2);
+        assert_eq!(
+            map.try_read_u32(),
+            Err(MemoryMapError::CrossesRegion {
+                addr: 0x2,
+                width: 4
+            })
+        );
+        assert_eq!(map.try_write_u16(0), Ok(()));
+        map.select_address(0x7);
+        assert_eq!(
+            map.try_write_u16(0),
+            Err(MemoryMapError::CrossesRegion {
+                addr: 0x7,
+                width: 2
+            })
+        );
+        assert_eq!(map.read_u32_at(0x4), 0xaaaaaaaa);
+    }
+
+    #[test]
+    fn test_unmap_and_remap() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x1000, 0x1fff, Ram::new(0x1000));
+        map.map_region(0x1800, 0x18ff, MemoryMapEntryType::UnmappedHigh);
+        map.map_ram(0x4000, 0x4fff, Ram::new(0x1000));
+        map.write_u8_at(0x1000, 0x42);
+        assert_eq!(map.read_u8_at(0x1800), 0xff);
+
+        let removed = map.unmap_region(0x1800).unwrap();
+        assert_eq!(removed.entry_type, MemoryMapEntryType::UnmappedHigh);
+        assert_eq!(map.read_u8_at(0x1800), 0x00);
+        assert!(map.unmap_region(0x1800).is_none());
+
+        assert!(map.unmap_region(0x1000).is_some());
+        assert_eq!(map.read_u8_at(0x1000), 0x00);
+        assert!(!map.handles(0x1000));
+
+        map.write_u8_at(0x4010, 0x99);
+        map.remap_region(0x4000, 0x8000, 0x8fff).unwrap();
+        assert!(!map.handles(0x4010));
+        // The same RAM answers at the new location.
+        assert_eq!(map.read_u8_at(0x8010), 0x99);
+        assert_eq!(
+            map.remap_region(0x4000, 0, 1).err(),
+            Some(MemoryMapError::NoEntriesFound { addr: 0x4000 })
+        );
+        assert_eq!(
+            map.regions().collect::<Vec<_>>(),
+            vec![(0x8000, 0x8fff, MemoryMapEntryType::ReadWrite)]
+        );
+    }
+
+    #[test]
+    fn test_alias() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x0fff, Ram::new(0x1000));
+        map.add_alias(0x8000, 0x8fff, 0x0000);
+        map.write_u16_at(0x0010, 0xbeef);
+        assert_eq!(map.read_u16_at(0x8010), 0xbeef);
+        map.write_u32_at(0x8ffe, 0x44332211);
+        assert_eq!(map.read_u16_at(0x0ffe), 0x2211);
+        // The alias ends where it was told to, even though the target space goes on.
+        assert_eq!(map.read_u16_at(0x9000), 0x0000);
+
+        let mut buf = [0; 2];
+        map.read_block(0x8010, &mut buf);
+        assert_eq!(buf, [0xef, 0xbe]);
+        map.fill(0x8020, 0x8021, 0x5a);
+        assert_eq!(map.read_u16_at(0x0020), 0x5a5a);
+    }
+
+    #[test]
+    fn test_strict_read_only() {
+        let mut map = MemoryMap::new();
+        map.map_rom(Rom::from_bytes(0x1000, vec![0x11; 0x10]));
+        map.map_region(0x2000, 0x2000, MemoryMapEntryType::Read)
+            .on_read_u8(|_| 0x22);
+        map.select_address(0x1004);
+        assert_eq!(map.try_write_u32(0xffffffff), Ok(()));
+        assert_eq!(map.read_u32(), 0x11111111);
+
+        map.set_strict_permissions(true);
+        assert_eq!(
+            map.try_write_u32(0xffffffff),
+            Err(MemoryMapError::WriteToReadOnly {
+                addr: 0x1004,
+                region: None
+            })
+        );
+        map.select_address(0x2000);
+        assert_eq!(
+            map.try_write_u8(0xff),
+            Err(MemoryMapError::WriteToReadOnly {
+                addr: 0x2000,
+                region: None
+            })
+        );
+        // Infallible writes still drop the error.
+        map.write_u8(0xff);
+        assert_eq!(map.read_u8(), 0x22);
+        map.write_block(0x1000, &[0; 4]);
+        assert_eq!(map.read_u32_at(0x1000), 0x11111111);
+    }
+
+    #[test]
+    fn test_region_name_in_error() {
+        let mut map = MemoryMap::<u32>::default();
+        map.map_rom(Rom::from_bytes(0xffff_0000, vec![0xea; 0x10000]))
+            .set_name("BIOS");
+        map.set_strict_permissions(true);
+        map.select_address(0xffff_0000);
+        let err = map.try_write_u8(0).unwrap_err();
+        assert_eq!(
+            err,
+            MemoryMapError::WriteToReadOnly {
+                addr: 0xffff_0000,
+                region: Some("BIOS")
+            }
+        );
+        assert_eq!(
+            std::format!("{}", err),
+            "write to read-only region 'BIOS' at 0xffff0000"
+        );
+        assert_eq!(map.region_at(0xffff_1234).unwrap().name, Some("BIOS"));
+    }
+
+    #[test]
+    fn test_strict_write_only() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x00ff, Ram::new(0x100));
+        map.map_region(0x1000, 0x1000, MemoryMapEntryType::Write)
+            .on_write_u8(|_, _| {});
+        map.write_u8_at(0x0000, 0x42);
+        map.select_address(0x1000);
+        // Nothing drives the bus for a write-only register, so the last value is read back.
+        assert_eq!(map.try_read_u8(), Ok(0x42));
+
+        map.set_strict_permissions(true);
+        assert_eq!(
+            map.try_read_u8(),
+            Err(MemoryMapError::ReadFromWriteOnly {
+                addr: 0x1000,
+                region: None
+            })
+        );
+        assert_eq!(map.try_write_u8(0x99), Ok(()));
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x00ff, Ram::new(0x100));
+        map.map_rom(Rom::from_bytes(0x1000, vec![0xaa; 0x10]));
+        map.add_alias(0x8000, 0x80ff, 0x0000);
+        map.global_addr_mask = 0x0fff;
+        map.write_u8_at(0x0010, 0x42);
+
+        map.clear();
+        assert!(map.is_empty());
+        for addr in [0x0010, 0x1000, 0x8010] {
+            assert!(!map.handles(addr));
+            assert_eq!(map.read_u8_at(addr), 0x00);
+        }
+        assert_eq!(map.global_addr_mask, u128::MAX);
+    }
+
+    #[test]
+    fn test_with_base_len() {
+        let entry = MemoryMapEntry::with_base_len(0x1000u128, 0x100);
+        assert_eq!((entry.start, entry.end), (0x1000, 0x10ff));
+        let entry = MemoryMapEntry::with_base_len(u128::MAX - 0xf, 0x10);
+        assert_eq!((entry.start, entry.end), (u128::MAX - 0xf, u128::MAX));
+        let entry = MemoryMapEntry::with_base_len(0xffffu16, 1);
+        assert_eq!((entry.start, entry.end), (0xffff, 0xffff));
+        let entry = MemoryMapEntry::with_base_len(0u16, 0xffff);
+        assert_eq!((entry.start, entry.end), (0, 0xfffe));
+
+        let mut map = MemoryMap::new();
+        map.map_region(u128::MAX - 0xf, u128::MAX, MemoryMapEntryType::ReadWrite)
+            .on_read_u8(|addr| addr as u8);
+        assert_eq!(map.read_u8_at(u128::MAX), 0xff);
+        assert_eq!(map.read_u8_at(u128::MAX - 0xf), 0xf0);
+        assert!(!map.handles(u128::MAX - 0x10));
+    }
+
+    #[test]
+    #[should_panic(expected = "past the end of the address space")]
+    fn test_with_base_len_overflow() {
+        MemoryMapEntry::with_base_len(u128::MAX - 0xf, 0x11);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one byte")]
+    fn test_with_base_len_empty() {
+        MemoryMapEntry::with_base_len(0x1000u128, 0);
+    }
+}
@@ -0,0 +1,75 @@
+use core::fmt;
+use core::ops::{Add, BitAnd, Sub};
+
+/// An unsigned integer wide enough to address a bus. Maps, buses and devices are generic over it
+/// so a 16-bit machine can use `u16` addresses instead of paying for `u128` ones.
+pub trait BusAddress:
+    Copy
+    + Ord
+    + fmt::Debug
+    + fmt::LowerHex
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + BitAnd<Output = Self>
+    + 'static
+{
+    const ZERO: Self;
+    const ONE: Self;
+    const MAX: Self;
+
+    fn wrapping_add(self, rhs: Self) -> Self;
+    fn saturating_add(self, rhs: Self) -> Self;
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+
+    /// Widens the address, e.g. for error reporting or indexing backing storage.
+    fn to_u128(self) -> u128;
+
+    /// Narrows `value` to this address type, discarding any bits that don't fit.
+    fn from_u128(value: u128) -> Self;
+}
+
+macro_rules! impl_bus_address {
+    ($($ty:ty),*) => {
+        $(
+            impl BusAddress for $ty {
+                const ZERO: Self = 0;
+                const ONE: Self = 1;
+                const MAX: Self = <$ty>::MAX;
+
+                fn wrapping_add(self, rhs: Self) -> Self {
+                    <$ty>::wrapping_add(self, rhs)
+                }
+
+                fn saturating_add(self, rhs: Self) -> Self {
+                    <$ty>::saturating_add(self, rhs)
+                }
+
+                fn checked_add(self, rhs: Self) -> Option<Self> {
+                    <$ty>::checked_add(self, rhs)
+                }
+
+                fn to_u128(self) -> u128 {
+                    self as u128
+                }
+
+                fn from_u128(value: u128) -> Self {
+                    value as $ty
+                }
+            }
+        )*
+    };
+}
+
+impl_bus_address!(u8, u16, u32, u64, u128, usize);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_u128_truncates() {
+        assert_eq!(u16::from_u128(0x1_2345), 0x2345);
+        assert_eq!(<u8 as BusAddress>::MAX.to_u128(), 0xff);
+        assert_eq!(BusAddress::checked_add(0xffffu16, 1), None);
+    }
+}
@@ -0,0 +1,205 @@
+use super::{AccessKind, BusAddress, Endianness, MemoryMap, MemoryMapError};
+
+/// What happens to a multi-byte access at an address that isn't a multiple of its width.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AlignmentPolicy {
+    /// Access the consecutive bytes from the address, the default.
+    #[default]
+    Allow,
+    /// Refuse the access with `MemoryMapError::Unaligned`.
+    Fault,
+    /// Stay within the naturally aligned block containing the address, wrapping around to its
+    /// start, like the rotated loads of early ARM cores.
+    Wrap,
+}
+
+impl<A: BusAddress> MemoryMap<A> {
+    pub fn set_alignment_policy(&mut self, policy: AlignmentPolicy) {
+        self.alignment = policy;
+    }
+
+    /// Makes every misaligned multi-byte access panic, naming the access, to catch CPU tests
+    /// that issue them by accident. Only debug builds check; release builds ignore the setting.
+    /// The panic comes before the alignment policy is applied.
+    pub fn debug_check_alignment(&mut self, check: bool) {
+        self.debug_alignment = check;
+    }
+
+    #[cfg(debug_assertions)]
+    fn check_alignment(&self, width: u8, kind: AccessKind) {
+        if self.debug_alignment {
+            panic!(
+                "Misaligned {}-byte {:?} at {:#x}.",
+                width, kind, self.current_addr
+            );
+        }
+    }
+
+    #[cfg(not(debug_assertions))]
+    fn check_alignment(&self, _width: u8, _kind: AccessKind) {}
+
+    /// The selected address's offset into the naturally aligned `width`-byte block holding it.
+    pub(super) fn misalignment(&self, width: u8) -> u8 {
+        (self.current_addr.to_u128() % width as u128) as u8
+    }
+
+    /// Applies the alignment policy to a `width`-byte read, returning its result if the policy
+    /// handled the access itself.
+    pub(super) fn align_read(&mut self, width: u8) -> Option<Result<u128, MemoryMapError>> {
+        let offset = self.misalignment(width);
+        if offset == 0 {
+            return None;
+        }
+        self.check_alignment(width, AccessKind::Read);
+        match self.alignment {
+            AlignmentPolicy::Allow => None,
+            AlignmentPolicy::Fault => Some(Err(self.unaligned(width))),
+            AlignmentPolicy::Wrap => Some(self.read_wrapped(width, offset)),
+        }
+    }
+
+    /// Applies the alignment policy to a `width`-byte write, returning its result if the policy
+    /// handled the access itself.
+    pub(super) fn align_write(
+        &mut self,
+        width: u8,
+        data: u128,
+    ) -> Option<Result<(), MemoryMapError>> {
+        let offset = self.misalignment(width);
+        if offset == 0 {
+            return None;
+        }
+        self.check_alignment(width, AccessKind::Write);
+        match self.alignment {
+            AlignmentPolicy::Allow => None,
+            AlignmentPolicy::Fault => Some(Err(self.unaligned(width))),
+            AlignmentPolicy::Wrap => Some(self.write_wrapped(width, offset, data)),
+        }
+    }
+
+    fn unaligned(&self, width: u8) -> MemoryMapError {
+        MemoryMapError::Unaligned {
+            addr: self.current_addr.to_u128(),
+            width,
+        }
+    }
+
+    /// The bit position of the `i`th byte of a `width`-byte value in the map's byte order.
+    fn byte_shift(&self, i: u8, width: u8) -> u32 {
+        match self.endianness {
+            Endianness::Little => i as u32 * 8,
+            Endianness::Big => (width - 1 - i) as u32 * 8,
+        }
+    }
+
+    /// Runs `func` once per byte of the access, with the selected address moved to that byte's
+    /// wrapped position inside the aligned block.
+    fn for_wrapped_bytes<F>(
+        &mut self,
+        width: u8,
+        offset: u8,
+        mut func: F,
+    ) -> Result<(), MemoryMapError>
+    where
+        F: FnMut(&mut MemoryMap<A>, u8) -> Result<(), MemoryMapError>,
+    {
+        let addr = self.current_addr;
+        let block = addr - A::from_u128(offset as u128);
+        let mut result = Ok(());
+        for i in 0..width {
+            let byte_offset = (offset + i) % width;
+            self.current_addr = block + A::from_u128(byte_offset as u128);
+            result = func(self, i);
+            if result.is_err() {
+                break;
+            }
+        }
+        self.current_addr = addr;
+        result
+    }
+
+    fn read_wrapped(&mut self, width: u8, offset: u8) -> Result<u128, MemoryMapError> {
+        let mut data = 0;
+        self.for_wrapped_bytes(width, offset, |map, i| {
+            data |= map.read_width(1)? << map.byte_shift(i, width);
+            Ok(())
+        })?;
+        Ok(data)
+    }
+
+    fn write_wrapped(&mut self, width: u8, offset: u8, data: u128) -> Result<(), MemoryMapError> {
+        self.for_wrapped_bytes(width, offset, |map, i| {
+            map.write_width(1, (data >> map.byte_shift(i, width)) & 0xff)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{Bus, Ram};
+
+    fn build_map(policy: AlignmentPolicy) -> MemoryMap {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x00ff, Ram::new(0x100));
+        map.write_block(0x0000, &[0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77]);
+        map.set_alignment_policy(policy);
+        map
+    }
+
+    #[test]
+    fn test_unaligned_allow() {
+        let mut map = build_map(AlignmentPolicy::Allow);
+        map.select_address(0x0001);
+        assert_eq!(map.try_read_u32(), Ok(0x44332211));
+    }
+
+    #[test]
+    fn test_unaligned_fault() {
+        let mut map = build_map(AlignmentPolicy::Fault);
+        map.select_address(0x0001);
+        assert_eq!(
+            map.try_read_u32(),
+            Err(MemoryMapError::Unaligned {
+                addr: 0x0001,
+                width: 4
+            })
+        );
+        assert_eq!(
+            map.try_write_u16(0xffff),
+            Err(MemoryMapError::Unaligned {
+                addr: 0x0001,
+                width: 2
+            })
+        );
+        assert_eq!(map.try_read_u8(), Ok(0x11));
+        map.select_address(0x0004);
+        assert_eq!(map.try_read_u32(), Ok(0x77665544));
+    }
+
+    #[test]
+    fn test_unaligned_wrap() {
+        let mut map = build_map(AlignmentPolicy::Wrap);
+        map.select_address(0x0001);
+        assert_eq!(map.try_read_u32(), Ok(0x00332211));
+        map.set_endianness(Endianness::Big);
+        assert_eq!(map.try_read_u32(), Ok(0x11223300));
+
+        map.set_endianness(Endianness::Little);
+        map.select_address(0x0007);
+        map.write_u16(0xbbaa);
+        map.select_address(0x0006);
+        assert_eq!(map.read_u16(), 0xaabb);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Misaligned 8-byte Read at 0x4.")]
+    fn test_debug_check_alignment() {
+        let mut map = build_map(AlignmentPolicy::Allow);
+        map.debug_check_alignment(true);
+        map.write_u16_at(0x0002, 0xbeef);
+        assert_eq!(map.read_u64_at(0x0000), 0x7766_5544_beef_1100);
+        map.read_u64_at(0x0004);
+    }
+}
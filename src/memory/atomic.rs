@@ -0,0 +1,60 @@
+use super::{Bus, BusAddress, MemoryMap};
+
+macro_rules! compare_exchange {
+    ($($name:ident, $read:ident, $write:ident, $ty:ty;)*) => {
+        impl<A: BusAddress> MemoryMap<A> {
+            $(
+                /// Reads the value at `addr` and writes `new` there if it equals `expected`, for
+                /// emulating atomic guest instructions. Returns `Ok` with the old value if the
+                /// write happened and `Err` with the value found otherwise. The address stays
+                /// selected afterwards.
+                ///
+                /// Nothing else can access the map between the read and the write. Across
+                /// threads, hold the lock of the `SharedBus` wrapping the map for the same
+                /// guarantee.
+                pub fn $name(&mut self, addr: A, expected: $ty, new: $ty) -> Result<$ty, $ty> {
+                    let old = self.$read(addr);
+                    if old != expected {
+                        return Err(old);
+                    }
+                    self.$write(addr, new);
+                    Ok(old)
+                }
+            )*
+        }
+    };
+}
+
+compare_exchange! {
+    compare_exchange_u8, read_u8_at, write_u8_at, u8;
+    compare_exchange_u16, read_u16_at, write_u16_at, u16;
+    compare_exchange_u32, read_u32_at, write_u32_at, u32;
+    compare_exchange_u64, read_u64_at, write_u64_at, u64;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::Ram;
+
+    #[test]
+    fn test_compare_exchange() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x00ff, Ram::new(0x100));
+        map.write_u32_at(0x0010, 0x1234_5678);
+
+        assert_eq!(
+            map.compare_exchange_u32(0x0010, 0x1234_5678, 0xdead_beef),
+            Ok(0x1234_5678)
+        );
+        assert_eq!(map.read_u32_at(0x0010), 0xdead_beef);
+
+        assert_eq!(
+            map.compare_exchange_u32(0x0010, 0x1234_5678, 0),
+            Err(0xdead_beef)
+        );
+        assert_eq!(map.read_u32_at(0x0010), 0xdead_beef);
+        assert_eq!(map.compare_exchange_u8(0x0010, 0xef, 0x01), Ok(0xef));
+        assert_eq!(map.read_u32_at(0x0010), 0xdead_be01);
+    }
+}
@@ -0,0 +1,268 @@
+use super::{Address, BusAddress, Device, MemoryMap, MemoryMapEntryType, MemoryMapError};
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// A window onto one of several equally sized banks, as used by cartridge mappers.
+///
+/// The region answers for offsets into the window, so it is mapped with relative addressing.
+/// Banks built with [`BankedRegion::new`] are writable; banks loaded with
+/// [`BankedRegion::from_bytes`] are read-only and drop writes.
+#[derive(Debug)]
+pub struct BankedRegion {
+    data: Vec<u8>,
+    bank_size: usize,
+    /// Shared with the [`BankSelector`] handed out when the region is mapped.
+    current: Arc<AtomicUsize>,
+    writable: bool,
+}
+
+/// Switches the bank a mapped [`BankedRegion`] shows, from any thread.
+#[derive(Clone, Debug)]
+pub struct BankSelector {
+    current: Arc<AtomicUsize>,
+    banks: usize,
+}
+
+impl BankSelector {
+    pub fn bank_count(&self) -> usize {
+        self.banks
+    }
+
+    pub fn current_bank(&self) -> usize {
+        self.current.load(Ordering::Relaxed)
+    }
+
+    pub fn select_bank(&self, bank: usize) -> Result<(), MemoryMapError> {
+        check_bank(bank, self.banks)?;
+        self.current.store(bank, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+fn check_bank(bank: usize, banks: usize) -> Result<(), MemoryMapError> {
+    if bank >= banks {
+        return Err(MemoryMapError::BankOutOfRange { bank, banks });
+    }
+    Ok(())
+}
+
+impl BankedRegion {
+    pub fn new(bank_count: usize, bank_size: usize) -> BankedRegion {
+        if bank_count == 0 || bank_size == 0 {
+            panic!("A banked region needs at least one non-empty bank.");
+        }
+        BankedRegion {
+            data: vec![0; bank_count * bank_size],
+            bank_size,
+            current: Arc::new(AtomicUsize::new(0)),
+            writable: true,
+        }
+    }
+
+    /// Splits `data` into read-only banks of `bank_size` bytes. A short final bank is padded
+    /// with zeroes.
+    pub fn from_bytes(mut data: Vec<u8>, bank_size: usize) -> BankedRegion {
+        if data.is_empty() || bank_size == 0 {
+            panic!("A banked region needs at least one non-empty bank.");
+        }
+        let padded = data.len().div_ceil(bank_size) * bank_size;
+        data.resize(padded, 0);
+        BankedRegion {
+            data,
+            bank_size,
+            current: Arc::new(AtomicUsize::new(0)),
+            writable: false,
+        }
+    }
+
+    pub fn bank_count(&self) -> usize {
+        self.data.len() / self.bank_size
+    }
+
+    pub fn bank_size(&self) -> usize {
+        self.bank_size
+    }
+
+    pub fn current_bank(&self) -> usize {
+        self.current.load(Ordering::Relaxed)
+    }
+
+    pub fn select_bank(&mut self, bank: usize) -> Result<(), MemoryMapError> {
+        check_bank(bank, self.bank_count())?;
+        self.current.store(bank, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn index(&self, offset: Address) -> usize {
+        self.current_bank() * self.bank_size + (offset % self.bank_size as Address) as usize
+    }
+}
+
+/// A clone starts out on the same bank but switches banks independently of the original.
+impl Clone for BankedRegion {
+    fn clone(&self) -> Self {
+        BankedRegion {
+            data: self.data.clone(),
+            bank_size: self.bank_size,
+            current: Arc::new(AtomicUsize::new(self.current_bank())),
+            writable: self.writable,
+        }
+    }
+}
+
+impl<A: BusAddress> Device<A> for BankedRegion {
+    fn read(&mut self, addr: A, _width: u8) -> u128 {
+        self.data[self.index(addr.to_u128())] as u128
+    }
+
+    fn write(&mut self, addr: A, _width: u8, data: u128) {
+        if self.writable {
+            let index = self.index(addr.to_u128());
+            self.data[index] = data as u8;
+        }
+    }
+
+    fn max_width(&self) -> u8 {
+        1
+    }
+
+    fn peek(&self, addr: A) -> Option<u8> {
+        Some(self.data[self.index(addr.to_u128())])
+    }
+
+    /// Changes the selected bank even if the banks are read-only.
+    fn poke(&mut self, addr: A, data: u8) -> bool {
+        let index = self.index(addr.to_u128());
+        self.data[index] = data;
+        true
+    }
+
+    fn try_clone(&self) -> Option<Box<dyn Device<A> + Send>> {
+        Some(Box::new(self.clone()))
+    }
+
+    /// The selected bank, followed by the contents of every bank when they are writable.
+    fn save_state(&self) -> Option<Vec<u8>> {
+        let mut state = (self.current_bank() as u64).to_le_bytes().to_vec();
+        if self.writable {
+            state.extend_from_slice(&self.data);
+        }
+        Some(state)
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> bool {
+        if state.len() < 8 {
+            return false;
+        }
+        let (bank, data) = state.split_at(8);
+        let bank = u64::from_le_bytes(bank.try_into().unwrap()) as usize;
+        let expected = if self.writable { self.data.len() } else { 0 };
+        if bank >= self.bank_count() || data.len() != expected {
+            return false;
+        }
+        self.current.store(bank, Ordering::Relaxed);
+        self.data[..expected].copy_from_slice(data);
+        true
+    }
+}
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// Maps a window of one bank's size at `start` onto `region`, returning a handle through which
+    /// the selected bank can be changed later.
+    ///
+    /// Panics if a bank is bigger than the address space or the window would run past its end.
+    pub fn map_banked(&mut self, start: A, region: BankedRegion) -> BankSelector {
+        let last_offset = region.bank_size as u128 - 1;
+        if last_offset > A::MAX.to_u128() {
+            panic!(
+                "A bank of {:#x} bytes doesn't fit the address space.",
+                region.bank_size
+            );
+        }
+        let end = match start.checked_add(A::from_u128(last_offset)) {
+            Some(end) => end,
+            None => panic!(
+                "Bank window of {:#x} bytes at {:#x} runs past the end of the address space.",
+                region.bank_size, start
+            ),
+        };
+        let entry_type = if region.writable {
+            MemoryMapEntryType::ReadWrite
+        } else {
+            MemoryMapEntryType::Read
+        };
+        let selector = BankSelector {
+            current: region.current.clone(),
+            banks: region.bank_count(),
+        };
+        let entry = self.map_region(start, end, entry_type);
+        entry.set_device(region);
+        entry.set_entry_type(entry_type);
+        entry.set_relative_addressing(true);
+        selector
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::Bus;
+
+    #[test]
+    fn test_bank_switching() {
+        let data: Vec<u8> = (0..4).flat_map(|bank| vec![bank * 0x11; 0x10]).collect();
+        let mut map = MemoryMap::new();
+        let banks = map.map_banked(0x8000, BankedRegion::from_bytes(data, 0x10));
+        assert_eq!(banks.bank_count(), 4);
+
+        map.select_address(0x8004);
+        assert_eq!(map.read_u8(), 0x00);
+        banks.select_bank(2).unwrap();
+        assert_eq!(banks.current_bank(), 2);
+        assert_eq!(map.read_u16(), 0x2222);
+        map.write_u8(0xff);
+        assert_eq!(map.read_u8(), 0x22);
+        banks.select_bank(3).unwrap();
+        map.select_address(0x800f);
+        assert_eq!(map.read_u8(), 0x33);
+        map.select_address(0x8010);
+        assert_eq!(map.read_u8(), 0x00);
+
+        assert_eq!(
+            banks.select_bank(4),
+            Err(MemoryMapError::BankOutOfRange { bank: 4, banks: 4 })
+        );
+        assert_eq!(banks.current_bank(), 3);
+    }
+
+    #[test]
+    fn test_writable_banks() {
+        let mut map = MemoryMap::new();
+        let banks = map.map_banked(0x6000, BankedRegion::new(2, 0x2000));
+        map.select_address(0x6010);
+        map.write_u8(0xaa);
+        banks.select_bank(1).unwrap();
+        assert_eq!(map.read_u8(), 0x00);
+        map.write_u8(0xbb);
+        banks.select_bank(0).unwrap();
+        assert_eq!(map.read_u8(), 0xaa);
+    }
+
+    #[test]
+    #[should_panic(expected = "runs past the end of the address space")]
+    fn test_banked_at_top() {
+        let mut map = MemoryMap::new();
+        map.map_banked(u128::MAX - 7, BankedRegion::new(2, 0x10));
+    }
+
+    #[test]
+    #[should_panic(expected = "doesn't fit the address space")]
+    fn test_bank_too_big() {
+        let mut map = MemoryMap::<u16>::default();
+        map.map_banked(0, BankedRegion::new(1, 0x2_0000));
+    }
+}
@@ -0,0 +1,165 @@
+use super::{
+    Address, BusAddress, Device, Endianness, MemoryMap, MemoryMapEntryType, MemoryMapError, Ram,
+    RegionSpec, Rom,
+};
+use alloc::vec::Vec;
+
+/// Describes a whole map up front and builds it in one go, checking the layout as it does.
+pub struct MemoryMapBuilder<A: BusAddress = Address> {
+    regions: Vec<RegionSpec<A>>,
+    endianness: Endianness,
+    mask: A,
+}
+
+impl MemoryMapBuilder {
+    pub fn new() -> MemoryMapBuilder {
+        MemoryMapBuilder::default()
+    }
+}
+
+impl<A: BusAddress> Default for MemoryMapBuilder<A> {
+    fn default() -> Self {
+        MemoryMapBuilder {
+            regions: Vec::new(),
+            endianness: Endianness::Little,
+            mask: A::MAX,
+        }
+    }
+}
+
+impl<A: BusAddress> MemoryMapBuilder<A> {
+    /// Adds `len` bytes of zeroed RAM at `start`.
+    ///
+    /// Panics if `len` is zero or the RAM would run past the top of the address space.
+    pub fn ram(self, start: A, len: usize) -> MemoryMapBuilder<A> {
+        let end = last_address(start, len);
+        self.region(
+            RegionSpec::new(start, end, MemoryMapEntryType::ReadWrite)
+                .with_device(Ram::new(len))
+                .relative(true),
+        )
+    }
+
+    /// Adds a ROM holding `bytes` at `start`.
+    ///
+    /// Panics if `bytes` is empty or runs past the top of the address space.
+    pub fn rom(self, start: A, bytes: Vec<u8>) -> MemoryMapBuilder<A> {
+        let end = last_address(start, bytes.len());
+        self.region(
+            RegionSpec::new(start, end, MemoryMapEntryType::Read)
+                .with_device(Rom::from_bytes(start.to_u128(), bytes)),
+        )
+    }
+
+    /// Routes `start..=end` to `device`, which sees bus addresses.
+    pub fn device<D>(self, start: A, end: A, device: D) -> MemoryMapBuilder<A>
+    where
+        D: Device<A> + Send + 'static,
+    {
+        self.region(RegionSpec::new(start, end, MemoryMapEntryType::ReadWrite).with_device(device))
+    }
+
+    /// Adds any region, for what the shorthands don't cover.
+    pub fn region(mut self, spec: RegionSpec<A>) -> MemoryMapBuilder<A> {
+        self.regions.push(spec);
+        self
+    }
+
+    pub fn endianness(mut self, endianness: Endianness) -> MemoryMapBuilder<A> {
+        self.endianness = endianness;
+        self
+    }
+
+    /// Sets the global address mask, as [`MemoryMap::set_address_mask`] does.
+    pub fn mask(mut self, mask: A) -> MemoryMapBuilder<A> {
+        self.mask = mask;
+        self
+    }
+
+    /// Builds the map, failing like [`MemoryMap::from_regions`] if a region overlaps one added
+    /// before it or has its start past its end.
+    pub fn build(self) -> Result<MemoryMap<A>, MemoryMapError> {
+        let mut map = MemoryMap::from_regions(self.regions)?;
+        map.set_endianness(self.endianness);
+        map.set_address_mask(self.mask);
+        Ok(map)
+    }
+}
+
+/// The last address of `len` bytes from `start`.
+fn last_address<A: BusAddress>(start: A, len: usize) -> A {
+    if len == 0 {
+        panic!("Memory region must be at least one byte long.");
+    }
+    let span = (len - 1) as u128;
+    let end = if span <= A::MAX.to_u128() {
+        start.checked_add(A::from_u128(span))
+    } else {
+        None
+    };
+    match end {
+        Some(end) => end,
+        None => panic!(
+            "Memory region of {:#x} bytes at {:#x} runs past the end of the address space.",
+            len, start
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{Bus, IoPort};
+    use alloc::vec;
+
+    #[test]
+    fn test_builder() {
+        let mut map = MemoryMapBuilder::new()
+            .ram(0x0000, 0x0800)
+            .rom(0xfff0, vec![0x00, 0x80, 0x34, 0x12])
+            .device(0x4000, 0x4001, IoPort::new(1))
+            .endianness(Endianness::Big)
+            .mask(0xffff)
+            .build()
+            .unwrap();
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.read_u16_at(0xfff0), 0x0080);
+        map.write_u16_at(0x07fe, 0xbeef);
+        assert_eq!(map.read_u8_at(0x07ff), 0xef);
+        // Mirrored by the 16-bit mask.
+        assert_eq!(map.read_u16_at(0x1_fff2), 0x3412);
+        map.write_u8_at(0x4001, 0xff);
+        assert_eq!(map.read_u8_at(0x4001), 0xff);
+    }
+
+    #[test]
+    fn test_builder_rejects_overlap() {
+        let result = MemoryMapBuilder::new()
+            .ram(0x0000, 0x1000)
+            .rom(0x0800, vec![0; 0x100])
+            .build();
+        assert_eq!(
+            result.err(),
+            Some(MemoryMapError::RegionOverlap {
+                start: 0x0000,
+                end: 0x0fff
+            })
+        );
+    }
+
+    #[test]
+    fn test_builder_full_space() {
+        let mut map = MemoryMapBuilder::<u16>::default()
+            .ram(0x0000, 0x1_0000)
+            .build()
+            .unwrap();
+        map.write_u8_at(0xffff, 0x42);
+        assert_eq!(map.read_u8_at(0xffff), 0x42);
+    }
+
+    #[test]
+    #[should_panic(expected = "Memory region of 0x10001 bytes at 0x0 runs past the end")]
+    fn test_builder_ram_too_big() {
+        let _ = MemoryMapBuilder::<u16>::default().ram(0x0000, 0x1_0001);
+    }
+}
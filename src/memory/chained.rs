@@ -0,0 +1,142 @@
+use super::{Address, Bus, BusAddress};
+
+/// Routes each access to `primary` when it handles the selected address and to `fallback`
+/// otherwise, e.g. a fast overlay in front of the full memory map.
+pub struct ChainedBus<P: Bus<A>, F: Bus<A>, A: BusAddress = Address> {
+    primary: P,
+    fallback: F,
+    addr: A,
+}
+
+impl<P: Bus<A>, F: Bus<A>, A: BusAddress> ChainedBus<P, F, A> {
+    pub fn new(primary: P, fallback: F) -> ChainedBus<P, F, A> {
+        ChainedBus {
+            primary,
+            fallback,
+            addr: A::ZERO,
+        }
+    }
+
+    pub fn primary_mut(&mut self) -> &mut P {
+        &mut self.primary
+    }
+
+    pub fn fallback_mut(&mut self) -> &mut F {
+        &mut self.fallback
+    }
+
+    pub fn into_inner(self) -> (P, F) {
+        (self.primary, self.fallback)
+    }
+
+    fn route(&mut self) -> &mut dyn Bus<A> {
+        if self.primary.handles(self.addr) {
+            &mut self.primary
+        } else {
+            &mut self.fallback
+        }
+    }
+}
+
+impl<P: Bus<A>, F: Bus<A>, A: BusAddress> Bus<A> for ChainedBus<P, F, A> {
+    fn select_address(&mut self, addr: A) {
+        self.addr = addr;
+        self.primary.select_address(addr);
+        self.fallback.select_address(addr);
+    }
+
+    fn handles(&self, addr: A) -> bool {
+        self.primary.handles(addr) || self.fallback.handles(addr)
+    }
+
+    fn read_u8(&mut self) -> u8 {
+        self.route().read_u8()
+    }
+
+    fn read_u16(&mut self) -> u16 {
+        self.route().read_u16()
+    }
+
+    fn read_u32(&mut self) -> u32 {
+        self.route().read_u32()
+    }
+
+    fn read_u64(&mut self) -> u64 {
+        self.route().read_u64()
+    }
+
+    fn read_u128(&mut self) -> u128 {
+        self.route().read_u128()
+    }
+
+    fn write_u8(&mut self, data: u8) {
+        self.route().write_u8(data)
+    }
+
+    fn write_u16(&mut self, data: u16) {
+        self.route().write_u16(data)
+    }
+
+    fn write_u32(&mut self, data: u32) {
+        self.route().write_u32(data)
+    }
+
+    fn write_u64(&mut self, data: u64) {
+        self.route().write_u64(data)
+    }
+
+    fn write_u128(&mut self, data: u128) {
+        self.route().write_u128(data)
+    }
+
+    fn fetch_u8(&mut self) -> u8 {
+        self.route().fetch_u8()
+    }
+
+    fn fetch_u16(&mut self) -> u16 {
+        self.route().fetch_u16()
+    }
+
+    fn fetch_u32(&mut self) -> u32 {
+        self.route().fetch_u32()
+    }
+
+    fn fetch_u64(&mut self) -> u64 {
+        self.route().fetch_u64()
+    }
+
+    fn fetch_u128(&mut self) -> u128 {
+        self.route().fetch_u128()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{MemoryMap, MemoryMapEntryType, Ram, Rom};
+
+    #[test]
+    fn test_chained_bus() {
+        let mut overlay = MemoryMap::new();
+        overlay.map_rom(Rom::from_bytes(0x00, vec![0xaa; 0x10]));
+        let mut memory = MemoryMap::new();
+        memory.map_ram(0x00, 0xff, Ram::new(0x100));
+        memory.map_region(0x100, 0x1ff, MemoryMapEntryType::UnmappedHigh);
+        assert!(overlay.handles(0x0f));
+        assert!(!overlay.handles(0x10));
+
+        let mut bus = ChainedBus::new(overlay, memory);
+        bus.select_address(0x08);
+        bus.write_u8(0x11);
+        assert_eq!(bus.read_u8(), 0xaa);
+        bus.select_address(0x10);
+        bus.write_u8(0x22);
+        assert_eq!(bus.read_u8(), 0x22);
+        assert!(bus.handles(0x1ff));
+        assert!(!bus.handles(0x200));
+
+        let (_, mut memory) = bus.into_inner();
+        memory.select_address(0x08);
+        assert_eq!(memory.read_u8(), 0x00);
+    }
+}
@@ -0,0 +1,138 @@
+use super::{search_entries, AccessKind, Address, BusAddress, MemoryMap, MemoryMapEntryType};
+use super::{MemoryMapError, WatchpointId};
+use alloc::boxed::Box;
+
+/// How much a [`BusEvent`] matters, ordered like the levels of logging crates so a hook can
+/// map them straight onto its logger's.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EventLevel {
+    Debug,
+    Info,
+    Warn,
+}
+
+/// Something noteworthy the map saw, passed to the hook set with [`MemoryMap::set_event_hook`].
+#[derive(Debug, PartialEq)]
+pub enum BusEvent<'a, A = Address> {
+    /// A read, write or fetch failed with this error. Logged at `Warn`.
+    Fault(&'a MemoryMapError),
+    /// A `width`-byte access starting at `addr` reached unmapped space. Logged at `Info`.
+    Unmapped {
+        addr: A,
+        width: u8,
+        kind: AccessKind,
+    },
+    /// The watchpoint `id` fired for an access at `addr` carrying `data`. Logged at `Debug`.
+    WatchpointHit {
+        id: WatchpointId,
+        addr: A,
+        kind: AccessKind,
+        data: u128,
+    },
+}
+
+impl<A> BusEvent<'_, A> {
+    pub fn level(&self) -> EventLevel {
+        match self {
+            BusEvent::Fault(_) => EventLevel::Warn,
+            BusEvent::Unmapped { .. } => EventLevel::Info,
+            BusEvent::WatchpointHit { .. } => EventLevel::Debug,
+        }
+    }
+}
+
+pub type EventHook<A = Address> = Box<dyn FnMut(&BusEvent<A>) + Send>;
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// Installs a hook told of faults, accesses to unmapped space and watchpoint hits, or
+    /// removes it with `None`, for emulators that forward them to a log aggregator such as
+    /// `tracing`. Unlike the trace hook it sees no ordinary accesses, and unlike the fault
+    /// handler it only observes: failed accesses behave the same with or without it.
+    ///
+    /// Faults and unmapped accesses are reported for single reads, writes and fetches, whether
+    /// through the infallible or the `try_` accesses, but not for block operations. While no
+    /// hook is set, checking for one is all it costs.
+    pub fn set_event_hook(&mut self, hook: Option<EventHook<A>>) {
+        self.event_hook = hook;
+    }
+
+    /// Tells the event hook how a `width`-byte access at the selected address went.
+    pub(super) fn report_access(
+        &mut self,
+        width: u8,
+        kind: AccessKind,
+        err: Option<&MemoryMapError>,
+    ) {
+        if self.event_hook.is_none() {
+            return;
+        }
+        let addr = self.current_addr;
+        let unmapped = err.is_none() && {
+            let index = search_entries(self);
+            matches!(
+                self.entries[index].entry_type,
+                MemoryMapEntryType::UnmappedLow
+                    | MemoryMapEntryType::UnmappedHigh
+                    | MemoryMapEntryType::UnmappedOpenBus
+            )
+        };
+        let hook = self.event_hook.as_mut().unwrap();
+        match err {
+            Some(err) => hook(&BusEvent::Fault(err)),
+            None if unmapped => hook(&BusEvent::Unmapped { addr, width, kind }),
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{Bus, Ram};
+    use std::sync::{Arc, Mutex};
+    use std::vec::Vec;
+
+    #[test]
+    fn test_event_hook() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let log = events.clone();
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x00ff, Ram::new(0x100));
+        map.map_region(0x1000, 0x10ff, MemoryMapEntryType::Read);
+        map.set_strict_permissions(true);
+        map.set_event_hook(Some(Box::new(move |event: &BusEvent| {
+            let line = match event {
+                BusEvent::Fault(err) => format!("{}", err),
+                BusEvent::Unmapped { addr, width, .. } => format!("unmapped {:#x}/{}", addr, width),
+                BusEvent::WatchpointHit { addr, data, .. } => {
+                    format!("watch {:#x}={:#x}", addr, data)
+                }
+            };
+            log.lock().unwrap().push((event.level(), line));
+        })));
+        let id = map.add_watchpoint(0x0010, 0x0010, AccessKind::Write, Box::new(|_, _, _| {}));
+
+        map.write_u8_at(0x0010, 0x42);
+        map.read_u16_at(0x0020);
+        map.read_u16_at(0x8000);
+        map.select_address(0x1000);
+        let err = map.try_write_u8(0x00).unwrap_err();
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                (EventLevel::Debug, "watch 0x10=0x42".into()),
+                (EventLevel::Info, "unmapped 0x8000/2".into()),
+                (EventLevel::Warn, format!("{}", err)),
+            ]
+        );
+        assert!(matches!(
+            err,
+            MemoryMapError::WriteToReadOnly { addr: 0x1000, .. }
+        ));
+        assert!(map.remove_watchpoint(id));
+
+        map.set_event_hook(None);
+        map.read_u8_at(0x8000);
+        assert_eq!(events.lock().unwrap().len(), 3);
+    }
+}
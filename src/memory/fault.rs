@@ -0,0 +1,153 @@
+use super::{search_entries, BusAddress, MemoryMap, MemoryMapEntryType, MemoryMapError};
+use alloc::boxed::Box;
+
+/// Handles a read that failed through the infallible `Bus` interface, and writes dropped in
+/// unmapped space if those are reported.
+pub type FaultHandler = Box<dyn FnMut(MemoryMapError) + Send>;
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// Calls `handler` with the error when an infallible read, or fetch, fails, instead of
+    /// panicking. Once the handler returns, the read yields what unmapped space would: all ones
+    /// if the address lies in an `UnmappedHigh` region and zeroes otherwise. Failed infallible
+    /// writes are dropped either way.
+    ///
+    /// The `try_` accesses never panic, whatever the address, width and policies, so they and
+    /// a fault handler are what a fuzz target should drive the map through. `read_block` still
+    /// panics on a broken map; use `try_read_block` there.
+    pub fn set_fault_handler(&mut self, handler: FaultHandler) {
+        self.fault_handler = Some(handler);
+    }
+
+    /// Reports every write to an unmapped region to the fault handler as `UnmappedWrite`, to catch
+    /// stray stores. The writes are still dropped, and nothing is reported without a handler.
+    /// Off by default.
+    pub fn set_report_unmapped_writes(&mut self, report: bool) {
+        self.report_unmapped_writes = report;
+    }
+
+    /// Whether the entry at `index` is unmapped, so a write to it goes nowhere, reporting the
+    /// write if asked to.
+    pub(super) fn drops_unmapped_write(
+        &mut self,
+        index: usize,
+        addr: A,
+        width: u8,
+        data: u128,
+    ) -> bool {
+        let unmapped = matches!(
+            self.entries[index].entry_type,
+            MemoryMapEntryType::UnmappedLow
+                | MemoryMapEntryType::UnmappedHigh
+                | MemoryMapEntryType::UnmappedOpenBus
+        );
+        if unmapped && self.report_unmapped_writes {
+            if let Some(handler) = self.fault_handler.as_mut() {
+                handler(MemoryMapError::UnmappedWrite {
+                    addr: addr.to_u128(),
+                    width,
+                    data,
+                });
+            }
+        }
+        unmapped
+    }
+
+    /// Unwraps the result of a `width`-byte read, handing an error to the fault handler or
+    /// panicking if there is none.
+    pub(super) fn unwrap_read(&mut self, result: Result<u128, MemoryMapError>, width: u8) -> u128 {
+        let err = match result {
+            Ok(data) => return data,
+            Err(err) => err,
+        };
+        let handler = match self.fault_handler.as_mut() {
+            Some(handler) => handler,
+            None => panic!("Your memory map is broken. Please fix it! {:?}", err),
+        };
+        handler(err);
+        let index = search_entries(self);
+        match self.entries[index].entry_type {
+            MemoryMapEntryType::UnmappedHigh if width == 16 => u128::MAX,
+            MemoryMapEntryType::UnmappedHigh => (1 << (width as u32 * 8)) - 1,
+            _ => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{Bus, Ram};
+    use std::sync::{Arc, Mutex};
+    use std::vec::Vec;
+
+    #[test]
+    fn test_fault_handler() {
+        let faults = Arc::new(Mutex::new(Vec::new()));
+        let log = faults.clone();
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x00ff, Ram::new(0x100));
+        map.map_region(0x1000, 0x1fff, MemoryMapEntryType::ReadWrite);
+        map.map_region(0x2000, 0x2fff, MemoryMapEntryType::UnmappedHigh);
+        map.set_fault_handler(Box::new(move |err| log.lock().unwrap().push(err)));
+
+        assert_eq!(map.read_u16_at(0x1000), 0);
+        map.set_alignment_policy(crate::memory::AlignmentPolicy::Fault);
+        assert_eq!(map.read_u32_at(0x2001), 0xffff_ffff);
+        assert_eq!(map.read_u8_at(0x0001), 0);
+        assert_eq!(
+            *faults.lock().unwrap(),
+            [
+                MemoryMapError::MissingDelegate {
+                    addr: 0x1000,
+                    width: 1,
+                    region: None
+                },
+                MemoryMapError::Unaligned {
+                    addr: 0x2001,
+                    width: 4
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_report_unmapped_writes() {
+        let faults = Arc::new(Mutex::new(Vec::new()));
+        let log = faults.clone();
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x00ff, Ram::new(0x100));
+        map.map_region(0x2000, 0x2fff, MemoryMapEntryType::UnmappedHigh);
+        map.set_fault_handler(Box::new(move |err| log.lock().unwrap().push(err)));
+        map.write_u8_at(0x1000, 0x11);
+        assert!(faults.lock().unwrap().is_empty());
+
+        map.set_report_unmapped_writes(true);
+        map.write_u8_at(0x0010, 0x22);
+        map.write_u16_at(0x2010, 0xbeef);
+        // Only the half past the RAM is reported.
+        map.write_u16_at(0x00ff, 0x3344);
+        map.select_address(0x2010);
+        assert_eq!(map.try_write_u8(0x55), Ok(()));
+        assert_eq!(map.read_u16_at(0x2010), 0xffff);
+        assert_eq!(
+            *faults.lock().unwrap(),
+            [
+                MemoryMapError::UnmappedWrite {
+                    addr: 0x2010,
+                    width: 2,
+                    data: 0xbeef
+                },
+                MemoryMapError::UnmappedWrite {
+                    addr: 0x0100,
+                    width: 1,
+                    data: 0x33
+                },
+                MemoryMapError::UnmappedWrite {
+                    addr: 0x2010,
+                    width: 1,
+                    data: 0x55
+                },
+            ]
+        );
+    }
+}
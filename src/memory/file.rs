@@ -0,0 +1,286 @@
+use super::{
+    Address, BusAddress, BusResponse, Device, MemoryMap, MemoryMapEntry, MemoryMapEntryType,
+};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// Memory backed by the start of a file, for large ROM images or battery-backed NVRAM. The
+/// contents are read in when the file is opened, and every write to a writable region is
+/// written through to the file, so it persists without an explicit save.
+///
+/// If writing to the file fails, e.g. because the disk is full, the region stops writing
+/// through and carries on as plain RAM, keeping the error for [`FileRegion::error`]. The write
+/// that failed answers with a bus error, so the `try_` writes notice.
+///
+/// Requires the `std` feature.
+#[derive(Debug)]
+pub struct FileRegion {
+    base: Address,
+    data: Vec<u8>,
+    writable: bool,
+    /// The file written through to, while the region is writable and writing to it works.
+    file: Option<File>,
+    error: Option<io::Error>,
+}
+
+impl FileRegion {
+    /// Opens the first `len` bytes of the file at `path`, refusing a file shorter than that.
+    /// A read-only region never opens the file for writing.
+    pub fn open<P: AsRef<Path>>(path: P, len: usize, writable: bool) -> io::Result<FileRegion> {
+        if len == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "A file region must be at least one byte long.",
+            ));
+        }
+        let mut file = OpenOptions::new().read(true).write(writable).open(path)?;
+        let mut data = vec![0; len];
+        let mut read = 0;
+        while read < len {
+            match file.read(&mut data[read..]) {
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "The file is shorter than its region.",
+                    ))
+                }
+                Ok(n) => read += n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(FileRegion {
+            base: 0,
+            data,
+            writable,
+            file: if writable { Some(file) } else { None },
+            error: None,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn is_writable(&self) -> bool {
+        self.writable
+    }
+
+    /// Whether writing through to the file has failed, so later writes no longer persist.
+    pub fn failed(&self) -> bool {
+        self.error.is_some()
+    }
+
+    /// The error writing through to the file failed with, if it has.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    fn index(&self, addr: Address) -> usize {
+        (addr.wrapping_sub(self.base) % self.data.len() as Address) as usize
+    }
+
+    /// Writes `bytes` to the file at `offset`, returning whether that worked. On the first
+    /// failure the error is kept and the file let go of, as its contents can no longer be
+    /// trusted to match the region's.
+    fn write_through(&mut self, offset: usize, bytes: &[u8]) -> bool {
+        let file = match self.file.as_mut() {
+            Some(file) => file,
+            None => return true,
+        };
+        let written = file
+            .seek(SeekFrom::Start(offset as u64))
+            .and_then(|_| file.write_all(bytes));
+        match written {
+            Ok(()) => true,
+            Err(err) => {
+                self.error = Some(err);
+                self.file = None;
+                false
+            }
+        }
+    }
+
+    /// Stores a byte written through the bus, returning whether it reached the file too.
+    fn store(&mut self, addr: Address, data: u8) -> bool {
+        if !self.writable {
+            return true;
+        }
+        let index = self.index(addr);
+        self.data[index] = data;
+        self.write_through(index, &[data])
+    }
+}
+
+impl<A: BusAddress> Device<A> for FileRegion {
+    fn read(&mut self, addr: A, _width: u8) -> u128 {
+        self.data[self.index(addr.to_u128())] as u128
+    }
+
+    fn write(&mut self, addr: A, _width: u8, data: u128) {
+        self.store(addr.to_u128(), data as u8);
+    }
+
+    /// Answers a write whose write-through failed with a bus error.
+    fn respond_write(&mut self, addr: A, _width: u8, data: u128) -> BusResponse {
+        if self.store(addr.to_u128(), data as u8) {
+            BusResponse::Ack
+        } else {
+            BusResponse::Error
+        }
+    }
+
+    fn max_width(&self) -> u8 {
+        1
+    }
+
+    fn save_state(&self) -> Option<Vec<u8>> {
+        Some(self.data.clone())
+    }
+
+    /// Restores the contents, writing them to the file too if it is writable.
+    fn load_state(&mut self, state: &[u8]) -> bool {
+        if state.len() != self.data.len() {
+            return false;
+        }
+        self.data.copy_from_slice(state);
+        self.write_through(0, state);
+        true
+    }
+
+    fn peek(&self, addr: A) -> Option<u8> {
+        Some(self.data[self.index(addr.to_u128())])
+    }
+
+    /// Patches the contents, and the file if it is writable.
+    fn poke(&mut self, addr: A, data: u8) -> bool {
+        let index = self.index(addr.to_u128());
+        self.data[index] = data;
+        self.write_through(index, &[data]);
+        true
+    }
+
+    fn backing(&self) -> Option<(u128, usize)> {
+        Some((self.base, self.data.len()))
+    }
+
+    fn bytes(&self) -> Option<&[u8]> {
+        Some(&self.data)
+    }
+}
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// Maps `start..=end` to the start of the file at `path`, as a `ReadWrite` region whose
+    /// writes persist to the file if `writable` is set and a `Read` one otherwise. Fails if
+    /// `start` is past `end` or the file can't be opened or is shorter than the region.
+    ///
+    /// Nothing is buffered: each byte written costs a seek and a write to the file, so a guest
+    /// writing the region heavily runs at the speed of the file system rather than of RAM.
+    /// Reads cost nothing beyond RAM.
+    pub fn map_file_region<P: AsRef<Path>>(
+        &mut self,
+        start: A,
+        end: A,
+        path: P,
+        writable: bool,
+    ) -> io::Result<&mut MemoryMapEntry<A>> {
+        if start > end {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The region's start is past its end.",
+            ));
+        }
+        let len = (end - start)
+            .to_u128()
+            .checked_add(1)
+            .and_then(|len| usize::try_from(len).ok())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "The region is too big to load.",
+                )
+            })?;
+        let mut region = FileRegion::open(path, len, writable)?;
+        region.base = start.to_u128();
+        let entry_type = if writable {
+            MemoryMapEntryType::ReadWrite
+        } else {
+            MemoryMapEntryType::Read
+        };
+        Ok(self.map_region(start, end, entry_type).on_device(region))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::Bus;
+    use std::{env, fs, process};
+
+    #[test]
+    fn test_file_region() {
+        let path = env::temp_dir().join(format!("rustemu-file-{}.bin", process::id()));
+        fs::write(&path, [0x11, 0x22, 0x33, 0x44, 0x55]).unwrap();
+
+        let mut map = MemoryMap::new();
+        map.map_file_region(0x1000, 0x1003, &path, true).unwrap();
+        assert_eq!(map.read_u32_at(0x1000), 0x4433_2211);
+        map.write_u16_at(0x1001, 0xbeef);
+        assert_eq!(map.read_u32_at(0x1000), 0x44be_ef11);
+        // The byte past the region is left alone.
+        assert_eq!(fs::read(&path).unwrap(), [0x11, 0xef, 0xbe, 0x44, 0x55]);
+
+        let mut rom = MemoryMap::new();
+        rom.map_file_region(0x0000, 0x0004, &path, false).unwrap();
+        rom.write_u8_at(0x0000, 0x00);
+        assert_eq!(rom.read_u8_at(0x0000), 0x11);
+        let err = rom
+            .map_file_region(0x2000, 0x2005, &path, false)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        assert_eq!(
+            rom.map_file_region(0x3000, 0x2fff, &path, false)
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::InvalidInput
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_failed_write_through() {
+        use crate::memory::MemoryMapError;
+        use std::sync::{Arc, Mutex};
+
+        // Reads as zeroes, and every write to it fails as if the disk were full.
+        let region = Arc::new(Mutex::new(FileRegion::open("/dev/full", 4, true).unwrap()));
+        let mut map = MemoryMap::new();
+        map.map_region(0x0000, 0x0003, MemoryMapEntryType::ReadWrite)
+            .on_device(region.clone());
+        map.select_address(0x0001);
+        assert_eq!(
+            map.try_write_u8(0x42),
+            Err(MemoryMapError::BusError { addr: 0x0001 })
+        );
+        assert!(region.lock().unwrap().failed());
+        assert_eq!(
+            region.lock().unwrap().error().unwrap().raw_os_error(),
+            Some(28)
+        );
+        // The region carries on as RAM.
+        assert_eq!(map.try_write_u8(0x43), Ok(()));
+        assert_eq!(map.read_u8_at(0x0001), 0x43);
+        assert!(region.lock().unwrap().is_writable());
+    }
+}
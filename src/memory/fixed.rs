@@ -0,0 +1,196 @@
+use super::{Address, Bus, MemoryMapEntry, MemoryMapEntryType, MemoryMapError};
+
+/// A memory map holding at most `N` regions in an array, which can be laid out in a `const` so
+/// building it needs no allocation.
+///
+/// It trades most of [`MemoryMap`](super::MemoryMap) for that: regions are searched linearly,
+/// so `N` should stay small, and wider accesses that no single handler takes are assembled from
+/// bytes in little-endian order. The capacity is fixed when the map is declared and nothing can
+/// be mapped beyond it, nor unmapped. There are no aliases, watchpoints, traces, permissions or
+/// statistics, and addresses outside every region read as zero. Devices and delegates are boxed,
+/// so they can only be attached at run time through [`FixedMemoryMap::entry_mut`].
+pub struct FixedMemoryMap<const N: usize> {
+    entries: [MemoryMapEntry; N],
+    len: usize,
+    current_addr: Address,
+}
+
+impl<const N: usize> FixedMemoryMap<N> {
+    const UNUSED: MemoryMapEntry =
+        MemoryMapEntry::with(0, Address::MAX, MemoryMapEntryType::UnmappedLow);
+
+    pub const fn new() -> FixedMemoryMap<N> {
+        FixedMemoryMap {
+            entries: [Self::UNUSED; N],
+            len: 0,
+            current_addr: 0,
+        }
+    }
+
+    /// Adds `entry`, which takes precedence over the regions added before it where they overlap.
+    ///
+    /// Panics, at compile time in a `const`, if the map already holds `N` regions.
+    pub const fn with_region(mut self, entry: MemoryMapEntry) -> FixedMemoryMap<N> {
+        if self.len == N {
+            panic!("The fixed memory map is out of room for regions.");
+        }
+        // The slot holds an unused entry with nothing to drop.
+        core::mem::forget(core::mem::replace(&mut self.entries[self.len], entry));
+        self.len += 1;
+        self
+    }
+
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// The region added `index`th, for attaching handlers.
+    pub fn entry_mut(&mut self, index: usize) -> Option<&mut MemoryMapEntry> {
+        self.entries[..self.len].get_mut(index)
+    }
+
+    /// The most recently added region containing `addr`.
+    fn find(&self, addr: Address) -> Option<usize> {
+        self.entries[..self.len]
+            .iter()
+            .rposition(|entry| entry.start <= addr && entry.end >= addr)
+    }
+
+    /// The region answering for a `width`-byte access at `addr` if it holds the whole access.
+    fn find_containing(&self, addr: Address, width: u8) -> Option<usize> {
+        let index = self.find(addr)?;
+        let last = addr.checked_add(width as Address - 1)?;
+        (last <= self.entries[index].end).then_some(index)
+    }
+
+    fn read_width(&mut self, width: u8) -> Result<u128, MemoryMapError> {
+        let addr = self.current_addr;
+        if let Some(index) = self.find_containing(addr, width) {
+            if let Some(data) = self.entries[index].dispatch_read(addr, width)? {
+                return Ok(data);
+            }
+        }
+        let mut data = 0;
+        for i in 0..width {
+            let addr = addr.wrapping_add(i as Address);
+            let byte = match self.find(addr) {
+                Some(index) => self.entries[index].read_byte(addr)?,
+                None => 0,
+            };
+            data |= (byte as u128) << (i as u32 * 8);
+        }
+        Ok(data)
+    }
+
+    fn write_width(&mut self, width: u8, data: u128) -> Result<(), MemoryMapError> {
+        let addr = self.current_addr;
+        if let Some(index) = self.find_containing(addr, width) {
+            if self.entries[index].dispatch_write(addr, width, data)? {
+                return Ok(());
+            }
+        }
+        for i in 0..width {
+            let addr = addr.wrapping_add(i as Address);
+            if let Some(index) = self.find(addr) {
+                self.entries[index].write_byte(addr, (data >> (i as u32 * 8)) as u8)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<const N: usize> Default for FixedMemoryMap<N> {
+    fn default() -> Self {
+        FixedMemoryMap::new()
+    }
+}
+
+macro_rules! fixed_accesses {
+    ($($read:ident, $write:ident, $ty:ty, $width:expr;)*) => {
+        $(
+            fn $read(&mut self) -> $ty {
+                match self.read_width($width) {
+                    Ok(data) => data as $ty,
+                    Err(err) => panic!("Your memory map is broken. Please fix it! {:?}", err),
+                }
+            }
+
+            fn $write(&mut self, data: $ty) {
+                self.write_width($width, data as u128).ok();
+            }
+        )*
+    };
+}
+
+impl<const N: usize> Bus for FixedMemoryMap<N> {
+    fn select_address(&mut self, addr: Address) {
+        self.current_addr = addr;
+    }
+
+    fn handles(&self, addr: Address) -> bool {
+        self.find(addr).is_some()
+    }
+
+    fixed_accesses! {
+        read_u8, write_u8, u8, 1;
+        read_u16, write_u16, u16, 2;
+        read_u32, write_u32, u32, 4;
+        read_u64, write_u64, u64, 8;
+        read_u128, write_u128, u128, 16;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::Ram;
+
+    const VECTORS: MemoryMapEntry =
+        MemoryMapEntry::with(0xfff0, 0xffff, MemoryMapEntryType::UnmappedHigh);
+
+    const MAP: FixedMemoryMap<4> =
+        FixedMemoryMap::new()
+            .with_region(VECTORS)
+            .with_region(MemoryMapEntry::with(
+                0x0000,
+                0x0fff,
+                MemoryMapEntryType::ReadWrite,
+            ));
+
+    #[test]
+    fn test_const_entry() {
+        assert_eq!(VECTORS.start, 0xfff0);
+        assert_eq!(VECTORS.end, 0xffff);
+        assert_eq!(VECTORS.entry_type, MemoryMapEntryType::UnmappedHigh);
+    }
+
+    #[test]
+    fn test_fixed_memory_map() {
+        let mut map = MAP;
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.capacity(), 4);
+        map.entry_mut(1).unwrap().set_device(Ram::new(0x1000));
+        map.write_u32_at(0x0ffe, 0xdeadbeef);
+        assert_eq!(map.read_u16_at(0x0ffe), 0xbeef);
+        // Half in RAM, half unmapped.
+        assert_eq!(map.read_u32_at(0x0ffe), 0x0000_beef);
+        assert_eq!(map.read_u16_at(0xfffe), 0xffff);
+        assert!(!map.handles(0x8000));
+        assert!(map.entry_mut(2).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "out of room")]
+    fn test_fixed_memory_map_full() {
+        let map = FixedMemoryMap::<1>::new().with_region(VECTORS);
+        map.with_region(VECTORS);
+    }
+}
@@ -0,0 +1,114 @@
+use super::{BusAddress, MemoryMap, MemoryMapEntry};
+use alloc::vec::Vec;
+
+impl<A: BusAddress> MemoryMapEntry<A> {
+    /// A deep copy of the region, or `None` if it has delegates or a device that can't be copied.
+    fn try_clone(&self) -> Option<MemoryMapEntry<A>> {
+        let has_delegates = self.func_read_u8.is_some()
+            || self.func_read_u16.is_some()
+            || self.func_read_u32.is_some()
+            || self.func_read_u64.is_some()
+            || self.func_read_u128.is_some()
+            || self.func_write_u8.is_some()
+            || self.func_write_u16.is_some()
+            || self.func_write_u32.is_some()
+            || self.func_write_u64.is_some()
+            || self.func_write_u128.is_some();
+        if has_delegates {
+            return None;
+        }
+        let device = match self.device.as_ref() {
+            Some(device) => Some(device.try_clone()?),
+            None => None,
+        };
+        Some(MemoryMapEntry {
+            start: self.start,
+            end: self.end,
+            entry_type: self.entry_type,
+            device,
+            addr_mask: self.addr_mask,
+            read_mask: self.read_mask,
+            write_mask: self.write_mask,
+            mirror: self.mirror,
+            priority: self.priority,
+            relative: self.relative,
+            alias: self.alias,
+            allow_width_synthesis: self.allow_width_synthesis,
+            granularity: self.granularity,
+            register: self.register,
+            name: self.name,
+            latency: self.latency,
+            contention: self.contention,
+            reads: self.reads,
+            writes: self.writes,
+            ..MemoryMapEntry::default()
+        })
+    }
+}
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// Forks the map along with the contents of its regions, e.g. for speculative execution.
+    /// [`Ram`](super::Ram), [`Rom`](super::Rom) and [`BankedRegion`](super::BankedRegion) can be
+    /// copied, though a copied `BankedRegion` no longer follows the original's `BankSelector`.
+    /// Returns `None` if a region has delegates or a device whose `try_clone` declines.
+    ///
+    /// Watchpoints, the trace and event hooks, the unmapped read provider and the fault handler
+    /// are closures and aren't copied; the fork starts without them.
+    pub fn try_clone(&self) -> Option<MemoryMap<A>> {
+        let entries = self
+            .entries
+            .iter()
+            .map(MemoryMapEntry::try_clone)
+            .collect::<Option<Vec<_>>>()?;
+        Some(MemoryMap {
+            entries,
+            needs_sort: self.needs_sort,
+            overlapping: self.overlapping,
+            reject_overlaps: self.reject_overlaps,
+            strict_permissions: self.strict_permissions,
+            strict_backing: self.strict_backing,
+            report_unmapped_writes: self.report_unmapped_writes,
+            debug_alignment: self.debug_alignment,
+            current_addr: self.current_addr,
+            global_addr_mask: self.global_addr_mask,
+            endianness: self.endianness,
+            next_watchpoint_id: self.next_watchpoint_id,
+            stats_enabled: self.stats_enabled,
+            timing_enabled: self.timing_enabled,
+            latency_cost: self.latency_cost,
+            cycles: self.cycles,
+            alignment: self.alignment,
+            cross_region: self.cross_region,
+            overflow: self.overflow,
+            bus_latch: self.bus_latch,
+            protections: self.protections.clone(),
+            ..MemoryMap::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{Bus, MemoryMapEntryType, Ram, Rom};
+    use alloc::vec;
+
+    #[test]
+    fn test_try_clone() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x00ff, Ram::new(0x100));
+        map.map_rom(Rom::from_bytes(0x1000, vec![0xaa; 0x10]));
+        map.write_u32_at(0x0010, 0x1234_5678);
+
+        let mut fork = map.try_clone().unwrap();
+        assert_eq!(fork.current_address(), 0x0010);
+        fork.write_u32_at(0x0010, 0xdead_beef);
+        assert_eq!(fork.read_u32_at(0x0010), 0xdead_beef);
+        assert_eq!(map.read_u32_at(0x0010), 0x1234_5678);
+        assert_eq!(fork.read_u8_at(0x1000), 0xaa);
+
+        map.map_region(0x2000, 0x20ff, MemoryMapEntryType::Read)
+            .on_read_u8(|_| 0);
+        assert!(map.try_clone().is_none());
+    }
+}
@@ -0,0 +1,80 @@
+use super::{BusAddress, MemoryMap, MemoryMapEntry, MemoryMapEntryType};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// Describes the layout in the memory map XML a GDB stub sends for `qXfer:memory-map:read`,
+    /// so the debugger knows where it may set software breakpoints. Read-only regions are listed
+    /// as `rom` and writable ones as `ram`, in address order, and unmapped regions are left out.
+    /// Aliases take the type of the region they lead to at their first byte.
+    ///
+    /// GDB refuses overlapping entries, so a map with overlapping regions needs them resolved
+    /// before this is of use to it.
+    pub fn to_gdb_xml(&self) -> String {
+        let mut entries: Vec<&MemoryMapEntry<A>> = self.entries[1..].iter().collect();
+        entries.sort_by_key(|entry| entry.start);
+
+        let mut xml = String::from(concat!(
+            "<?xml version=\"1.0\"?>\n",
+            "<!DOCTYPE memory-map PUBLIC \"+//IDN gnu.org//DTD GDB Memory Map V1.0//EN\" ",
+            "\"http://sourceware.org/gdb/gdb-memory-map.dtd\">\n",
+            "<memory-map>\n",
+        ));
+        for entry in entries {
+            let entry_type = match entry.alias {
+                Some(_) => match self.resolve_alias(entry.start) {
+                    Some(index) => self.entries[index].entry_type,
+                    None => continue,
+                },
+                None => entry.entry_type,
+            };
+            let kind = match entry_type {
+                MemoryMapEntryType::Read => "rom",
+                MemoryMapEntryType::Write | MemoryMapEntryType::ReadWrite => "ram",
+                _ => continue,
+            };
+            let length = (entry.end - entry.start).to_u128().saturating_add(1);
+            writeln!(
+                xml,
+                "  <memory type=\"{}\" start=\"{:#x}\" length=\"{:#x}\"/>",
+                kind, entry.start, length
+            )
+            .unwrap();
+        }
+        xml.push_str("</memory-map>\n");
+        xml
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{Ram, Rom};
+    use alloc::vec;
+
+    #[test]
+    fn test_gdb_xml() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x2000_0000, 0x2000_ffff, Ram::new(0x1_0000));
+        map.map_region(0x4000_0000, 0x4fff_ffff, MemoryMapEntryType::UnmappedHigh);
+        map.map_rom(Rom::from_bytes(0x0000_0000, vec![0; 0x4000]));
+        map.add_alias(0x1000_0000, 0x1000_3fff, 0x0000_0000);
+        map.add_alias(0x3000_0000, 0x3000_ffff, 0x2000_0000);
+        map.add_alias(0x5000_0000, 0x5000_00ff, 0x4000_0000);
+        assert_eq!(
+            map.to_gdb_xml(),
+            concat!(
+                "<?xml version=\"1.0\"?>\n",
+                "<!DOCTYPE memory-map PUBLIC \"+//IDN gnu.org//DTD GDB Memory Map V1.0//EN\" ",
+                "\"http://sourceware.org/gdb/gdb-memory-map.dtd\">\n",
+                "<memory-map>\n",
+                "  <memory type=\"rom\" start=\"0x0\" length=\"0x4000\"/>\n",
+                "  <memory type=\"rom\" start=\"0x10000000\" length=\"0x4000\"/>\n",
+                "  <memory type=\"ram\" start=\"0x20000000\" length=\"0x10000\"/>\n",
+                "  <memory type=\"ram\" start=\"0x30000000\" length=\"0x10000\"/>\n",
+                "</memory-map>\n",
+            )
+        );
+    }
+}
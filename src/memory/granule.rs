@@ -0,0 +1,141 @@
+use super::{BusAddress, Endianness, MemoryMap, MemoryMapEntry, MemoryMapError};
+
+impl<A: BusAddress> MemoryMapEntry<A> {
+    /// Makes the region's handlers see nothing narrower than `width` bytes, for buses that only
+    /// transfer whole words. A narrower read is widened to the naturally aligned granule holding
+    /// it and the requested bytes are picked out in the map's byte order, while a narrower write
+    /// reads the granule, merges its bytes in and writes the granule back. Defaults to 1.
+    ///
+    /// Accesses that straddle two granules are split, and ones the region has no granule-wide
+    /// handler for are handled as if it had no granularity.
+    ///
+    /// Panics unless `width` is 1, 2, 4, 8 or 16.
+    pub fn set_granularity(&mut self, width: u8) {
+        if !matches!(width, 1 | 2 | 4 | 8 | 16) {
+            panic!("A granularity of {} bytes isn't an access width.", width);
+        }
+        self.granularity = width;
+    }
+}
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// The address of the granule holding a `width`-byte access at `addr` in the entry at
+    /// `index` and the access's offset into it, or `None` if the access is at least a granule
+    /// wide or the granule isn't wholly inside the region.
+    fn granule(&self, index: usize, addr: A, width: u8) -> Option<(A, u8)> {
+        let entry = &self.entries[index];
+        let granularity = entry.granularity;
+        if width >= granularity {
+            return None;
+        }
+        let offset = (addr.to_u128() % granularity as u128) as u8;
+        let start = addr - A::from_u128(offset as u128);
+        let last = start.checked_add(A::from_u128(granularity as u128 - 1))?;
+        if offset + width > granularity || start < entry.start || last > entry.end {
+            return None;
+        }
+        Some((start, offset))
+    }
+
+    /// The bit position of a `width`-byte access `offset` bytes into a granule.
+    fn granule_shift(&self, index: usize, offset: u8, width: u8) -> u32 {
+        match self.endianness {
+            Endianness::Little => offset as u32 * 8,
+            Endianness::Big => (self.entries[index].granularity - offset - width) as u32 * 8,
+        }
+    }
+
+    /// Reads the granule holding a narrower access and returns the access's bytes, if the
+    /// entry at `index` has a granule-wide handler for it.
+    pub(super) fn read_granule(
+        &mut self,
+        index: usize,
+        addr: A,
+        width: u8,
+    ) -> Result<Option<u128>, MemoryMapError> {
+        let (start, offset) = match self.granule(index, addr, width) {
+            Some(granule) => granule,
+            None => return Ok(None),
+        };
+        let granularity = self.entries[index].granularity;
+        let data = match self.entries[index].dispatch_read(start, granularity)? {
+            Some(data) => data,
+            None => return Ok(None),
+        };
+        let mask = (1 << (width as u32 * 8)) - 1;
+        Ok(Some(
+            (data >> self.granule_shift(index, offset, width)) & mask,
+        ))
+    }
+
+    /// Merges a narrower write into the granule holding it, returning whether the entry at
+    /// `index` has granule-wide handlers to read and write it.
+    pub(super) fn write_granule(
+        &mut self,
+        index: usize,
+        addr: A,
+        width: u8,
+        data: u128,
+    ) -> Result<bool, MemoryMapError> {
+        let (start, offset) = match self.granule(index, addr, width) {
+            Some(granule) => granule,
+            None => return Ok(false),
+        };
+        let granularity = self.entries[index].granularity;
+        let old = match self.entries[index].dispatch_read(start, granularity)? {
+            Some(old) => old,
+            None => return Ok(false),
+        };
+        let shift = self.granule_shift(index, offset, width);
+        let mask = ((1 << (width as u32 * 8)) - 1) << shift;
+        let merged = (old & !mask) | ((data << shift) & mask);
+        self.entries[index].dispatch_write(start, granularity, merged)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{Bus, MemoryMapEntryType};
+    use std::sync::{Arc, Mutex};
+    use std::vec::Vec;
+
+    #[test]
+    fn test_word_granularity() {
+        let word = Arc::new(Mutex::new(0x1122_3344u32));
+        let reads = Arc::new(Mutex::new(Vec::new()));
+        let mut map = MemoryMap::new();
+        let (value, log) = (word.clone(), reads.clone());
+        let written = word.clone();
+        let entry = map.map_region(0x1000, 0x10ff, MemoryMapEntryType::ReadWrite);
+        entry
+            .on_read_u32(move |addr| {
+                log.lock().unwrap().push(addr);
+                *value.lock().unwrap()
+            })
+            .on_write_u32(move |_, data| *written.lock().unwrap() = data);
+        entry.set_granularity(4);
+
+        assert_eq!(map.read_u8_at(0x1000), 0x44);
+        assert_eq!(map.read_u8_at(0x1003), 0x11);
+        assert_eq!(map.read_u16_at(0x1002), 0x1122);
+        assert_eq!(*reads.lock().unwrap(), [0x1000, 0x1000, 0x1000]);
+        map.write_u8_at(0x1001, 0xaa);
+        assert_eq!(*word.lock().unwrap(), 0x1122_aa44);
+
+        // Byte 0 of a big-endian word is its most significant.
+        map.set_endianness(Endianness::Big);
+        assert_eq!(map.read_u8_at(0x1000), 0x11);
+        assert_eq!(map.read_u8_at(0x1002), 0xaa);
+        map.write_u16_at(0x1002, 0xbeef);
+        assert_eq!(*word.lock().unwrap(), 0x1122_beef);
+        // Straddles two granules, so it's read a byte at a time.
+        assert_eq!(map.read_u16_at(0x1003), 0xef11);
+    }
+
+    #[test]
+    #[should_panic(expected = "isn't an access width")]
+    fn test_bad_granularity() {
+        MemoryMapEntry::new().set_granularity(3);
+    }
+}
@@ -0,0 +1,84 @@
+use super::{Bus, BusAddress, MemoryMap, MemoryMapEntryType};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// Formats `len` bytes from `start` like `hexdump -C`: sixteen bytes a line, each line led by
+    /// its address and followed by the bytes as ASCII. Mapped bytes are read through the bus, so
+    /// hooks fire as for any read, while unmapped ones, including those in regions mapped as
+    /// unmapped and aliases of them, show as `--` and ones whose read fails as `??`. The selected address is left alone.
+    pub fn hexdump(&mut self, start: A, len: usize) -> String {
+        let selected = self.current_addr;
+        let bytes: Vec<Result<u8, &str>> = (0..len)
+            .map(|i| {
+                let addr = start.wrapping_add(A::from_u128(i as u128)) & self.global_addr_mask;
+                let unmapped = match self.resolve_alias(addr) {
+                    Some(index) => matches!(
+                        self.entries[index].entry_type,
+                        MemoryMapEntryType::UnmappedLow
+                            | MemoryMapEntryType::UnmappedHigh
+                            | MemoryMapEntryType::UnmappedOpenBus
+                    ),
+                    None => true,
+                };
+                if unmapped {
+                    return Err("--");
+                }
+                self.select_address(addr);
+                self.try_read_u8().map_err(|_| "??")
+            })
+            .collect();
+        self.current_addr = selected;
+
+        let mut out = String::new();
+        for (line, chunk) in bytes.chunks(16).enumerate() {
+            let addr = start.wrapping_add(A::from_u128(line as u128 * 16)) & self.global_addr_mask;
+            write!(out, "{:08x} ", addr).unwrap();
+            for i in 0..16 {
+                if i % 8 == 0 {
+                    out.push(' ');
+                }
+                match chunk.get(i) {
+                    Some(Ok(byte)) => write!(out, "{:02x} ", byte).unwrap(),
+                    Some(Err(placeholder)) => write!(out, "{} ", placeholder).unwrap(),
+                    None => out.push_str("   "),
+                }
+            }
+            out.push_str(" |");
+            for byte in chunk {
+                out.push(match byte {
+                    Ok(byte @ 0x20..=0x7e) => *byte as char,
+                    _ => '.',
+                });
+            }
+            out.push_str("|\n");
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::Ram;
+
+    #[test]
+    fn test_hexdump() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x1000, 0x1013, Ram::new(0x14));
+        map.write_block(0x1000, b"Hello, world!\n\x00\x01\xff");
+        map.map_region(0x1016, 0x1017, MemoryMapEntryType::UnmappedHigh);
+        map.add_alias(0x1018, 0x1019, 0x1016);
+        map.add_alias(0x101a, 0x101a, 0x1000);
+        map.select_address(0x1234);
+        assert_eq!(
+            map.hexdump(0x1000, 0x1c),
+            "\
+00001000  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0a 00 01  |Hello, world!...|
+00001010  ff 00 00 00 -- -- -- --  -- -- 48 --              |..........H.|
+"
+        );
+        assert_eq!(map.current_address(), 0x1234);
+    }
+}
@@ -0,0 +1,178 @@
+use super::{BusAddress, MemoryMap};
+use alloc::vec::Vec;
+
+/// Why an Intel HEX image couldn't be loaded. `line` counts from 1.
+#[derive(Debug, PartialEq, Eq)]
+pub enum IhexError {
+    /// The line doesn't start with `:`.
+    MissingStartCode {
+        line: usize,
+    },
+    /// The line has an odd number of digits or something other than hex digits.
+    InvalidHex {
+        line: usize,
+    },
+    /// The record's byte count doesn't match the data it carries, or an address record has the
+    /// wrong size.
+    BadLength {
+        line: usize,
+    },
+    /// The record's checksum byte is `found` where its contents call for `expected`.
+    Checksum {
+        line: usize,
+        expected: u8,
+        found: u8,
+    },
+    UnsupportedRecord {
+        line: usize,
+        record_type: u8,
+    },
+    /// A data record lands outside the map's address space.
+    AddressOutOfRange {
+        line: usize,
+        addr: u128,
+    },
+    /// The image ended without an end-of-file record.
+    MissingEof,
+}
+
+/// Decodes the hex digits of one record after its start code.
+fn decode_record(digits: &str, line: usize) -> Result<Vec<u8>, IhexError> {
+    if !digits.len().is_multiple_of(2) || !digits.bytes().all(|digit| digit.is_ascii_hexdigit()) {
+        return Err(IhexError::InvalidHex { line });
+    }
+    Ok((0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).unwrap())
+        .collect())
+}
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// Loads an Intel HEX image, writing each data record through the bus at the absolute address
+    /// given by the record and the extended segment or linear address records before it. Start
+    /// address records are accepted but ignored.
+    ///
+    /// Records before the line that fails to load have already been written.
+    pub fn load_ihex(&mut self, data: &str) -> Result<(), IhexError> {
+        let mut base: u128 = 0;
+        for (i, text) in data.lines().enumerate() {
+            let line = i + 1;
+            let text = text.trim();
+            if text.is_empty() {
+                continue;
+            }
+            let digits = text
+                .strip_prefix(':')
+                .ok_or(IhexError::MissingStartCode { line })?;
+            let record = decode_record(digits, line)?;
+            if record.len() < 5 || record.len() != record[0] as usize + 5 {
+                return Err(IhexError::BadLength { line });
+            }
+            let (contents, found) = record.split_at(record.len() - 1);
+            let expected = contents
+                .iter()
+                .fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+                .wrapping_neg();
+            if found[0] != expected {
+                return Err(IhexError::Checksum {
+                    line,
+                    expected,
+                    found: found[0],
+                });
+            }
+            let offset = u16::from_be_bytes([record[1], record[2]]) as u128;
+            let payload = &contents[4..];
+            match record[3] {
+                0x00 => {
+                    let addr = base + offset;
+                    let last = addr + payload.len() as u128;
+                    if !payload.is_empty() && last - 1 > A::MAX.to_u128() {
+                        return Err(IhexError::AddressOutOfRange { line, addr });
+                    }
+                    self.write_block(A::from_u128(addr), payload);
+                }
+                0x01 => return Ok(()),
+                record_type @ (0x02 | 0x04) => {
+                    if payload.len() != 2 {
+                        return Err(IhexError::BadLength { line });
+                    }
+                    let value = u16::from_be_bytes([payload[0], payload[1]]) as u128;
+                    base = if record_type == 0x02 {
+                        value << 4
+                    } else {
+                        value << 16
+                    };
+                }
+                0x03 | 0x05 => {}
+                record_type => return Err(IhexError::UnsupportedRecord { line, record_type }),
+            }
+        }
+        Err(IhexError::MissingEof)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{Bus, Ram};
+
+    #[test]
+    fn test_load_ihex() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x00ff, Ram::new(0x100));
+        map.map_ram(0x1_0000, 0x1_00ff, Ram::new(0x100));
+        let image = "\
+:040010001122334442
+:020000040001F9
+:02002000ABCD66
+:00000001FF
+";
+        assert_eq!(map.load_ihex(image), Ok(()));
+        map.select_address(0x10);
+        assert_eq!(map.read_u32(), 0x44332211);
+        map.select_address(0x1_0020);
+        assert_eq!(map.read_u16(), 0xcdab);
+    }
+
+    #[test]
+    fn test_ihex_errors() {
+        let mut map = MemoryMap::new();
+        assert_eq!(map.load_ihex(":00000001FF\n"), Ok(()));
+        assert_eq!(
+            map.load_ihex("\n:040010001122334443\n"),
+            Err(IhexError::Checksum {
+                line: 2,
+                expected: 0x42,
+                found: 0x43
+            })
+        );
+        assert_eq!(
+            map.load_ihex("0400100011223344EE"),
+            Err(IhexError::MissingStartCode { line: 1 })
+        );
+        assert_eq!(
+            map.load_ihex(":050010001122334442"),
+            Err(IhexError::BadLength { line: 1 })
+        );
+        assert_eq!(
+            map.load_ihex(":040010001122334442\n"),
+            Err(IhexError::MissingEof)
+        );
+        assert_eq!(
+            map.load_ihex(":00000006FA"),
+            Err(IhexError::UnsupportedRecord {
+                line: 1,
+                record_type: 6
+            })
+        );
+
+        let mut narrow = MemoryMap::<u16>::default();
+        assert_eq!(
+            narrow.load_ihex(":020000040001F9\n:0100000000FF\n"),
+            Err(IhexError::AddressOutOfRange {
+                line: 2,
+                addr: 0x1_0000
+            })
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use super::{AccessKind, BusAddress, MemoryMap};
+use core::ops::{Index, IndexMut};
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// The entry holding the buffer behind the byte at `addr` and the byte's index into it,
+    /// following aliases and mirrors the way a `kind` access would.
+    fn backing_index(&self, addr: A, kind: AccessKind) -> Option<(usize, usize)> {
+        let addr = addr & self.global_addr_mask;
+        let index = self.find_region(addr)?;
+        let entry = &self.entries[index];
+        if let Some(target) = entry.alias_target(addr) {
+            return self.backing_index(target, kind);
+        }
+        let (base, len) = entry.device.as_ref()?.backing()?;
+        let offset = entry.handler_addr(addr, kind).to_u128().wrapping_sub(base);
+        Some((index, (offset % len as u128) as usize))
+    }
+}
+
+/// Reads the byte at `addr` straight out of the buffer backing it, like
+/// [`MemoryMap::peek_u8`] but without telling the trace hook, for terse tests and tools.
+///
+/// Panics if the byte isn't backed by a buffer, as in unmapped space and regions handled by
+/// delegates or shared devices.
+impl<A: BusAddress> Index<A> for MemoryMap<A> {
+    type Output = u8;
+
+    fn index(&self, addr: A) -> &u8 {
+        self.backing_index(addr, AccessKind::Read)
+            .and_then(|(index, offset)| {
+                Some(&self.entries[index].device.as_ref()?.bytes()?[offset])
+            })
+            .unwrap_or_else(|| panic!("No buffer backs the byte at {:#x}.", addr))
+    }
+}
+
+/// Changes the byte at `addr` in the buffer backing it, like [`MemoryMap::poke_u8`] but
+/// without telling the trace hook. ROM lends out no buffer to change, so poke it instead.
+///
+/// Panics if the byte isn't backed by a writable buffer.
+impl<A: BusAddress> IndexMut<A> for MemoryMap<A> {
+    fn index_mut(&mut self, addr: A) -> &mut u8 {
+        let (index, offset) = self
+            .backing_index(addr, AccessKind::Write)
+            .unwrap_or_else(|| panic!("No writable buffer backs the byte at {:#x}.", addr));
+        match self.entries[index]
+            .device
+            .as_mut()
+            .and_then(|device| device.bytes_mut())
+        {
+            Some(bytes) => &mut bytes[offset],
+            None => panic!("No writable buffer backs the byte at {:#x}.", addr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{Bus, MemoryMapEntryType, Ram, Rom};
+    use alloc::vec;
+
+    #[test]
+    fn test_index() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x1000, 0x1fff, Ram::new(0x100));
+        map.map_rom(Rom::from_bytes(0x0000, vec![0xaa; 0x10]));
+        map[0x1010] = 0x42;
+        assert_eq!(map.read_u8_at(0x1010), 0x42);
+        // The RAM is mirrored across its region.
+        assert_eq!(map[0x1110], 0x42);
+        map.write_u8_at(0x1020, 0x99);
+        assert_eq!(map[0x1020], 0x99);
+        assert_eq!(map[0x0004], 0xaa);
+    }
+
+    #[test]
+    #[should_panic(expected = "No buffer backs the byte at 0x3000")]
+    fn test_index_delegate() {
+        let mut map = MemoryMap::new();
+        map.map_region(0x3000, 0x30ff, MemoryMapEntryType::ReadWrite)
+            .on_read_u8(|_| 0);
+        let _ = map[0x3000];
+    }
+
+    #[test]
+    #[should_panic(expected = "No writable buffer backs the byte at 0x4")]
+    fn test_index_mut_rom() {
+        let mut map = MemoryMap::new();
+        map.map_rom(Rom::from_bytes(0x0000, vec![0xaa; 0x10]));
+        map[0x0004] = 0x00;
+    }
+}
@@ -0,0 +1,176 @@
+use super::{Address, BusAddress, MemoryMap, MemoryMapEntry, MemoryMapEntryType};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// What the map knows about the region answering for an address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RegionInfo<A = Address> {
+    pub start: A,
+    pub end: A,
+    pub entry_type: MemoryMapEntryType,
+    pub name: Option<&'static str>,
+    /// Whether a device handles the region, in which case delegates are ignored.
+    pub has_device: bool,
+    /// Whether a read delegate is installed, indexed by the log2 of the width in bytes like
+    /// [`RegionStats`](super::RegionStats).
+    pub read_delegates: [bool; 5],
+    /// Write delegates, indexed like `read_delegates`.
+    pub write_delegates: [bool; 5],
+}
+
+impl<A: BusAddress> RegionInfo<A> {
+    fn of(entry: &MemoryMapEntry<A>) -> RegionInfo<A> {
+        RegionInfo {
+            start: entry.start,
+            end: entry.end,
+            entry_type: entry.entry_type,
+            name: entry.name,
+            has_device: entry.device.is_some(),
+            read_delegates: [
+                entry.func_read_u8.is_some(),
+                entry.func_read_u16.is_some(),
+                entry.func_read_u32.is_some(),
+                entry.func_read_u64.is_some(),
+                entry.func_read_u128.is_some(),
+            ],
+            write_delegates: [
+                entry.func_write_u8.is_some(),
+                entry.func_write_u16.is_some(),
+                entry.func_write_u32.is_some(),
+                entry.func_write_u64.is_some(),
+                entry.func_write_u128.is_some(),
+            ],
+        }
+    }
+}
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// Describes the region answering for `addr`, after the address mask, without performing an
+    /// access. Returns `None` where no region is mapped.
+    pub fn region_at(&self, addr: A) -> Option<RegionInfo<A>> {
+        let index = self.find_region(addr & self.global_addr_mask)?;
+        Some(RegionInfo::of(&self.entries[index]))
+    }
+}
+
+fn write_gap<A: BusAddress>(
+    f: &mut fmt::Formatter<'_>,
+    start: A,
+    end: A,
+    entry_type: MemoryMapEntryType,
+) -> fmt::Result {
+    writeln!(f, "{:#x}-{:#x}  {:?}", start, end, entry_type)
+}
+
+/// Lists the layout one region per line as `START-END  TYPE  [handlers]  'name'`, in address
+/// order. The handlers are `device` or the widths with read (`r8`) and write (`w8`) delegates
+/// installed, and the name is left out for unnamed regions.
+/// Gaps up to the global address mask are listed with the type of the default entry.
+impl<A: BusAddress> fmt::Display for MemoryMap<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut entries: Vec<&MemoryMapEntry<A>> = self.entries[1..].iter().collect();
+        entries.sort_by_key(|entry| entry.start);
+        let gap_type = self.entries[0].entry_type;
+        // The first address not covered by a region listed so far, or `None` past the top.
+        let mut next = Some(A::ZERO);
+        for entry in entries {
+            if let Some(gap) = next.filter(|&gap| gap < entry.start) {
+                write_gap(f, gap, entry.start - A::ONE, gap_type)?;
+            }
+            let info = RegionInfo::of(entry);
+            write!(
+                f,
+                "{:#x}-{:#x}  {:?}",
+                info.start, info.end, info.entry_type
+            )?;
+            if let Some(target) = entry.alias {
+                write!(f, "  -> {:#x}", target)?;
+            } else if info.has_device {
+                write!(f, "  [device]")?;
+            } else {
+                let handlers: Vec<_> = (0..5)
+                    .flat_map(|i| {
+                        let bits = 8 << i;
+                        let read = info.read_delegates[i].then_some(('r', bits));
+                        let write = info.write_delegates[i].then_some(('w', bits));
+                        read.into_iter().chain(write)
+                    })
+                    .collect();
+                if !handlers.is_empty() {
+                    write!(f, "  [")?;
+                    for (i, (kind, bits)) in handlers.into_iter().enumerate() {
+                        if i > 0 {
+                            write!(f, " ")?;
+                        }
+                        write!(f, "{}{}", kind, bits)?;
+                    }
+                    write!(f, "]")?;
+                }
+            }
+            if let Some(name) = info.name {
+                write!(f, "  '{}'", name)?;
+            }
+            writeln!(f)?;
+            if next.is_some_and(|next| next <= entry.end) {
+                next = entry.end.checked_add(A::ONE);
+            }
+        }
+        if let Some(gap) = next.filter(|&gap| gap <= self.global_addr_mask) {
+            write_gap(f, gap, self.global_addr_mask, gap_type)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::Ram;
+
+    #[test]
+    fn test_region_at() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x0fff, Ram::new(0x1000));
+        map.map_region(0x2000, 0x20ff, MemoryMapEntryType::ReadWrite)
+            .on_read_u16(|_| 0)
+            .on_write_u8(|_, _| {});
+
+        let ram = map.region_at(0x0800).unwrap();
+        assert_eq!((ram.start, ram.end), (0x0000, 0x0fff));
+        assert_eq!(ram.entry_type, MemoryMapEntryType::ReadWrite);
+        assert!(ram.has_device);
+
+        let io = map.region_at(0x20ff).unwrap();
+        assert!(!io.has_device);
+        assert_eq!(io.read_delegates, [false, true, false, false, false]);
+        assert_eq!(io.write_delegates, [true, false, false, false, false]);
+
+        assert_eq!(map.region_at(0x1000), None);
+        assert_eq!(map.region_at(0x2100), None);
+    }
+
+    #[test]
+    fn test_display() {
+        let mut map = MemoryMap::<u16>::default();
+        map.map_ram(0x0000, 0x0fff, Ram::new(0x1000))
+            .set_name("RAM");
+        map.map_region(0x2000, 0x20ff, MemoryMapEntryType::ReadWrite)
+            .on_read_u8(|_| 0)
+            .on_read_u16(|_| 0)
+            .on_write_u8(|_, _| {});
+        map.map_region(0x2100, 0x21ff, MemoryMapEntryType::UnmappedHigh);
+        map.add_alias(0x8000, 0x8fff, 0x0000);
+        assert_eq!(
+            alloc::format!("{}", map),
+            "\
+0x0-0xfff  ReadWrite  [device]  'RAM'
+0x1000-0x1fff  UnmappedLow
+0x2000-0x20ff  ReadWrite  [r8 w8 r16]
+0x2100-0x21ff  UnmappedHigh
+0x2200-0x7fff  UnmappedLow
+0x8000-0x8fff  ReadWrite  -> 0x0
+0x9000-0xffff  UnmappedLow
+"
+        );
+    }
+}
@@ -0,0 +1,199 @@
+use super::{BusAddress, Device, MemoryMap, MemoryMapEntryType};
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// The state of an [`IoPort`], shared with the [`IoPins`] handed out when it is mapped.
+#[derive(Debug, Default)]
+struct PortState {
+    input: AtomicU64,
+    output: AtomicU64,
+    direction: AtomicU64,
+}
+
+/// A GPIO-style port: a data register followed by a data-direction register of the same width.
+///
+/// Bits set in the direction register are outputs, which the guest drives by writing the data
+/// register; the others are inputs driven from outside through [`IoPins`]. Reads of the data
+/// register see output bits as last written and input bits as driven. Registers are little endian
+/// byte by byte, so narrower accesses reach the bytes they cover.
+#[derive(Debug)]
+pub struct IoPort {
+    width: u8,
+    state: Arc<PortState>,
+}
+
+/// The outside of a mapped [`IoPort`], usable from any thread.
+#[derive(Clone, Debug)]
+pub struct IoPins {
+    mask: u64,
+    state: Arc<PortState>,
+}
+
+impl IoPins {
+    /// Drives the input bits. Bits configured as outputs are ignored until they become inputs.
+    pub fn set_external_input(&self, value: u64) {
+        self.state.input.store(value & self.mask, Ordering::Relaxed);
+    }
+
+    /// The levels of the output bits, with input bits reading as zero.
+    pub fn output(&self) -> u64 {
+        self.state.output.load(Ordering::Relaxed) & self.direction()
+    }
+
+    pub fn direction(&self) -> u64 {
+        self.state.direction.load(Ordering::Relaxed)
+    }
+}
+
+impl IoPort {
+    /// A port `width` bytes wide, 1, 2, 4 or 8, with every bit an input.
+    pub fn new(width: u8) -> IoPort {
+        if !matches!(width, 1 | 2 | 4 | 8) {
+            panic!("An I/O port must be 1, 2, 4 or 8 bytes wide.");
+        }
+        IoPort {
+            width,
+            state: Arc::new(PortState::default()),
+        }
+    }
+
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    pub fn set_external_input(&self, value: u64) {
+        self.pins().set_external_input(value)
+    }
+
+    fn mask(&self) -> u64 {
+        u64::MAX >> (64 - self.width as u32 * 8)
+    }
+
+    fn pins(&self) -> IoPins {
+        IoPins {
+            mask: self.mask(),
+            state: self.state.clone(),
+        }
+    }
+
+    /// The register at `offset` as the guest sees it, and which register that is.
+    fn register(&self, offset: u128) -> (u64, bool) {
+        let state = &self.state;
+        let direction = state.direction.load(Ordering::Relaxed);
+        if offset >= self.width as u128 {
+            return (direction, true);
+        }
+        let output = state.output.load(Ordering::Relaxed);
+        let input = state.input.load(Ordering::Relaxed);
+        ((output & direction) | (input & !direction), false)
+    }
+}
+
+impl<A: BusAddress> Device<A> for IoPort {
+    fn read(&mut self, addr: A, width: u8) -> u128 {
+        let offset = addr.to_u128() % (self.width as u128 * 2);
+        let (value, _) = self.register(offset);
+        let shift = (offset % self.width as u128) as u32 * 8;
+        let lanes = u64::MAX >> (64 - width.min(8) as u32 * 8);
+        ((value >> shift) & lanes) as u128
+    }
+
+    fn write(&mut self, addr: A, width: u8, data: u128) {
+        let offset = addr.to_u128() % (self.width as u128 * 2);
+        let shift = (offset % self.width as u128) as u32 * 8;
+        let lanes = (u64::MAX >> (64 - width.min(8) as u32 * 8)) << shift & self.mask();
+        let data = (data as u64) << shift & lanes;
+        let (_, is_direction) = self.register(offset);
+        let state = &self.state;
+        if is_direction {
+            let direction = state.direction.load(Ordering::Relaxed);
+            state
+                .direction
+                .store((direction & !lanes) | data, Ordering::Relaxed);
+        } else {
+            // Only the output bits take the write.
+            let writable = lanes & state.direction.load(Ordering::Relaxed);
+            let output = state.output.load(Ordering::Relaxed);
+            state
+                .output
+                .store((output & !writable) | (data & writable), Ordering::Relaxed);
+        }
+    }
+
+    fn max_width(&self) -> u8 {
+        self.width
+    }
+}
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// Maps `port` with its data register at `start` and its direction register right after,
+    /// returning the pins the rest of the emulator drives and observes.
+    ///
+    /// Panics if the registers would run past the end of the address space.
+    pub fn map_io_port(&mut self, start: A, port: IoPort) -> IoPins {
+        let pins = port.pins();
+        let len = port.width as u128 * 2;
+        let end = match start.checked_add(A::from_u128(len - 1)) {
+            Some(end) => end,
+            None => panic!(
+                "I/O port of {:#x} bytes at {:#x} runs past the end of the address space.",
+                len, start
+            ),
+        };
+        let entry = self.map_region(start, end, MemoryMapEntryType::ReadWrite);
+        entry.set_device(port);
+        entry.set_relative_addressing(true);
+        pins
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::Bus;
+
+    #[test]
+    fn test_io_port() {
+        let mut map = MemoryMap::new();
+        let port = IoPort::new(1);
+        port.set_external_input(0xa5);
+        let pins = map.map_io_port(0x4000, port);
+        assert_eq!(map.read_u8_at(0x4000), 0xa5);
+
+        // The low nibble becomes outputs.
+        map.write_u8_at(0x4001, 0x0f);
+        assert_eq!(pins.direction(), 0x0f);
+        map.write_u8_at(0x4000, 0xff);
+        assert_eq!(pins.output(), 0x0f);
+        assert_eq!(map.read_u8_at(0x4000), 0xaf);
+        pins.set_external_input(0x00);
+        assert_eq!(map.read_u8_at(0x4000), 0x0f);
+
+        // Outputs keep their value when turned into inputs and back.
+        map.write_u8_at(0x4001, 0x00);
+        assert_eq!(pins.output(), 0x00);
+        map.write_u8_at(0x4001, 0x01);
+        assert_eq!(pins.output(), 0x01);
+    }
+
+    #[test]
+    fn test_wide_io_port() {
+        let mut map = MemoryMap::new();
+        let pins = map.map_io_port(0x4000, IoPort::new(2));
+        map.write_u16_at(0x4002, 0xff00);
+        pins.set_external_input(0x1234);
+        map.write_u16_at(0x4000, 0xabcd);
+        assert_eq!(map.read_u16_at(0x4000), 0xab34);
+        assert_eq!(map.read_u8_at(0x4001), 0xab);
+        map.write_u8_at(0x4001, 0x00);
+        assert_eq!(pins.output(), 0x0000);
+        assert_eq!(map.read_u8_at(0x4003), 0xff);
+    }
+
+    #[test]
+    #[should_panic(expected = "runs past the end of the address space")]
+    fn test_io_port_at_top() {
+        let mut map = MemoryMap::new();
+        map.map_io_port(u128::MAX, IoPort::new(1));
+    }
+}
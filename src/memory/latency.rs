@@ -0,0 +1,115 @@
+use super::{Address, BusAddress, MemoryMap, MemoryMapEntry};
+
+/// The cycles one access costs, given the latency of the region it starts in and its width in
+/// bytes.
+pub type LatencyCost = fn(latency: u32, width: u8) -> u64;
+
+/// The extra cycles an access to `addr` stalls for when it starts at `cycle`, as counted by
+/// [`MemoryMap::consumed_cycles`].
+pub type ContentionFn<A = Address> = fn(cycle: u64, addr: A) -> u32;
+
+/// Charges every access the region's latency once, whatever its width.
+pub(super) fn per_access(latency: u32, _width: u8) -> u64 {
+    latency as u64
+}
+
+impl<A: BusAddress> MemoryMapEntry<A> {
+    /// The cycles an access to the region takes while the map keeps time. Defaults to zero.
+    pub fn set_latency(&mut self, cycles: u32) {
+        self.latency = cycles;
+    }
+
+    /// Charges each access to the region the stall `contention` returns on top of its latency,
+    /// e.g. for video memory the display fetches from during parts of every line.
+    pub fn set_contention(&mut self, contention: ContentionFn<A>) {
+        self.contention = Some(contention);
+    }
+}
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// Turns cycle accounting on or off. Like stats, it costs an extra region lookup per access
+    /// while enabled. Block transfers and `fill` are charged byte by byte.
+    pub fn enable_timing(&mut self, enabled: bool) {
+        self.timing_enabled = enabled;
+    }
+
+    /// Replaces the default cost of one latency per access, e.g. to charge wide accesses per
+    /// byte on a narrow bus.
+    pub fn set_latency_cost(&mut self, cost: LatencyCost) {
+        self.latency_cost = cost;
+    }
+
+    /// The cycles consumed by accesses since timing was enabled or the count was last reset.
+    pub fn consumed_cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    pub fn reset_cycles(&mut self) {
+        self.cycles = 0;
+    }
+
+    /// Charges an access to `addr` in the entry at `index`.
+    pub(super) fn charge(&mut self, index: usize, addr: A, width: u8) {
+        let entry = &self.entries[index];
+        let mut cost = (self.latency_cost)(entry.latency, width);
+        if let Some(contention) = entry.contention {
+            cost = cost.saturating_add(contention(self.cycles, addr) as u64);
+        }
+        self.cycles = self.cycles.saturating_add(cost);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{Bus, Ram};
+
+    #[test]
+    fn test_latency() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x0fff, Ram::new(0x1000)).set_latency(1);
+        map.map_ram(0x8000, 0x8fff, Ram::new(0x1000)).set_latency(4);
+        map.read_u8_at(0x8000);
+        assert_eq!(map.consumed_cycles(), 0);
+
+        map.enable_timing(true);
+        map.read_u32_at(0x0000);
+        map.write_u8_at(0x8000, 1);
+        map.read_u16_at(0x8002);
+        assert_eq!(map.consumed_cycles(), 9);
+        // Unmapped space is free.
+        map.read_u8_at(0x4000);
+        map.write_block(0x0ffe, &[0; 4]);
+        assert_eq!(map.consumed_cycles(), 11);
+
+        map.reset_cycles();
+        map.set_latency_cost(|latency, width| latency as u64 * width as u64);
+        map.read_u32_at(0x8000);
+        assert_eq!(map.consumed_cycles(), 16);
+    }
+
+    #[test]
+    fn test_contention() {
+        let mut map = MemoryMap::new();
+        let screen = map.map_ram(0x4000, 0x5fff, Ram::new(0x2000));
+        screen.set_latency(3);
+        screen.set_contention(|cycle, _| if cycle % 2 == 0 { 4 } else { 0 });
+        map.map_ram(0x8000, 0x8fff, Ram::new(0x1000)).set_latency(3);
+        map.enable_timing(true);
+
+        // Starts on cycle 0, so it stalls and ends on cycle 7.
+        map.read_u8_at(0x4000);
+        assert_eq!(map.consumed_cycles(), 7);
+        map.write_u8_at(0x4001, 1);
+        assert_eq!(map.consumed_cycles(), 10);
+        // Uncontended memory never stalls.
+        map.read_u8_at(0x8000);
+        map.read_u8_at(0x8000);
+        assert_eq!(map.consumed_cycles(), 16);
+        map.read_u16_at(0x4000);
+        assert_eq!(map.consumed_cycles(), 23);
+        // Block transfers are charged byte by byte, each on the cycle it starts.
+        map.read_block(0x4000, &mut [0; 2]);
+        assert_eq!(map.consumed_cycles(), 33);
+    }
+}
@@ -0,0 +1,205 @@
+use super::{Address, BusAddress, Device, MemoryMap, MemoryMapEntry, MemoryMapEntryType};
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+
+/// Called with the offset of a page's first byte when [`LazyRam`] allocates it.
+pub type PageAllocCallback = Box<dyn FnMut(Address) + Send>;
+
+/// Read/write memory that only allocates the pages written to, for address spaces far larger
+/// than the memory a guest actually touches.
+///
+/// Pages never written read as the unwritten byte, zero unless set with
+/// [`LazyRam::with_unwritten_byte`], without being allocated. The map's unmapped configuration,
+/// such as [`MemoryMap::set_unmapped_read`], has no say in it: the pages are mapped memory that
+/// just isn't allocated yet, not unmapped space. Addresses are taken relative to `base` like [`Ram`](super::Ram), but nothing wraps:
+/// the memory is as large as the region it's mapped over.
+pub struct LazyRam {
+    base: Address,
+    page_size: usize,
+    unwritten: u8,
+    pages: BTreeMap<Address, Box<[u8]>>,
+    on_page_alloc: Option<PageAllocCallback>,
+}
+
+impl LazyRam {
+    pub fn new(page_size: usize) -> LazyRam {
+        if page_size == 0 {
+            panic!("Pages must be at least one byte long.");
+        }
+        LazyRam {
+            base: 0,
+            page_size,
+            unwritten: 0,
+            pages: BTreeMap::new(),
+            on_page_alloc: None,
+        }
+    }
+
+    /// Sets the byte that pages never written read as, and that new pages start out filled with.
+    pub fn with_unwritten_byte(mut self, byte: u8) -> LazyRam {
+        self.unwritten = byte;
+        self
+    }
+
+    pub fn on_page_alloc<F>(mut self, callback: F) -> LazyRam
+    where
+        F: FnMut(Address) + Send + 'static,
+    {
+        self.on_page_alloc = Some(Box::new(callback));
+        self
+    }
+
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    /// How many pages have been allocated so far.
+    pub fn allocated_pages(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// The page holding `addr` and the byte's index within it.
+    fn locate(&self, addr: Address) -> (Address, usize) {
+        let offset = addr.wrapping_sub(self.base);
+        let page_size = self.page_size as Address;
+        (offset / page_size, (offset % page_size) as usize)
+    }
+
+    pub fn read_u8(&self, addr: Address) -> u8 {
+        let (page, index) = self.locate(addr);
+        self.pages
+            .get(&page)
+            .map_or(self.unwritten, |page| page[index])
+    }
+
+    pub fn write_u8(&mut self, addr: Address, data: u8) {
+        let (page, index) = self.locate(addr);
+        let page_size = self.page_size;
+        let unwritten = self.unwritten;
+        let on_page_alloc = &mut self.on_page_alloc;
+        let page = self.pages.entry(page).or_insert_with(|| {
+            if let Some(callback) = on_page_alloc {
+                callback(page * page_size as Address);
+            }
+            vec![unwritten; page_size].into_boxed_slice()
+        });
+        page[index] = data;
+    }
+}
+
+impl<A: BusAddress> Device<A> for LazyRam {
+    fn read(&mut self, addr: A, _width: u8) -> u128 {
+        self.read_u8(addr.to_u128()) as u128
+    }
+
+    fn write(&mut self, addr: A, _width: u8, data: u128) {
+        self.write_u8(addr.to_u128(), data as u8);
+    }
+
+    fn max_width(&self) -> u8 {
+        1
+    }
+
+    /// The allocated pages, each as its 16-byte little-endian page number followed by its bytes.
+    fn save_state(&self) -> Option<Vec<u8>> {
+        let mut state = Vec::new();
+        for (page, bytes) in &self.pages {
+            state.extend_from_slice(&page.to_le_bytes());
+            state.extend_from_slice(bytes);
+        }
+        Some(state)
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> bool {
+        let record = 16 + self.page_size;
+        if !state.len().is_multiple_of(record) {
+            return false;
+        }
+        self.pages = state
+            .chunks(record)
+            .map(|chunk| {
+                let (page, bytes) = chunk.split_at(16);
+                (
+                    Address::from_le_bytes(page.try_into().unwrap()),
+                    bytes.into(),
+                )
+            })
+            .collect();
+        true
+    }
+
+    fn peek(&self, addr: A) -> Option<u8> {
+        Some(self.read_u8(addr.to_u128()))
+    }
+
+    fn poke(&mut self, addr: A, data: u8) -> bool {
+        self.write_u8(addr.to_u128(), data);
+        true
+    }
+}
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// Maps `ram` over `start..=end`, rebasing it to `start`.
+    pub fn map_lazy_ram(&mut self, start: A, end: A, mut ram: LazyRam) -> &mut MemoryMapEntry<A> {
+        ram.base = start.to_u128();
+        self.map_region(start, end, MemoryMapEntryType::ReadWrite)
+            .on_device(ram)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::Bus;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_lazy_ram() {
+        let allocated = Arc::new(Mutex::new(Vec::new()));
+        let log = allocated.clone();
+        let ram = LazyRam::new(0x1000)
+            .with_unwritten_byte(0xff)
+            .on_page_alloc(move |offset| log.lock().unwrap().push(offset));
+        let mut map = MemoryMap::new();
+        // A gigabyte of address space.
+        map.map_lazy_ram(0x4000_0000, 0x7fff_ffff, ram);
+
+        assert_eq!(map.read_u32_at(0x5000_0000), 0xffff_ffff);
+        assert!(allocated.lock().unwrap().is_empty());
+        map.write_u32_at(0x5000_0ffe, 0x1234_5678);
+        map.write_u8_at(0x7fff_ffff, 0x42);
+        map.write_u8_at(0x5000_0000, 0x00);
+        assert_eq!(
+            *allocated.lock().unwrap(),
+            [0x1000_0000, 0x1000_1000, 0x3fff_f000]
+        );
+        assert_eq!(map.read_u32_at(0x5000_0ffe), 0x1234_5678);
+        assert_eq!(map.read_u16_at(0x5000_0000), 0xff00);
+        assert_eq!(map.read_u8_at(0x7fff_ffff), 0x42);
+
+        let snapshot = map.snapshot().unwrap();
+        let mut restored = MemoryMap::new();
+        restored.map_lazy_ram(0x4000_0000, 0x7fff_ffff, LazyRam::new(0x1000));
+        restored.restore(&snapshot).unwrap();
+        assert_eq!(restored.read_u32_at(0x5000_0ffe), 0x1234_5678);
+        // Untouched pages read as the unwritten byte of the memory restored into.
+        assert_eq!(restored.read_u8_at(0x6000_0000), 0x00);
+    }
+
+    #[test]
+    fn test_lazy_ram_ignores_unmapped_read() {
+        let mut map = MemoryMap::new();
+        map.map_lazy_ram(
+            0x1000,
+            0x1fff,
+            LazyRam::new(0x100).with_unwritten_byte(0xff),
+        );
+        map.set_unmapped_read(Box::new(|_, _| 0x5a));
+        assert_eq!(map.read_u8_at(0x0000), 0x5a);
+        assert_eq!(map.read_u8_at(0x1000), 0xff);
+        assert_eq!(map.peek_u8(0x1000), Some(0xff));
+    }
+}
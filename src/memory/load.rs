@@ -0,0 +1,90 @@
+use super::{BusAddress, MemoryMap};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// Writes the contents of the file at `path` through the bus byte by byte from `base`,
+    /// returning how many bytes were written. Requires the `std` feature.
+    ///
+    /// Nothing is written if no region contains `base` or the data would run past the end of the
+    /// region that does. Loading fails with `InvalidInput` at the first byte the region doesn't
+    /// take, e.g. because it is read-only, leaving the bytes before it written.
+    pub fn load_binary(&mut self, base: A, path: &Path) -> io::Result<usize> {
+        let data = fs::read(path)?;
+        if self.needs_sort {
+            self.sort_entries();
+        }
+        let index = self.find_region(base).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("No region is mapped at {:#x}.", base),
+            )
+        })?;
+        let end = self.entries[index].end;
+        // A region spanning the whole of a 128-bit address space fits anything.
+        let room = (end - base).to_u128().checked_add(1);
+        if room.is_some_and(|room| data.len() as u128 > room) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} bytes at {:#x} run past the end of the region ending at {:#x}.",
+                    data.len(),
+                    base,
+                    end
+                ),
+            ));
+        }
+        self.try_write_block(base, &data).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Loading {} bytes at {:#x} failed: {}.",
+                    data.len(),
+                    base,
+                    err
+                ),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{Bus, MemoryMapEntryType, Ram, Rom};
+    use alloc::vec;
+    use std::env;
+    use std::process;
+
+    #[test]
+    fn test_load_binary() {
+        let path = env::temp_dir().join(format!("rustemu-load-{}.bin", process::id()));
+        fs::write(&path, [0x11, 0x22, 0x33, 0x44]).unwrap();
+        let mut map = MemoryMap::new();
+        map.map_ram(0x100, 0x107, Ram::new(8));
+
+        assert_eq!(map.load_binary(0x104, &path).unwrap(), 4);
+        map.select_address(0x104);
+        assert_eq!(map.read_u32(), 0x44332211);
+
+        let err = map.load_binary(0x105, &path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        map.select_address(0x105);
+        assert_eq!(map.read_u8(), 0x22);
+        assert!(map.load_binary(0x200, &path).is_err());
+
+        map.map_rom(Rom::from_bytes(0x200, vec![0; 8]));
+        let err = map.load_binary(0x200, &path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(map.read_u8_at(0x200), 0x00);
+        map.map_region(0x300, 0x3ff, MemoryMapEntryType::ReadWrite)
+            .on_read_u8(|_| 0);
+        assert!(map.load_binary(0x300, &path).is_err());
+
+        let mut full = MemoryMap::new();
+        full.map_ram(0, u128::MAX, Ram::new(8));
+        assert_eq!(full.load_binary(0, &path).unwrap(), 4);
+        fs::remove_file(&path).unwrap();
+    }
+}
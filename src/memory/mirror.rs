@@ -0,0 +1,139 @@
+use super::{check_bounds, BusAddress, MemoryMap, MemoryMapEntry, RegionSpec};
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// Maps `start..=end` as one region repeating the window `target` describes every `stride`
+    /// bytes, like the NES PPU registers mirrored every 8 bytes through 0x2000-0x3fff. An access
+    /// at `addr` reaches the target's device or delegates at `target.start + (addr - start) %
+    /// stride`, or at just the offset if the target is relative. The region takes the target's
+    /// type and name.
+    ///
+    /// Panics if the target's start is past its end, or `stride` is zero or larger than the
+    /// target window.
+    pub fn map_mirrored(
+        &mut self,
+        start: A,
+        end: A,
+        stride: A,
+        target: RegionSpec<A>,
+    ) -> &mut MemoryMapEntry<A> {
+        check_bounds(target.start, target.end);
+        let window = (target.end - target.start).to_u128().saturating_add(1);
+        if stride == A::ZERO || stride.to_u128() > window {
+            panic!(
+                "A mirror stride of {:#x} doesn't fit the {:#x}-byte target window.",
+                stride, window
+            );
+        }
+        let entry = self.map_region(start, end, target.entry_type);
+        entry.device = target.device;
+        entry.relative = target.relative;
+        entry.name = target.name;
+        entry.mirror = Some((stride, target.start));
+        entry
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{Bus, Device, MemoryMapEntryType};
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use std::sync::{Arc, Mutex};
+
+    /// Eight registers that log the address of every access.
+    struct Registers {
+        values: [u8; 8],
+        log: Arc<Mutex<Vec<u128>>>,
+    }
+
+    impl Device for Registers {
+        fn read(&mut self, addr: u128, _width: u8) -> u128 {
+            self.log.lock().unwrap().push(addr);
+            self.values[(addr - 0x2000) as usize] as u128
+        }
+
+        fn write(&mut self, addr: u128, _width: u8, data: u128) {
+            self.log.lock().unwrap().push(addr);
+            self.values[(addr - 0x2000) as usize] = data as u8;
+        }
+
+        fn max_width(&self) -> u8 {
+            1
+        }
+    }
+
+    #[test]
+    fn test_map_mirrored() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let registers = Registers {
+            values: [0; 8],
+            log: log.clone(),
+        };
+        let mut map = MemoryMap::new();
+        map.map_mirrored(
+            0x2000,
+            0x3fff,
+            0x8,
+            RegionSpec::new(0x2000, 0x2007, MemoryMapEntryType::ReadWrite)
+                .with_device(registers)
+                .named("PPU"),
+        );
+        assert_eq!(map.len(), 1);
+        map.write_u8_at(0x2006, 0x21);
+        assert_eq!(map.read_u8_at(0x3ffe), 0x21);
+        assert_eq!(map.read_u8_at(0x200e), 0x21);
+        map.write_u16_at(0x2ff7, 0x4455);
+        assert_eq!(map.read_u8_at(0x2007), 0x55);
+        assert_eq!(map.read_u8_at(0x2000), 0x44);
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![0x2006, 0x2006, 0x2006, 0x2007, 0x2000, 0x2007, 0x2000]
+        );
+        assert_eq!(map.region_at(0x3000).unwrap().name, Some("PPU"));
+    }
+
+    #[test]
+    fn test_map_mirrored_relative() {
+        let mut map = MemoryMap::new();
+        map.map_mirrored(
+            0x4000,
+            0x40ff,
+            0x3,
+            RegionSpec::new(0x0000, 0x0002, MemoryMapEntryType::Read).relative(true),
+        )
+        .set_read_u8(|offset| offset as u8);
+        assert_eq!(map.read_u8_at(0x4000), 0);
+        assert_eq!(map.read_u8_at(0x4005), 2);
+        assert_eq!(map.read_u8_at(0x40ff), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "doesn't fit the 0x8-byte target window")]
+    fn test_map_mirrored_stride_too_large() {
+        let mut map = MemoryMap::new();
+        map.map_mirrored(
+            0x2000,
+            0x3fff,
+            0x10,
+            RegionSpec::new(0x2000, 0x2007, MemoryMapEntryType::ReadWrite),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "is past its end")]
+    fn test_map_mirrored_inverted_target() {
+        let mut map = MemoryMap::new();
+        let mut target = RegionSpec::new(0x2000, 0x2007, MemoryMapEntryType::ReadWrite);
+        target.end = 0x1fff;
+        map.map_mirrored(0x2000, 0x3fff, 0x8, target);
+    }
+
+    #[test]
+    fn test_map_mirrored_full_range_target() {
+        let mut map = MemoryMap::new();
+        let target = RegionSpec::new(0, u128::MAX, MemoryMapEntryType::UnmappedHigh);
+        map.map_mirrored(0x2000, 0x3fff, 0x8, target);
+        assert_eq!(map.read_u8_at(0x2000), 0xff);
+    }
+}
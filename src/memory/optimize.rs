@@ -0,0 +1,170 @@
+use super::{BusAddress, MemoryMap, MemoryMapEntry};
+
+impl<A: BusAddress> MemoryMapEntry<A> {
+    /// Whether `next`, which starts right after this entry ends, answers every access just as
+    /// this entry would if it were extended over `next`.
+    ///
+    /// Delegates and devices can't be compared, so entries with either never merge. Plain RAM
+    /// is the exception, handled by [`MemoryMapEntry::merge_ram`].
+    fn merges_with(&self, next: &MemoryMapEntry<A>) -> bool {
+        let handler_free =
+            |entry: &MemoryMapEntry<A>| !entry.has_read_handler() && !entry.has_write_handler();
+        let alias_continues = match (self.alias, next.alias) {
+            (None, None) => true,
+            (Some(target), Some(next_target)) => {
+                target.wrapping_add(next.start - self.start) == next_target
+            }
+            _ => false,
+        };
+        handler_free(self) && handler_free(next) && alias_continues && self.behaves_like(next)
+    }
+
+    /// Whether the two entries treat accesses alike apart from their handlers and aliases.
+    fn behaves_like(&self, next: &MemoryMapEntry<A>) -> bool {
+        self.entry_type == next.entry_type
+            && self.name == next.name
+            && self.addr_mask == next.addr_mask
+            && self.read_mask == next.read_mask
+            && self.write_mask == next.write_mask
+            && self.priority == next.priority
+            && self.mirror.is_none()
+            && next.mirror.is_none()
+            && self.relative == next.relative
+            && self.allow_width_synthesis == next.allow_width_synthesis
+            && self.granularity == next.granularity
+            && self.register == next.register
+            && self.latency == next.latency
+            && self.contention.is_none()
+            && next.contention.is_none()
+    }
+
+    /// Whether the entry is nothing but its [`Ram`](super::Ram), with no delegates, alias or
+    /// relative addressing, and the RAM's buffer covers the region exactly once.
+    fn is_plain_ram(&mut self) -> bool {
+        let delegated = self.func_read_u8.is_some()
+            || self.func_read_u16.is_some()
+            || self.func_read_u32.is_some()
+            || self.func_read_u64.is_some()
+            || self.func_read_u128.is_some()
+            || self.func_write_u8.is_some()
+            || self.func_write_u16.is_some()
+            || self.func_write_u32.is_some()
+            || self.func_write_u64.is_some()
+            || self.func_write_u128.is_some();
+        if delegated || self.alias.is_some() || self.relative {
+            return false;
+        }
+        let (start, end) = (self.start.to_u128(), self.end.to_u128());
+        match self.device.as_mut().and_then(|device| device.as_ram_mut()) {
+            Some(ram) => {
+                ram.base() == start && (end - start).checked_add(1) == Some(ram.len() as u128)
+            }
+            None => false,
+        }
+    }
+
+    /// Moves the contents of `next`'s RAM onto the end of this entry's, if both entries are
+    /// plain RAM that behave alike, returning whether it did.
+    fn merge_ram(&mut self, next: &mut MemoryMapEntry<A>) -> bool {
+        if !self.behaves_like(next) || !self.is_plain_ram() || !next.is_plain_ram() {
+            return false;
+        }
+        let ram = self.device.as_mut().and_then(|device| device.as_ram_mut());
+        let next_ram = next.device.as_mut().and_then(|device| device.as_ram_mut());
+        match (ram, next_ram) {
+            (Some(ram), Some(next_ram)) => ram.append(next_ram),
+            _ => false,
+        }
+    }
+}
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// Merges runs of adjacent regions that behave identically, such as consecutive aliases of
+    /// consecutive memory, into single regions to cut down lookup work. Adjacent
+    /// [`Ram`](super::Ram) regions whose buffers are contiguous merge into one holding both
+    /// buffers, unless either tracks poisoned reads. Other regions with a device or delegates
+    /// are left as they are, and so is a map with overlapping regions. The merged region keeps
+    /// the combined access counts.
+    pub fn optimize(&mut self) {
+        if self.needs_sort {
+            self.sort_entries();
+        }
+        if self.overlapping {
+            return;
+        }
+        let mut i = 1;
+        while i + 1 < self.entries.len() {
+            let (left, right) = self.entries.split_at_mut(i + 1);
+            let (current, next) = (&mut left[i], &mut right[0]);
+            let merges = current.end.checked_add(A::ONE) == Some(next.start)
+                && (current.merges_with(next) || current.merge_ram(next));
+            if !merges {
+                i += 1;
+                continue;
+            }
+            let next = self.entries.remove(i + 1);
+            let current = &mut self.entries[i];
+            current.end = next.end;
+            for (count, merged) in current.reads.iter_mut().zip(next.reads) {
+                *count += merged;
+            }
+            for (count, merged) in current.writes.iter_mut().zip(next.writes) {
+                *count += merged;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{Bus, MemoryMapEntryType, Ram};
+
+    #[test]
+    fn test_optimize() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x0fff, Ram::new(0x1000));
+        map.write_u32_at(0x07fe, 0xdeadbeef);
+        // Two halves of one mirror of the RAM.
+        map.add_alias(0x1800, 0x1fff, 0x0800);
+        map.add_alias(0x1000, 0x17ff, 0x0000);
+        map.map_region(0x4000, 0x40ff, MemoryMapEntryType::UnmappedHigh);
+        map.map_region(0x4100, 0x41ff, MemoryMapEntryType::UnmappedHigh);
+        map.map_region(0x4200, 0x42ff, MemoryMapEntryType::UnmappedHigh)
+            .set_name("IO");
+        assert_eq!(map.len(), 6);
+
+        map.optimize();
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.read_u32_at(0x17fe), 0xdeadbeef);
+        assert_eq!(map.read_u8_at(0x1fff), 0x00);
+        assert_eq!(map.read_u16_at(0x41ff), 0xffff);
+        assert_eq!(map.region_at(0x4100).unwrap().start, 0x4000);
+        assert_eq!(map.region_at(0x4200).unwrap().name, Some("IO"));
+    }
+
+    #[test]
+    fn test_optimize_ram() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x5000, 0x50ff, Ram::new(0x100));
+        map.map_ram(0x5100, 0x51ff, Ram::new(0x100));
+        map.write_u32_at(0x50fe, 0xdeadbeef);
+        map.write_u8_at(0x5000, 0x11);
+        map.write_u8_at(0x51ff, 0x22);
+        map.optimize();
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.read_u32_at(0x50fe), 0xdeadbeef);
+        assert_eq!(map.read_u8_at(0x5000), 0x11);
+        assert_eq!(map.read_u8_at(0x51ff), 0x22);
+        assert_eq!(map.region_bytes(0x5000).unwrap().len(), 0x200);
+
+        // A mirrored RAM and one tracking poisoned reads can't be merged.
+        map.map_ram(0x6000, 0x60ff, Ram::new(0x10));
+        map.map_ram(0x6100, 0x61ff, Ram::new(0x100));
+        let mut tracked = Ram::new(0x100);
+        tracked.enable_poison_tracking(true);
+        map.map_ram(0x6200, 0x62ff, tracked);
+        map.optimize();
+        assert_eq!(map.len(), 4);
+    }
+}
@@ -0,0 +1,128 @@
+use super::{AccessKind, AccessOrigin, BusAddress, MemoryMap, MemoryMapEntryType};
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// Reads the byte at `addr`, after the address mask, without watchpoints, stats or the open
+    /// bus latch noticing, for debuggers. The trace hook sees it as a `Debugger` read. Returns
+    /// `None` where that's impossible: in regions handled by delegates or by a device that can't
+    /// peek, and in unmapped space answered by an unmapped read provider.
+    pub fn peek_u8(&self, addr: A) -> Option<u8> {
+        let addr = addr & self.global_addr_mask;
+        let data = self.peek_byte(addr)?;
+        self.trace_access(
+            addr,
+            1,
+            AccessKind::Read,
+            data as u128,
+            AccessOrigin::Debugger,
+        );
+        Some(data)
+    }
+
+    fn peek_byte(&self, addr: A) -> Option<u8> {
+        let addr = addr & self.global_addr_mask;
+        let entry = &self.entries[self.find_region(addr).unwrap_or(0)];
+        if let Some(target) = entry.alias_target(addr) {
+            return self.peek_byte(target);
+        }
+        match entry.entry_type {
+            MemoryMapEntryType::UnmappedLow | MemoryMapEntryType::UnmappedHigh
+                if self.unmapped_read.is_some() =>
+            {
+                None
+            }
+            MemoryMapEntryType::UnmappedLow => Some(0x00),
+            MemoryMapEntryType::UnmappedHigh => Some(0xff),
+            MemoryMapEntryType::UnmappedOpenBus => Some(self.bus_latch as u8),
+            MemoryMapEntryType::Write if !entry.has_read_handler() => Some(self.bus_latch as u8),
+            _ => entry
+                .device
+                .as_ref()?
+                .peek(entry.handler_addr(addr, AccessKind::Read)),
+        }
+    }
+
+    /// Changes the byte at `addr` in the backing storage of the device there, bypassing the
+    /// hooks like [`MemoryMap::peek_u8`] and any write protection, e.g. to patch a ROM. Returns
+    /// whether the device took it, and only then tells the trace hook of a `Debugger` write.
+    pub fn poke_u8(&mut self, addr: A, data: u8) -> bool {
+        let addr = addr & self.global_addr_mask;
+        if !self.poke_byte(addr, data) {
+            return false;
+        }
+        self.trace_access(
+            addr,
+            1,
+            AccessKind::Write,
+            data as u128,
+            AccessOrigin::Debugger,
+        );
+        true
+    }
+
+    fn poke_byte(&mut self, addr: A, data: u8) -> bool {
+        let addr = addr & self.global_addr_mask;
+        let index = match self.find_region(addr) {
+            Some(index) => index,
+            None => return false,
+        };
+        let entry = &mut self.entries[index];
+        if let Some(target) = entry.alias_target(addr) {
+            return self.poke_byte(target, data);
+        }
+        let addr = entry.handler_addr(addr, AccessKind::Write);
+        match entry.device.as_mut() {
+            Some(device) => device.poke(addr, data),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{AccessKind, BankedRegion, Bus, Ram, Rom};
+    use alloc::boxed::Box;
+    use alloc::vec;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_peek_and_poke() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x00ff, Ram::new(0x100));
+        map.map_rom(Rom::from_bytes(0x1000, vec![0xaa; 0x10]));
+        map.map_banked(0x2000, BankedRegion::new(2, 0x10));
+        map.map_region(0x3000, 0x30ff, MemoryMapEntryType::Read)
+            .on_read_u8(|_| 0x55);
+        map.map_region(0x4000, 0x40ff, MemoryMapEntryType::UnmappedHigh);
+        map.add_alias(0x8000, 0x80ff, 0x0000);
+        map.add_watchpoint(
+            0x0000,
+            0xffff,
+            AccessKind::ReadWrite,
+            Box::new(move |_, _, _| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }),
+        );
+        map.write_u8_at(0x0010, 0x42);
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        assert_eq!(map.peek_u8(0x0010), Some(0x42));
+        assert_eq!(map.peek_u8(0x8010), Some(0x42));
+        assert_eq!(map.peek_u8(0x1000), Some(0xaa));
+        assert_eq!(map.peek_u8(0x2000), Some(0x00));
+        assert_eq!(map.peek_u8(0x3000), None);
+        assert_eq!(map.peek_u8(0x4000), Some(0xff));
+        assert_eq!(map.peek_u8(0x5000), Some(0x00));
+
+        assert!(map.poke_u8(0x8011, 0x43));
+        assert!(map.poke_u8(0x1000, 0xbb));
+        assert!(!map.poke_u8(0x3000, 0x00));
+        assert!(!map.poke_u8(0x5000, 0x00));
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+        assert_eq!(map.read_u8_at(0x0011), 0x43);
+        assert_eq!(map.read_u8_at(0x1000), 0xbb);
+    }
+}
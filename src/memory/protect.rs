@@ -0,0 +1,170 @@
+use super::{BusAddress, MemoryMap, MemoryMapError};
+use core::ops::BitOr;
+
+/// A set of access rights, combined with `|`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Permissions(u8);
+
+impl Permissions {
+    pub const NONE: Permissions = Permissions(0);
+    pub const READ: Permissions = Permissions(1);
+    pub const WRITE: Permissions = Permissions(2);
+    pub const EXECUTE: Permissions = Permissions(4);
+    pub const ALL: Permissions = Permissions(7);
+
+    /// Whether every right in `other` is also in `self`.
+    pub fn contains(self, other: Permissions) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// The rights as bits to save, `READ`, `WRITE` and `EXECUTE` being 1, 2 and 4.
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// The rights saved with `bits`, or `None` if `bits` has any other bit set.
+    pub fn from_bits(bits: u8) -> Option<Permissions> {
+        if bits & !Permissions::ALL.0 != 0 {
+            return None;
+        }
+        Some(Permissions(bits))
+    }
+}
+
+impl BitOr for Permissions {
+    type Output = Permissions;
+
+    fn bitor(self, rhs: Permissions) -> Permissions {
+        Permissions(self.0 | rhs.0)
+    }
+}
+
+#[derive(Clone)]
+pub(super) struct Protection<A> {
+    start: A,
+    end: A,
+    permissions: Permissions,
+}
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// Restricts accesses to `start..=end` to `permissions`. Reads need `READ`, writes `WRITE`
+    /// and fetches `EXECUTE`, and the fallible accesses fail with `Protection` for any byte
+    /// lacking them. The range needn't line up with regions, so a region can be protected page
+    /// by page. A later call wins where ranges overlap, and addresses never given permissions
+    /// allow everything.
+    ///
+    /// Block transfers, `fill` and the trace and watchpoint hooks ignore permissions.
+    pub fn set_permissions(&mut self, start: A, end: A, permissions: Permissions) {
+        self.protections.push(Protection {
+            start,
+            end,
+            permissions,
+        });
+    }
+
+    /// Lifts every restriction set by [`MemoryMap::set_permissions`].
+    pub fn clear_permissions(&mut self) {
+        self.protections.clear();
+    }
+
+    /// The rights at `addr`, going by the most recent range covering it.
+    fn permissions_at(&self, addr: A) -> Permissions {
+        self.protections
+            .iter()
+            .rev()
+            .find(|protection| protection.start <= addr && protection.end >= addr)
+            .map_or(Permissions::ALL, |protection| protection.permissions)
+    }
+
+    /// Refuses a `width`-byte access at the selected address if any of its bytes lacks `needed`.
+    pub(super) fn check_permissions(
+        &self,
+        width: u8,
+        needed: Permissions,
+    ) -> Result<(), MemoryMapError> {
+        if self.protections.is_empty() {
+            return Ok(());
+        }
+        for i in 0..width {
+            let addr =
+                self.current_addr.wrapping_add(A::from_u128(i as u128)) & self.global_addr_mask;
+            if !self.permissions_at(addr).contains(needed) {
+                return Err(MemoryMapError::Protection {
+                    addr: addr.to_u128(),
+                    needed,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{Bus, Ram};
+
+    #[test]
+    fn test_page_permissions() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x1fff, Ram::new(0x2000));
+        map.set_permissions(0x0000, 0x0fff, Permissions::READ | Permissions::EXECUTE);
+        map.set_permissions(0x1000, 0x1fff, Permissions::READ | Permissions::WRITE);
+
+        map.select_address(0x0100);
+        assert_eq!(map.try_read_u32(), Ok(0));
+        assert_eq!(
+            map.try_write_u8(0x42),
+            Err(MemoryMapError::Protection {
+                addr: 0x0100,
+                needed: Permissions::WRITE
+            })
+        );
+        assert_eq!(map.try_fetch_u32(), Ok(0));
+
+        // The last two bytes of this access fall on the page that isn't executable.
+        map.select_address(0x0ffe);
+        assert_eq!(
+            map.try_fetch_u32(),
+            Err(MemoryMapError::Protection {
+                addr: 0x1000,
+                needed: Permissions::EXECUTE
+            })
+        );
+        assert_eq!(
+            map.try_write_u16(0xbeef),
+            Err(MemoryMapError::Protection {
+                addr: 0x0ffe,
+                needed: Permissions::WRITE
+            })
+        );
+        map.select_address(0x1000);
+        assert_eq!(map.try_write_u16(0xbeef), Ok(()));
+        assert_eq!(map.try_read_u16(), Ok(0xbeef));
+
+        map.clear_permissions();
+        map.select_address(0x0100);
+        assert_eq!(map.try_write_u8(0x42), Ok(()));
+        assert!(Permissions::ALL.contains(Permissions::READ | Permissions::EXECUTE));
+        assert!(!Permissions::NONE.contains(Permissions::READ));
+    }
+
+    #[test]
+    #[should_panic(expected = "Your memory map is broken")]
+    fn test_fetch_from_no_execute_page() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x0fff, Ram::new(0x1000));
+        map.set_permissions(0x0800, 0x0fff, Permissions::READ | Permissions::WRITE);
+        map.select_address(0x0800);
+        assert_eq!(map.read_u16(), 0);
+        map.fetch_u16();
+    }
+
+    #[test]
+    fn test_permission_bits() {
+        let rights = Permissions::READ | Permissions::EXECUTE;
+        assert_eq!(rights.bits(), 5);
+        assert_eq!(Permissions::from_bits(rights.bits()), Some(rights));
+        assert_eq!(Permissions::from_bits(8), None);
+    }
+}
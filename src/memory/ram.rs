@@ -0,0 +1,362 @@
+use super::{Address, BusAddress, Device, MemoryMap, MemoryMapEntry, MemoryMapEntryType};
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Plain read/write memory backed by a byte buffer.
+///
+/// Addresses are taken relative to `base`. Accesses past the end of the buffer wrap around, so
+/// mapping a small `Ram` over a larger range mirrors it across the whole range. It works on a bus
+/// of any address width, keeping its base as a full-width `Address`.
+#[derive(Clone, Debug)]
+pub struct Ram {
+    base: Address,
+    data: Vec<u8>,
+    /// One bit per byte, set once the byte has been written, while poison tracking is on.
+    written: Option<Vec<u64>>,
+    on_poisoned_read: Option<PoisonCallback>,
+}
+
+/// Told the address of each read of a never-written byte of a [`Ram`].
+#[derive(Clone)]
+struct PoisonCallback(Arc<dyn Fn(Address) + Send + Sync>);
+
+impl fmt::Debug for PoisonCallback {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("PoisonCallback")
+    }
+}
+
+impl Ram {
+    pub fn new(size: usize) -> Ram {
+        if size == 0 {
+            panic!("RAM must be at least one byte long.");
+        }
+        Ram {
+            base: 0,
+            data: vec![0; size],
+            written: None,
+            on_poisoned_read: None,
+        }
+    }
+
+    /// Starts tracking which bytes have been written, treating every byte as unwritten, or stops
+    /// and forgets. Reads of unwritten bytes still return their contents but are reported to
+    /// the callback set with [`Ram::on_poisoned_read`], to catch guest code that relies on
+    /// uninitialized memory. Poking, loading a snapshot and writes through the bus all count as
+    /// writing.
+    pub fn enable_poison_tracking(&mut self, enable: bool) {
+        self.written = if enable {
+            Some(vec![0; self.data.len().div_ceil(64)])
+        } else {
+            None
+        };
+    }
+
+    /// Sets the callback told the bus address of each read of an unwritten byte. Peeks are
+    /// never reported.
+    pub fn on_poisoned_read<F>(&mut self, callback: F)
+    where
+        F: Fn(Address) + Send + Sync + 'static,
+    {
+        self.on_poisoned_read = Some(PoisonCallback(Arc::new(callback)));
+    }
+
+    /// Whether the byte at `addr` has been written since poison tracking was turned on. Always
+    /// true while it is off.
+    pub fn is_initialized(&self, addr: Address) -> bool {
+        let index = self.index(addr);
+        self.written
+            .as_ref()
+            .is_none_or(|written| written[index / 64] & (1 << (index % 64)) != 0)
+    }
+
+    pub fn base(&self) -> Address {
+        self.base
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn index(&self, addr: Address) -> usize {
+        (addr.wrapping_sub(self.base) % self.data.len() as Address) as usize
+    }
+
+    /// Appends the contents of `next`, whose buffer must start where this one ends, leaving
+    /// `next` empty. Returns whether it did, which it doesn't while either tracks poisoned reads.
+    pub(super) fn append(&mut self, next: &mut Ram) -> bool {
+        let contiguous = self.base.checked_add(self.data.len() as Address) == Some(next.base);
+        let tracked = |ram: &Ram| ram.written.is_some() || ram.on_poisoned_read.is_some();
+        if !contiguous || tracked(self) || tracked(next) {
+            return false;
+        }
+        self.data.append(&mut next.data);
+        true
+    }
+
+    pub fn read_u8(&self, addr: Address) -> u8 {
+        self.data[self.index(addr)]
+    }
+
+    pub fn write_u8(&mut self, addr: Address, data: u8) {
+        let index = self.index(addr);
+        self.data[index] = data;
+        if let Some(written) = self.written.as_mut() {
+            written[index / 64] |= 1 << (index % 64);
+        }
+    }
+}
+
+impl<A: BusAddress> Device<A> for Ram {
+    fn read(&mut self, addr: A, _width: u8) -> u128 {
+        let addr = addr.to_u128();
+        if let Some(callback) = self.on_poisoned_read.as_ref() {
+            if !self.is_initialized(addr) {
+                (callback.0)(addr);
+            }
+        }
+        self.read_u8(addr) as u128
+    }
+
+    fn write(&mut self, addr: A, _width: u8, data: u128) {
+        self.write_u8(addr.to_u128(), data as u8);
+    }
+
+    fn max_width(&self) -> u8 {
+        1
+    }
+
+    fn save_state(&self) -> Option<Vec<u8>> {
+        Some(self.data.clone())
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> bool {
+        if state.len() != self.data.len() {
+            return false;
+        }
+        self.data.copy_from_slice(state);
+        if let Some(written) = self.written.as_mut() {
+            written.fill(u64::MAX);
+        }
+        true
+    }
+
+    fn peek(&self, addr: A) -> Option<u8> {
+        Some(self.read_u8(addr.to_u128()))
+    }
+
+    fn poke(&mut self, addr: A, data: u8) -> bool {
+        self.write_u8(addr.to_u128(), data);
+        true
+    }
+
+    fn try_clone(&self) -> Option<Box<dyn Device<A> + Send>> {
+        Some(Box::new(self.clone()))
+    }
+
+    fn backing(&self) -> Option<(u128, usize)> {
+        Some((self.base, self.data.len()))
+    }
+
+    fn bytes(&self) -> Option<&[u8]> {
+        Some(&self.data)
+    }
+
+    /// Lends out the buffer, which counts as writing all of it for poison tracking.
+    fn bytes_mut(&mut self) -> Option<&mut [u8]> {
+        if let Some(written) = self.written.as_mut() {
+            written.fill(u64::MAX);
+        }
+        Some(&mut self.data)
+    }
+
+    fn as_ram_mut(&mut self) -> Option<&mut Ram> {
+        Some(self)
+    }
+}
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// Maps `ram` over `start..=end`, rebasing it to `start`.
+    pub fn map_ram(&mut self, start: A, end: A, mut ram: Ram) -> &mut MemoryMapEntry<A> {
+        ram.base = start.to_u128();
+        self.map_region(start, end, MemoryMapEntryType::ReadWrite)
+            .on_device(ram)
+    }
+
+    /// The index of the region starting at `start` without sorting the map first, preferring the
+    /// most recently mapped one like `region_starting_at`.
+    fn region_index_at(&self, start: A) -> Option<usize> {
+        self.entries[1..]
+            .iter()
+            .rposition(|entry| entry.start == start)
+            .map(|index| index + 1)
+    }
+
+    /// The bytes backing the region starting at `start`, e.g. to blit a framebuffer, or `None`
+    /// if no region starts there or its device keeps no buffer, as with delegates and shared
+    /// devices. Byte `i` of a [`Ram`] or [`Rom`](super::Rom) region's slice is what address
+    /// `start + i` reads.
+    ///
+    /// The slice is the device's own storage, so reading it bypasses watchpoints, trace hooks,
+    /// access accounting and the open bus latch.
+    pub fn region_bytes(&self, start: A) -> Option<&[u8]> {
+        let index = self.region_index_at(start)?;
+        self.entries[index].device.as_ref()?.bytes()
+    }
+
+    /// Like [`MemoryMap::region_bytes`], for changing the bytes in place. Rom regions lend out
+    /// nothing here; poke them instead. Writes through the slice bypass the same hooks as reads.
+    pub fn region_bytes_mut(&mut self, start: A) -> Option<&mut [u8]> {
+        let index = self.region_index_at(start)?;
+        self.entries[index].device.as_mut()?.bytes_mut()
+    }
+
+    /// A 64-bit FNV-1a hash of the bytes backing the region starting at `start`, for a
+    /// debugger to poll cheaply to see whether the region changed instead of diffing it. `None`
+    /// whenever [`MemoryMap::region_bytes`] is.
+    ///
+    /// The hash isn't cryptographic, so a change can go unnoticed, if very rarely.
+    pub fn region_hash(&self, start: A) -> Option<u64> {
+        let bytes = self.region_bytes(start)?;
+        Some(bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{Bus, MemoryMapError, Rom};
+
+    #[test]
+    fn test_ram() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x100, 0x1ff, Ram::new(0x100));
+        map.select_address(0x104);
+        map.write_u32(0x12345678);
+        let bytes: Vec<u8> = (0x104..0x108)
+            .map(|addr| {
+                map.select_address(addr);
+                map.read_u8()
+            })
+            .collect();
+        assert_eq!(bytes, [0x78, 0x56, 0x34, 0x12]);
+        map.select_address(0x104);
+        assert_eq!(map.read_u32(), 0x12345678);
+    }
+
+    #[test]
+    fn test_ram_wraps() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x100, 0x1ff, Ram::new(0x10));
+        map.select_address(0x101);
+        map.write_u8(0xaa);
+        map.select_address(0x111);
+        assert_eq!(map.read_u8(), 0xaa);
+        map.select_address(0x1f1);
+        assert_eq!(map.read_u8(), 0xaa);
+    }
+
+    #[test]
+    fn test_strict_backing() {
+        let mut map = MemoryMap::new();
+        // One byte bigger than the RAM behind it.
+        map.map_ram(0x100, 0x110, Ram::new(0x10));
+        map.set_strict_backing(true);
+        map.select_address(0x10f);
+        assert_eq!(map.try_write_u8(0xaa), Ok(()));
+        map.select_address(0x110);
+        let past_end = || MemoryMapError::BackingOutOfBounds {
+            addr: 0x110,
+            region_start: 0x100,
+            region_len: 0x10,
+        };
+        assert_eq!(map.try_read_u8(), Err(past_end()));
+        assert_eq!(map.try_write_u8(0xbb), Err(past_end()));
+        // Only the second byte is out of bounds.
+        map.select_address(0x10f);
+        assert_eq!(map.try_read_u16(), Err(past_end()));
+
+        map.set_strict_backing(false);
+        map.select_address(0x110);
+        assert_eq!(map.try_read_u8(), Ok(0x00));
+    }
+
+    #[test]
+    fn test_region_bytes() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x1000, 0x10ff, Ram::new(0x100));
+        map.map_rom(Rom::from_bytes(0x2000, vec![0xaa; 0x10]));
+        map.map_region(0x3000, 0x30ff, MemoryMapEntryType::ReadWrite)
+            .on_read_u8(|_| 0);
+        map.write_u32_at(0x1010, 0xdead_beef);
+
+        let bytes = map.region_bytes(0x1000).unwrap();
+        assert_eq!(bytes.len(), 0x100);
+        assert_eq!(bytes[0x10..0x14], [0xef, 0xbe, 0xad, 0xde]);
+        assert_eq!(map.region_bytes(0x2000), Some(&[0xaa; 0x10][..]));
+        assert_eq!(map.region_bytes(0x3000), None);
+        assert_eq!(map.region_bytes(0x1001), None);
+
+        map.region_bytes_mut(0x1000).unwrap()[0x20] = 0x42;
+        assert_eq!(map.read_u8_at(0x1020), 0x42);
+        assert!(map.region_bytes_mut(0x2000).is_none());
+    }
+
+    #[test]
+    fn test_region_hash() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x1000, 0x10ff, Ram::new(0x100));
+        map.map_region(0x3000, 0x30ff, MemoryMapEntryType::ReadWrite)
+            .on_read_u8(|_| 0);
+
+        let hash = map.region_hash(0x1000).unwrap();
+        map.read_u32_at(0x1010);
+        assert_eq!(map.region_hash(0x1000), Some(hash));
+        map.write_u8_at(0x1010, 0x01);
+        let changed = map.region_hash(0x1000).unwrap();
+        assert_ne!(changed, hash);
+        assert_eq!(map.region_hash(0x1000), Some(changed));
+        map.write_u8_at(0x1010, 0x00);
+        assert_eq!(map.region_hash(0x1000), Some(hash));
+        assert_eq!(map.region_hash(0x3000), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one byte")]
+    fn test_empty_ram() {
+        Ram::new(0);
+    }
+
+    #[test]
+    fn test_poison_tracking() {
+        use std::sync::Mutex;
+
+        let reads = Arc::new(Mutex::new(Vec::new()));
+        let log = reads.clone();
+        let mut ram = Ram::new(0x100);
+        ram.enable_poison_tracking(true);
+        ram.on_poisoned_read(move |addr| log.lock().unwrap().push(addr));
+        let mut map = MemoryMap::new();
+        map.map_ram(0x1000, 0x10ff, ram);
+
+        assert_eq!(map.read_u8_at(0x1010), 0);
+        map.write_u8_at(0x1010, 0x42);
+        assert_eq!(map.read_u8_at(0x1010), 0x42);
+        // Half of this was never written.
+        map.write_u8_at(0x1041, 0x11);
+        assert_eq!(map.read_u16_at(0x1040), 0x1100);
+        assert_eq!(map.peek_u8(0x10ff), Some(0));
+        assert!(map.poke_u8(0x10ff, 0x99));
+        assert_eq!(map.read_u8_at(0x10ff), 0x99);
+        assert_eq!(*reads.lock().unwrap(), [0x1010, 0x1040]);
+    }
+}
@@ -0,0 +1,224 @@
+use super::{AccessKind, AccessOrigin, Address, Bus, BusAddress, TraceEvent};
+use alloc::vec::Vec;
+
+/// Wraps a bus, forwarding every transaction to it and logging each one, so a run can be checked
+/// afterwards or replayed with a [`ReplayBus`]. Fetches are logged as reads.
+pub struct RecordingBus<B: Bus<A>, A: BusAddress = Address> {
+    inner: B,
+    addr: A,
+    log: Vec<TraceEvent<A>>,
+}
+
+impl<B: Bus<A>, A: BusAddress> RecordingBus<B, A> {
+    pub fn new(inner: B) -> RecordingBus<B, A> {
+        RecordingBus {
+            inner,
+            addr: A::ZERO,
+            log: Vec::new(),
+        }
+    }
+
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut B {
+        &mut self.inner
+    }
+
+    pub fn log(&self) -> &[TraceEvent<A>] {
+        &self.log
+    }
+
+    pub fn into_log(self) -> Vec<TraceEvent<A>> {
+        self.log
+    }
+
+    fn record(&mut self, width: u8, kind: AccessKind, data: u128) {
+        self.log.push(TraceEvent {
+            addr: self.addr,
+            width,
+            kind,
+            data,
+            origin: AccessOrigin::Guest,
+        });
+    }
+}
+
+macro_rules! recorded_accesses {
+    ($($read:ident, $fetch:ident, $write:ident, $ty:ty, $width:expr;)*) => {
+        $(
+            fn $read(&mut self) -> $ty {
+                let data = self.inner.$read();
+                self.record($width, AccessKind::Read, data as u128);
+                data
+            }
+
+            fn $fetch(&mut self) -> $ty {
+                let data = self.inner.$fetch();
+                self.record($width, AccessKind::Read, data as u128);
+                data
+            }
+
+            fn $write(&mut self, data: $ty) {
+                self.inner.$write(data);
+                self.record($width, AccessKind::Write, data as u128);
+            }
+        )*
+    };
+}
+
+impl<B: Bus<A>, A: BusAddress> Bus<A> for RecordingBus<B, A> {
+    fn select_address(&mut self, addr: A) {
+        self.addr = addr;
+        self.inner.select_address(addr);
+    }
+
+    fn handles(&self, addr: A) -> bool {
+        self.inner.handles(addr)
+    }
+
+    recorded_accesses! {
+        read_u8, fetch_u8, write_u8, u8, 1;
+        read_u16, fetch_u16, write_u16, u16, 2;
+        read_u32, fetch_u32, write_u32, u32, 4;
+        read_u64, fetch_u64, write_u64, u64, 8;
+        read_u128, fetch_u128, write_u128, u128, 16;
+    }
+}
+
+/// Plays back a log from a [`RecordingBus`] without the bus it was recorded on, e.g. to feed a
+/// CPU core in a unit test. Each access must match the next logged one in address, width and
+/// direction, and writes in value too; reads return the logged value.
+///
+/// Panics when an access diverges from the log or runs past its end.
+pub struct ReplayBus<A: BusAddress = Address> {
+    addr: A,
+    log: Vec<TraceEvent<A>>,
+    next: usize,
+}
+
+impl<A: BusAddress> ReplayBus<A> {
+    pub fn new(log: Vec<TraceEvent<A>>) -> ReplayBus<A> {
+        ReplayBus {
+            addr: A::ZERO,
+            log,
+            next: 0,
+        }
+    }
+
+    /// Whether every logged access has been replayed.
+    pub fn is_finished(&self) -> bool {
+        self.next == self.log.len()
+    }
+
+    fn replay(&mut self, width: u8, kind: AccessKind, data: Option<u128>) -> u128 {
+        let expected = match self.log.get(self.next) {
+            Some(event) => *event,
+            None => panic!(
+                "Replay ran past the end of the log at a {}-byte {:?} of {:#x}.",
+                width, kind, self.addr
+            ),
+        };
+        let actual = TraceEvent {
+            addr: self.addr,
+            width,
+            kind,
+            data: data.unwrap_or(expected.data),
+            origin: AccessOrigin::Guest,
+        };
+        if actual != expected {
+            panic!(
+                "Replay diverged from the log at entry {}: expected {:?}, got {:?}.",
+                self.next, expected, actual
+            );
+        }
+        self.next += 1;
+        expected.data
+    }
+}
+
+macro_rules! replayed_accesses {
+    ($($read:ident, $write:ident, $ty:ty, $width:expr;)*) => {
+        $(
+            fn $read(&mut self) -> $ty {
+                self.replay($width, AccessKind::Read, None) as $ty
+            }
+
+            fn $write(&mut self, data: $ty) {
+                self.replay($width, AccessKind::Write, Some(data as u128));
+            }
+        )*
+    };
+}
+
+impl<A: BusAddress> Bus<A> for ReplayBus<A> {
+    fn select_address(&mut self, addr: A) {
+        self.addr = addr;
+    }
+
+    replayed_accesses! {
+        read_u8, write_u8, u8, 1;
+        read_u16, write_u16, u16, 2;
+        read_u32, write_u32, u32, 4;
+        read_u64, write_u64, u64, 8;
+        read_u128, write_u128, u128, 16;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{MemoryMap, Ram};
+
+    fn run<B: Bus>(bus: &mut B) -> u32 {
+        bus.write_u16_at(0x10, 0xbeef);
+        let data = bus.read_u32_at(0x10);
+        bus.write_u8_at(0x20, (data >> 8) as u8);
+        data
+    }
+
+    #[test]
+    fn test_record_and_replay() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x00, 0xff, Ram::new(0x100));
+        map.write_u32_at(0x10, 0x1234_5678);
+        let mut bus = RecordingBus::new(map);
+        assert_eq!(run(&mut bus), 0x1234_beef);
+        assert_eq!(bus.inner_mut().read_u8_at(0x20), 0xbe);
+
+        let event = |addr, width, kind, data| TraceEvent {
+            addr,
+            width,
+            kind,
+            data,
+            origin: AccessOrigin::Guest,
+        };
+        let log = bus.into_log();
+        assert_eq!(
+            log,
+            [
+                event(0x10, 2, AccessKind::Write, 0xbeef),
+                event(0x10, 4, AccessKind::Read, 0x1234_beef),
+                event(0x20, 1, AccessKind::Write, 0xbe),
+            ]
+        );
+
+        let mut replay = ReplayBus::new(log);
+        assert_eq!(run(&mut replay), 0x1234_beef);
+        assert!(replay.is_finished());
+    }
+
+    #[test]
+    #[should_panic(expected = "Replay diverged from the log")]
+    fn test_replay_divergence() {
+        let mut replay: ReplayBus = ReplayBus::new(vec![TraceEvent {
+            addr: 0x10,
+            width: 1,
+            kind: AccessKind::Read,
+            data: 0x42,
+            origin: AccessOrigin::Guest,
+        }]);
+        replay.read_u16_at(0x10);
+    }
+}
@@ -0,0 +1,107 @@
+use super::{BusAddress, MemoryMap, MemoryMapEntry, MemoryMapError};
+
+/// How a write wider than a region's registers is made to fit them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegisterOverflow {
+    /// Keep the low bits, as most hardware does.
+    Wrap,
+    /// Clamp the value to the largest one the register holds.
+    Saturate,
+}
+
+impl<A: BusAddress> MemoryMapEntry<A> {
+    /// Makes the region a bank of `width`-byte registers. A wider write is narrowed to a single
+    /// `width`-byte write at the same address, of its value wrapped or saturated as `overflow`
+    /// says, instead of spilling into the registers after it. In strict mode a value that
+    /// doesn't fit fails with `RegisterOverflow` and nothing is written.
+    ///
+    /// Panics unless `width` is 1, 2, 4 or 8.
+    pub fn set_register_width(&mut self, width: u8, overflow: RegisterOverflow) {
+        if !matches!(width, 1 | 2 | 4 | 8) {
+            panic!("A register width of {} bytes isn't supported.", width);
+        }
+        self.register = Some((width, overflow));
+    }
+}
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// The register width and value a `width`-byte write of `data` to the entry at `index`
+    /// becomes, or `None` if it fits the region's registers as it is.
+    pub(super) fn narrow_register_write(
+        &self,
+        index: usize,
+        width: u8,
+        data: u128,
+    ) -> Result<Option<(u8, u128)>, MemoryMapError> {
+        let (register_width, overflow) = match self.entries[index].register {
+            Some((register_width, overflow)) if width > register_width => {
+                (register_width, overflow)
+            }
+            _ => return Ok(None),
+        };
+        let max = (1 << (register_width as u32 * 8)) - 1;
+        if data > max && self.strict_permissions {
+            return Err(MemoryMapError::RegisterOverflow {
+                addr: self.current_addr.to_u128(),
+                width: register_width,
+                data,
+            });
+        }
+        let narrowed = match overflow {
+            RegisterOverflow::Wrap => data & max,
+            RegisterOverflow::Saturate => data.min(max),
+        };
+        Ok(Some((register_width, narrowed)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{Bus, MemoryMapEntryType};
+    use std::sync::{Arc, Mutex};
+    use std::vec::Vec;
+
+    #[test]
+    fn test_register_width() {
+        let writes = Arc::new(Mutex::new(Vec::new()));
+        let mut map = MemoryMap::new();
+        for (start, overflow) in [
+            (0x00, RegisterOverflow::Wrap),
+            (0x10, RegisterOverflow::Saturate),
+        ] {
+            let log = writes.clone();
+            map.map_region(start, start + 0x0f, MemoryMapEntryType::Write)
+                .on_write_u8(move |addr, data| log.lock().unwrap().push((addr, data)))
+                .set_register_width(1, overflow);
+        }
+
+        map.write_u16_at(0x04, 0x1ff);
+        map.write_u16_at(0x04, 0x123);
+        map.write_u32_at(0x14, 0x123);
+        map.write_u8_at(0x15, 0x42);
+        assert_eq!(
+            *writes.lock().unwrap(),
+            [(0x04, 0xff), (0x04, 0x23), (0x14, 0xff), (0x15, 0x42)]
+        );
+
+        map.set_strict_permissions(true);
+        map.select_address(0x04);
+        assert_eq!(
+            map.try_write_u16(0x1ff),
+            Err(MemoryMapError::RegisterOverflow {
+                addr: 0x04,
+                width: 1,
+                data: 0x1ff
+            })
+        );
+        assert_eq!(map.try_write_u16(0x0ff), Ok(()));
+        assert_eq!(writes.lock().unwrap().len(), 5);
+    }
+
+    #[test]
+    #[should_panic(expected = "isn't supported")]
+    fn test_bad_register_width() {
+        MemoryMapEntry::new().set_register_width(16, RegisterOverflow::Wrap);
+    }
+}
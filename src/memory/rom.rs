@@ -0,0 +1,169 @@
+use super::{Address, BusAddress, Device, MemoryMap, MemoryMapEntry, MemoryMapEntryType};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::fs;
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
+use std::path::Path;
+
+/// Read-only memory loaded from a byte image. Writes to it are silently dropped.
+///
+/// Like [`Ram`](super::Ram), addresses are taken relative to `base` and wrap around the image.
+#[derive(Clone, Debug)]
+pub struct Rom {
+    base: Address,
+    data: Vec<u8>,
+}
+
+impl Rom {
+    pub fn from_bytes(base: Address, data: Vec<u8>) -> Rom {
+        if data.is_empty() {
+            panic!("ROM image must be at least one byte long.");
+        }
+        Rom { base, data }
+    }
+
+    /// Loads the image from a file, refusing an empty one with `InvalidData`. Requires the
+    /// `std` feature.
+    #[cfg(feature = "std")]
+    pub fn from_file<P: AsRef<Path>>(base: Address, path: P) -> io::Result<Rom> {
+        let data = fs::read(path)?;
+        if data.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "ROM image file is empty.",
+            ));
+        }
+        Ok(Rom::from_bytes(base, data))
+    }
+
+    pub fn base(&self) -> Address {
+        self.base
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// The last address covered by the image.
+    pub fn end(&self) -> Address {
+        self.base
+            .checked_add(self.data.len() as Address - 1)
+            .expect("ROM image runs past the end of the address space.")
+    }
+
+    fn index(&self, addr: Address) -> usize {
+        (addr.wrapping_sub(self.base) % self.data.len() as Address) as usize
+    }
+
+    pub fn read_u8(&self, addr: Address) -> u8 {
+        self.data[self.index(addr)]
+    }
+}
+
+impl<A: BusAddress> Device<A> for Rom {
+    fn read(&mut self, addr: A, _width: u8) -> u128 {
+        self.read_u8(addr.to_u128()) as u128
+    }
+
+    fn write(&mut self, _addr: A, _width: u8, _data: u128) {}
+
+    fn max_width(&self) -> u8 {
+        1
+    }
+
+    fn peek(&self, addr: A) -> Option<u8> {
+        Some(self.read_u8(addr.to_u128()))
+    }
+
+    /// Patches the image, which the bus can't.
+    fn poke(&mut self, addr: A, data: u8) -> bool {
+        let index = self.index(addr.to_u128());
+        self.data[index] = data;
+        true
+    }
+
+    fn try_clone(&self) -> Option<Box<dyn Device<A> + Send>> {
+        Some(Box::new(self.clone()))
+    }
+
+    fn backing(&self) -> Option<(u128, usize)> {
+        Some((self.base, self.data.len()))
+    }
+
+    fn bytes(&self) -> Option<&[u8]> {
+        Some(&self.data)
+    }
+}
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// Maps `rom` over `base..=end` as a read-only region.
+    ///
+    /// Panics if the image doesn't fit in the map's address type.
+    pub fn map_rom(&mut self, rom: Rom) -> &mut MemoryMapEntry<A> {
+        if rom.end() > A::MAX.to_u128() {
+            panic!("ROM image runs past the end of the address space.");
+        }
+        let (start, end) = (A::from_u128(rom.base), A::from_u128(rom.end()));
+        let entry = self.map_region(start, end, MemoryMapEntryType::Read);
+        entry.set_device(rom);
+        entry.set_entry_type(MemoryMapEntryType::Read);
+        entry
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::Bus;
+
+    #[test]
+    fn test_rom() {
+        let image: Vec<u8> = (0..16).map(|i| i * 3).collect();
+        let mut map = MemoryMap::new();
+        map.map_rom(Rom::from_bytes(0x8000, image.clone()));
+        for (i, byte) in image.iter().enumerate() {
+            map.select_address(0x8000 + i as Address);
+            assert_eq!(map.read_u8(), *byte);
+        }
+        map.select_address(0x8004);
+        map.write_u8(0xff);
+        assert_eq!(map.try_write_u32(0xffff_ffff), Ok(()));
+        assert_eq!(map.read_u8(), 12);
+        map.select_address(0x8010);
+        assert_eq!(map.read_u8(), 0);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_rom_from_file() {
+        let path = std::env::temp_dir().join(format!("rustemu-rom-{}.bin", std::process::id()));
+        fs::write(&path, [0xde, 0xad, 0xbe, 0xef]).unwrap();
+        let rom = Rom::from_file(0x10, &path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(rom.len(), 4);
+        assert_eq!(rom.end(), 0x13);
+
+        let mut map = MemoryMap::new();
+        map.map_rom(rom);
+        map.select_address(0x10);
+        map.set_endianness(crate::memory::Endianness::Big);
+        assert_eq!(map.read_u32(), 0xdeadbeef);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_rom_from_empty_file() {
+        let path = std::env::temp_dir().join(format!("rustemu-empty-{}.bin", std::process::id()));
+        fs::write(&path, []).unwrap();
+        let err = Rom::from_file(0x10, &path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,216 @@
+use super::{lock, Address, Bus, BusAddress};
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+
+/// A handle to a bus shared between several emulated cores, possibly on different threads.
+/// Requires the `std` feature.
+///
+/// Every handle selects addresses on its own, so one core selecting an address can't redirect
+/// another core's access. Each access locks the bus for just that transaction, which makes
+/// accesses from different handles atomic with respect to each other but means cores contend
+/// for the lock on every access. A core doing a burst of accesses can hold [`SharedBus::lock`]
+/// for the whole burst instead, at the cost of stalling the other cores meanwhile.
+pub struct SharedBus<B: Bus<A>, A: BusAddress = Address> {
+    bus: Arc<Mutex<B>>,
+    addr: A,
+}
+
+/// Returned by the `try_` accessors of [`SharedBus`] when another handle holds the bus.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BusBusy;
+
+impl<B: Bus<A>, A: BusAddress> SharedBus<B, A> {
+    pub fn new(bus: B) -> SharedBus<B, A> {
+        SharedBus {
+            bus: Arc::new(Mutex::new(bus)),
+            addr: A::ZERO,
+        }
+    }
+
+    /// Locks the bus until the guard is dropped, blocking every other handle meanwhile.
+    pub fn lock(&self) -> MutexGuard<'_, B> {
+        lock(&self.bus)
+    }
+
+    /// Performs one transaction at this handle's selected address, blocking until the bus is
+    /// free.
+    fn access<T>(&mut self, func: impl FnOnce(&mut B) -> T) -> T {
+        let mut bus = lock(&self.bus);
+        bus.select_address(self.addr);
+        func(&mut bus)
+    }
+
+    /// Performs one transaction at this handle's selected address if the bus is free right now.
+    fn try_access<T>(&mut self, func: impl FnOnce(&mut B) -> T) -> Result<T, BusBusy> {
+        let mut bus = match self.bus.try_lock() {
+            Ok(bus) => bus,
+            Err(TryLockError::WouldBlock) => return Err(BusBusy),
+            Err(TryLockError::Poisoned(_)) => {
+                panic!("A shared device or bus was poisoned by a panic.")
+            }
+        };
+        bus.select_address(self.addr);
+        Ok(func(&mut bus))
+    }
+
+    pub fn try_read_u8(&mut self) -> Result<u8, BusBusy> {
+        self.try_access(|bus| bus.read_u8())
+    }
+
+    pub fn try_read_u16(&mut self) -> Result<u16, BusBusy> {
+        self.try_access(|bus| bus.read_u16())
+    }
+
+    pub fn try_read_u32(&mut self) -> Result<u32, BusBusy> {
+        self.try_access(|bus| bus.read_u32())
+    }
+
+    pub fn try_read_u64(&mut self) -> Result<u64, BusBusy> {
+        self.try_access(|bus| bus.read_u64())
+    }
+
+    pub fn try_read_u128(&mut self) -> Result<u128, BusBusy> {
+        self.try_access(|bus| bus.read_u128())
+    }
+
+    pub fn try_write_u8(&mut self, data: u8) -> Result<(), BusBusy> {
+        self.try_access(|bus| bus.write_u8(data))
+    }
+
+    pub fn try_write_u16(&mut self, data: u16) -> Result<(), BusBusy> {
+        self.try_access(|bus| bus.write_u16(data))
+    }
+
+    pub fn try_write_u32(&mut self, data: u32) -> Result<(), BusBusy> {
+        self.try_access(|bus| bus.write_u32(data))
+    }
+
+    pub fn try_write_u64(&mut self, data: u64) -> Result<(), BusBusy> {
+        self.try_access(|bus| bus.write_u64(data))
+    }
+
+    pub fn try_write_u128(&mut self, data: u128) -> Result<(), BusBusy> {
+        self.try_access(|bus| bus.write_u128(data))
+    }
+}
+
+/// Another handle to the same bus, starting at the same selected address.
+impl<B: Bus<A>, A: BusAddress> Clone for SharedBus<B, A> {
+    fn clone(&self) -> Self {
+        SharedBus {
+            bus: self.bus.clone(),
+            addr: self.addr,
+        }
+    }
+}
+
+impl<B: Bus<A>, A: BusAddress> Bus<A> for SharedBus<B, A> {
+    fn select_address(&mut self, addr: A) {
+        self.addr = addr;
+    }
+
+    fn handles(&self, addr: A) -> bool {
+        lock(&self.bus).handles(addr)
+    }
+
+    fn read_u8(&mut self) -> u8 {
+        self.access(|bus| bus.read_u8())
+    }
+
+    fn read_u16(&mut self) -> u16 {
+        self.access(|bus| bus.read_u16())
+    }
+
+    fn read_u32(&mut self) -> u32 {
+        self.access(|bus| bus.read_u32())
+    }
+
+    fn read_u64(&mut self) -> u64 {
+        self.access(|bus| bus.read_u64())
+    }
+
+    fn read_u128(&mut self) -> u128 {
+        self.access(|bus| bus.read_u128())
+    }
+
+    fn write_u8(&mut self, data: u8) {
+        self.access(|bus| bus.write_u8(data))
+    }
+
+    fn write_u16(&mut self, data: u16) {
+        self.access(|bus| bus.write_u16(data))
+    }
+
+    fn write_u32(&mut self, data: u32) {
+        self.access(|bus| bus.write_u32(data))
+    }
+
+    fn write_u64(&mut self, data: u64) {
+        self.access(|bus| bus.write_u64(data))
+    }
+
+    fn write_u128(&mut self, data: u128) {
+        self.access(|bus| bus.write_u128(data))
+    }
+
+    fn fetch_u8(&mut self) -> u8 {
+        self.access(|bus| bus.fetch_u8())
+    }
+
+    fn fetch_u16(&mut self) -> u16 {
+        self.access(|bus| bus.fetch_u16())
+    }
+
+    fn fetch_u32(&mut self) -> u32 {
+        self.access(|bus| bus.fetch_u32())
+    }
+
+    fn fetch_u64(&mut self) -> u64 {
+        self.access(|bus| bus.fetch_u64())
+    }
+
+    fn fetch_u128(&mut self) -> u128 {
+        self.access(|bus| bus.fetch_u128())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{MemoryMap, Ram};
+    use std::thread;
+
+    #[test]
+    fn test_shared_bus() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x0fff, Ram::new(0x1000));
+        let mut bus = SharedBus::new(map);
+
+        let cores: Vec<_> = (0..4u32)
+            .map(|core| {
+                let mut bus = bus.clone();
+                thread::spawn(move || {
+                    for i in 0..0x100 {
+                        bus.select_address((core * 0x400 + i * 4) as Address);
+                        bus.write_u32((core << 24) | i);
+                    }
+                })
+            })
+            .collect();
+        for core in cores {
+            core.join().unwrap();
+        }
+
+        bus.select_address(0x0c04);
+        assert_eq!(bus.read_u32(), 0x0300_0001);
+        let mut other = bus.clone();
+        other.select_address(0x0400);
+        assert_eq!(bus.read_u32(), 0x0300_0001);
+
+        let guard = bus.lock();
+        assert_eq!(other.try_read_u32(), Err(BusBusy));
+        drop(guard);
+        assert_eq!(other.try_read_u32(), Ok(0x0100_0000));
+        assert!(other.handles(0x0fff));
+        assert!(!other.handles(0x1000));
+    }
+}
@@ -0,0 +1,132 @@
+use super::{Bus, BusAddress, Endianness};
+use core::convert::TryFrom;
+
+/// The simplest bus there is: a borrowed byte slice starting at address 0, e.g. as the memory of
+/// a CPU core in a unit test.
+///
+/// Panics on any access reaching past the end of the slice.
+pub struct SliceBus<'a> {
+    data: &'a mut [u8],
+    addr: u128,
+    endianness: Endianness,
+}
+
+impl<'a> SliceBus<'a> {
+    /// A little-endian bus over `data`.
+    pub fn new(data: &'a mut [u8]) -> SliceBus<'a> {
+        SliceBus {
+            data,
+            addr: 0,
+            endianness: Endianness::Little,
+        }
+    }
+
+    pub fn with_endianness(mut self, endianness: Endianness) -> SliceBus<'a> {
+        self.endianness = endianness;
+        self
+    }
+
+    /// The bytes a `width`-byte access at the selected address covers.
+    fn bytes(&mut self, width: u8) -> &mut [u8] {
+        let len = self.data.len();
+        let range = usize::try_from(self.addr)
+            .ok()
+            .and_then(|start| Some(start..start.checked_add(width as usize)?))
+            .filter(|range| range.end <= len);
+        match range {
+            Some(range) => &mut self.data[range],
+            None => panic!(
+                "A {}-byte access at {:#x} runs past the end of the {}-byte slice.",
+                width, self.addr, len
+            ),
+        }
+    }
+
+    fn read_width(&mut self, width: u8) -> u128 {
+        let endianness = self.endianness;
+        let bytes = self.bytes(width);
+        let fold = |data: u128, &byte: &u8| (data << 8) | byte as u128;
+        match endianness {
+            Endianness::Little => bytes.iter().rev().fold(0, fold),
+            Endianness::Big => bytes.iter().fold(0, fold),
+        }
+    }
+
+    fn write_width(&mut self, width: u8, data: u128) {
+        let endianness = self.endianness;
+        let bytes = self.bytes(width);
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            let lane = match endianness {
+                Endianness::Little => i,
+                Endianness::Big => width as usize - 1 - i,
+            };
+            *byte = (data >> (lane * 8)) as u8;
+        }
+    }
+}
+
+macro_rules! slice_accesses {
+    ($($read:ident, $write:ident, $ty:ty, $width:expr;)*) => {
+        $(
+            fn $read(&mut self) -> $ty {
+                self.read_width($width) as $ty
+            }
+
+            fn $write(&mut self, data: $ty) {
+                self.write_width($width, data as u128);
+            }
+        )*
+    };
+}
+
+impl<'a, A: BusAddress> Bus<A> for SliceBus<'a> {
+    fn select_address(&mut self, addr: A) {
+        self.addr = addr.to_u128();
+    }
+
+    fn handles(&self, addr: A) -> bool {
+        addr.to_u128() < self.data.len() as u128
+    }
+
+    slice_accesses! {
+        read_u8, write_u8, u8, 1;
+        read_u16, write_u16, u16, 2;
+        read_u32, write_u32, u32, 4;
+        read_u64, write_u64, u64, 8;
+        read_u128, write_u128, u128, 16;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::Address;
+
+    #[test]
+    fn test_slice_bus() {
+        let mut memory = [0u8; 16];
+        let mut bus = SliceBus::new(&mut memory);
+        Bus::<Address>::write_u32_at(&mut bus, 0x4, 0xdeadbeef);
+        assert_eq!(Bus::<Address>::read_u16_at(&mut bus, 0x6), 0xdead);
+        assert_eq!(memory[4..8], [0xef, 0xbe, 0xad, 0xde]);
+
+        let mut bus = SliceBus::new(&mut memory).with_endianness(Endianness::Big);
+        Bus::<u16>::write_u16_at(&mut bus, 0x0, 0x1234);
+        assert_eq!(Bus::<u16>::read_u32_at(&mut bus, 0x4), 0xefbeadde);
+        assert_eq!(
+            Bus::<u16>::read_u128_at(&mut bus, 0x0),
+            0x1234 << 112 | 0xefbeadde << 64
+        );
+        assert!(Bus::<u16>::handles(&bus, 0xf));
+        assert!(!Bus::<u16>::handles(&bus, 0x10));
+        assert_eq!(memory[..2], [0x12, 0x34]);
+    }
+
+    #[test]
+    #[should_panic(expected = "runs past the end of the 16-byte slice")]
+    fn test_slice_bus_out_of_bounds() {
+        let mut memory = [0u8; 16];
+        let mut bus = SliceBus::new(&mut memory);
+        Bus::<Address>::read_u32_at(&mut bus, 0xe);
+    }
+}
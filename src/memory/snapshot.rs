@@ -0,0 +1,312 @@
+use super::{Address, BusAddress, Endianness, MemoryMap, MemoryMapEntry, MemoryMapError};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+
+/// The saved state of one region, identified by its bounds.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegionSnapshot<A = Address> {
+    pub start: A,
+    pub end: A,
+    pub state: Vec<u8>,
+}
+
+/// A save state: the selected address, the byte order and the state of every region whose device
+/// owns its contents, such as [`Ram`](super::Ram) and [`BankedRegion`](super::BankedRegion).
+///
+/// Delegates can't be captured, so a writable region driven by delegates makes
+/// [`MemoryMap::snapshot`] fail rather than silently leave its contents out. Read-only regions
+/// without device state, such as [`Rom`](super::Rom), are skipped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot<A = Address> {
+    current_addr: A,
+    endianness: Endianness,
+    regions: Vec<RegionSnapshot<A>>,
+}
+
+const MAGIC: &[u8; 4] = b"RESS";
+
+impl<A: BusAddress> Snapshot<A> {
+    pub fn regions(&self) -> &[RegionSnapshot<A>] {
+        &self.regions
+    }
+
+    /// Lists `(addr, old, new)` for every byte that differs from this snapshot to `other`, in
+    /// region order.
+    ///
+    /// Only regions present in both with the same bounds and state of the same length are
+    /// compared, and byte `i` of a region's state is taken to live at `start + i`, as it does
+    /// for [`Ram`](super::Ram). State longer than its region, like that of a
+    /// [`BankedRegion`](super::BankedRegion), isn't memory laid out from the start and is
+    /// skipped.
+    pub fn diff(&self, other: &Snapshot<A>) -> Vec<(A, u8, u8)> {
+        let mut changes = Vec::new();
+        for old in &self.regions {
+            let size = (old.end - old.start).to_u128().saturating_add(1);
+            if old.state.len() as u128 > size {
+                continue;
+            }
+            let new = other.regions.iter().find(|new| {
+                new.start == old.start && new.end == old.end && new.state.len() == old.state.len()
+            });
+            let new = match new {
+                Some(new) => new,
+                None => continue,
+            };
+            for (i, (&before, &after)) in old.state.iter().zip(&new.state).enumerate() {
+                if before != after {
+                    changes.push((old.start + A::from_u128(i as u128), before, after));
+                }
+            }
+        }
+        changes
+    }
+
+    /// Encodes the snapshot so it can be written to disk and read back with `from_bytes`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(match self.endianness {
+            Endianness::Little => 0,
+            Endianness::Big => 1,
+        });
+        bytes.extend_from_slice(&self.current_addr.to_u128().to_le_bytes());
+        bytes.extend_from_slice(&(self.regions.len() as u64).to_le_bytes());
+        for region in &self.regions {
+            bytes.extend_from_slice(&region.start.to_u128().to_le_bytes());
+            bytes.extend_from_slice(&region.end.to_u128().to_le_bytes());
+            bytes.extend_from_slice(&(region.state.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(&region.state);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Snapshot<A>, MemoryMapError> {
+        let mut reader = Reader(bytes);
+        if reader.take(4)? != MAGIC {
+            return Err(MemoryMapError::InvalidSnapshot);
+        }
+        let endianness = match reader.take(1)?[0] {
+            0 => Endianness::Little,
+            1 => Endianness::Big,
+            _ => return Err(MemoryMapError::InvalidSnapshot),
+        };
+        let current_addr = reader.address()?;
+        let mut regions = Vec::new();
+        for _ in 0..reader.u64()? {
+            let start = reader.address()?;
+            let end = reader.address()?;
+            let len = reader.u64()?;
+            let state = reader.take(len)?.to_vec();
+            regions.push(RegionSnapshot { start, end, state });
+        }
+        if !reader.0.is_empty() {
+            return Err(MemoryMapError::InvalidSnapshot);
+        }
+        Ok(Snapshot {
+            current_addr,
+            endianness,
+            regions,
+        })
+    }
+}
+
+/// Consumes an encoded snapshot, or layout, front to back.
+pub(super) struct Reader<'a>(pub(super) &'a [u8]);
+
+impl<'a> Reader<'a> {
+    pub(super) fn take(&mut self, len: u64) -> Result<&'a [u8], MemoryMapError> {
+        if len > self.0.len() as u64 {
+            return Err(MemoryMapError::InvalidSnapshot);
+        }
+        let (head, rest) = self.0.split_at(len as usize);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    pub(super) fn u64(&mut self) -> Result<u64, MemoryMapError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    /// Reads a full-width address, refusing one that doesn't fit the map's address type.
+    pub(super) fn address<A: BusAddress>(&mut self) -> Result<A, MemoryMapError> {
+        let addr = u128::from_le_bytes(self.take(16)?.try_into().unwrap());
+        if addr > A::MAX.to_u128() {
+            return Err(MemoryMapError::InvalidSnapshot);
+        }
+        Ok(A::from_u128(addr))
+    }
+}
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// Captures the selected address, the byte order and the state of every state-owning region.
+    ///
+    /// Fails with `NotSnapshottable` if a writable region other than an alias has no device state
+    /// to capture.
+    pub fn snapshot(&self) -> Result<Snapshot<A>, MemoryMapError> {
+        let mut entries: Vec<&MemoryMapEntry<A>> = self.entries[1..].iter().collect();
+        if self.needs_sort {
+            entries.sort_by_key(|entry| entry.start);
+        }
+        let mut regions = Vec::new();
+        // Aliases hold no state of their own.
+        for entry in entries.into_iter().filter(|entry| entry.alias.is_none()) {
+            match entry.device.as_ref().and_then(|device| device.save_state()) {
+                Some(state) => regions.push(RegionSnapshot {
+                    start: entry.start,
+                    end: entry.end,
+                    state,
+                }),
+                None if entry.is_writable() => {
+                    return Err(MemoryMapError::NotSnapshottable {
+                        start: entry.start.to_u128(),
+                        end: entry.end.to_u128(),
+                    })
+                }
+                None => {}
+            }
+        }
+        Ok(Snapshot {
+            current_addr: self.current_addr,
+            endianness: self.endianness,
+            regions,
+        })
+    }
+
+    /// Reloads a snapshot into a map laid out like the one it was taken from. Each region's state
+    /// goes to the first region with the same bounds whose device accepts it.
+    ///
+    /// Fails with `SnapshotMismatch` if no region takes some of the state. Regions handled before
+    /// that keep their restored state, but the selected address and byte order are left alone.
+    pub fn restore(&mut self, snapshot: &Snapshot<A>) -> Result<(), MemoryMapError> {
+        let mut used = vec![false; self.entries.len()];
+        for region in &snapshot.regions {
+            let mut restored = false;
+            for (i, entry) in self.entries.iter_mut().enumerate().skip(1) {
+                if used[i] || entry.start != region.start || entry.end != region.end {
+                    continue;
+                }
+                if let Some(device) = entry.device.as_mut() {
+                    if device.load_state(&region.state) {
+                        used[i] = true;
+                        restored = true;
+                        break;
+                    }
+                }
+            }
+            if !restored {
+                return Err(MemoryMapError::SnapshotMismatch {
+                    start: region.start.to_u128(),
+                    end: region.end.to_u128(),
+                });
+            }
+        }
+        self.current_addr = snapshot.current_addr;
+        self.endianness = snapshot.endianness;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{BankedRegion, Bus, MemoryMapEntryType, Ram, Rom};
+
+    fn build_map() -> MemoryMap {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x00ff, Ram::new(0x100));
+        map.map_rom(Rom::from_bytes(0x1000, vec![0xaa; 0x10]));
+        map.add_alias(0x8000, 0x80ff, 0x0000);
+        map
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut map = build_map();
+        let banks = map.map_banked(0x2000, BankedRegion::new(4, 0x10));
+        map.set_endianness(Endianness::Big);
+        map.select_address(0x0010);
+        map.write_u32(0xdeadbeef);
+        banks.select_bank(2).unwrap();
+        map.select_address(0x2004);
+        map.write_u8(0x55);
+
+        let snapshot = map.snapshot().unwrap();
+        // The ROM has nothing to save.
+        assert_eq!(snapshot.regions().len(), 2);
+        let bytes = snapshot.to_bytes();
+        assert_eq!(Snapshot::from_bytes(&bytes), Ok(snapshot));
+
+        let mut restored = build_map();
+        let restored_banks = restored.map_banked(0x2000, BankedRegion::new(4, 0x10));
+        restored
+            .restore(&Snapshot::from_bytes(&bytes).unwrap())
+            .unwrap();
+        assert_eq!(restored_banks.current_bank(), 2);
+        assert_eq!(restored.read_u8(), 0x55);
+        restored.select_address(0x0010);
+        assert_eq!(restored.read_u32(), 0xdeadbeef);
+        restored.select_address(0x0010);
+        assert_eq!(restored.read_u8(), 0xde);
+    }
+
+    #[test]
+    fn test_snapshot_diff() {
+        let mut map = build_map();
+        map.map_ram(0x4000, 0x40ff, Ram::new(0x100));
+        let banks = map.map_banked(0x2000, BankedRegion::new(4, 0x10));
+        map.write_u8_at(0x0010, 0x11);
+        let before = map.snapshot().unwrap();
+
+        map.write_u16_at(0x0010, 0x2233);
+        map.write_u8_at(0x40ff, 0x44);
+        map.write_u8_at(0x0020, 0x00);
+        banks.select_bank(1).unwrap();
+        map.write_u8_at(0x2000, 0x55);
+        let after = map.snapshot().unwrap();
+
+        assert_eq!(
+            before.diff(&after),
+            [
+                (0x0010, 0x11, 0x33),
+                (0x0011, 0x00, 0x22),
+                (0x40ff, 0x00, 0x44)
+            ]
+        );
+        assert_eq!(after.diff(&after), []);
+    }
+
+    #[test]
+    fn test_snapshot_rejects_delegates() {
+        let mut map = build_map();
+        map.map_region(0x3000, 0x3fff, MemoryMapEntryType::Write)
+            .on_write_u8(|_, _| {});
+        assert_eq!(
+            map.snapshot(),
+            Err(MemoryMapError::NotSnapshottable {
+                start: 0x3000,
+                end: 0x3fff
+            })
+        );
+    }
+
+    #[test]
+    fn test_restore_mismatch() {
+        let snapshot = build_map().snapshot().unwrap();
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x00ff, Ram::new(0x80));
+        assert_eq!(
+            map.restore(&snapshot),
+            Err(MemoryMapError::SnapshotMismatch {
+                start: 0x0000,
+                end: 0x00ff
+            })
+        );
+
+        let mut bytes = snapshot.to_bytes();
+        bytes.pop();
+        assert_eq!(
+            Snapshot::<Address>::from_bytes(&bytes),
+            Err(MemoryMapError::InvalidSnapshot)
+        );
+    }
+}
@@ -0,0 +1,295 @@
+use super::snapshot::Reader;
+use super::{Address, BusAddress, Device, MemoryMap, MemoryMapEntryType, MemoryMapError};
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+const MAGIC: &[u8; 4] = b"RELY";
+
+/// The plain-data part of a [`RegionSpec`], everything but its device, which can be saved and
+/// loaded with a machine's configuration using [`RegionLayout::encode`] and
+/// [`RegionLayout::decode`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegionLayout<A = Address> {
+    pub start: A,
+    pub end: A,
+    pub entry_type: MemoryMapEntryType,
+    /// Borrowed from the spec it came from, or owned once decoded.
+    pub name: Option<Cow<'static, str>>,
+    pub relative: bool,
+}
+
+impl<A: BusAddress> RegionLayout<A> {
+    /// Encodes a machine's layouts so they can be written to disk and read back with `decode`.
+    pub fn encode(layouts: &[RegionLayout<A>]) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&(layouts.len() as u64).to_le_bytes());
+        for layout in layouts {
+            bytes.extend_from_slice(&layout.start.to_u128().to_le_bytes());
+            bytes.extend_from_slice(&layout.end.to_u128().to_le_bytes());
+            bytes.push(entry_type_code(layout.entry_type));
+            bytes.push(layout.relative as u8);
+            match &layout.name {
+                Some(name) => {
+                    bytes.push(1);
+                    bytes.extend_from_slice(&(name.len() as u64).to_le_bytes());
+                    bytes.extend_from_slice(name.as_bytes());
+                }
+                None => bytes.push(0),
+            }
+        }
+        bytes
+    }
+
+    /// Decodes layouts encoded with `encode`, failing with `InvalidLayout` on anything else,
+    /// including addresses too wide for `A`.
+    pub fn decode(bytes: &[u8]) -> Result<Vec<RegionLayout<A>>, MemoryMapError> {
+        decode_layouts(&mut Reader(bytes)).map_err(|_| MemoryMapError::InvalidLayout)
+    }
+}
+
+fn decode_layouts<A: BusAddress>(
+    reader: &mut Reader,
+) -> Result<Vec<RegionLayout<A>>, MemoryMapError> {
+    if reader.take(4)? != MAGIC {
+        return Err(MemoryMapError::InvalidLayout);
+    }
+    let mut layouts = Vec::new();
+    for _ in 0..reader.u64()? {
+        let start = reader.address()?;
+        let end = reader.address()?;
+        let entry_type = entry_type_from_code(reader.take(1)?[0])?;
+        let relative = match reader.take(1)?[0] {
+            0 => false,
+            1 => true,
+            _ => return Err(MemoryMapError::InvalidLayout),
+        };
+        let name = match reader.take(1)?[0] {
+            0 => None,
+            1 => {
+                let len = reader.u64()?;
+                let name = String::from_utf8(reader.take(len)?.to_vec())
+                    .map_err(|_| MemoryMapError::InvalidLayout)?;
+                Some(Cow::Owned(name))
+            }
+            _ => return Err(MemoryMapError::InvalidLayout),
+        };
+        layouts.push(RegionLayout {
+            start,
+            end,
+            entry_type,
+            name,
+            relative,
+        });
+    }
+    if !reader.0.is_empty() {
+        return Err(MemoryMapError::InvalidLayout);
+    }
+    Ok(layouts)
+}
+
+fn entry_type_code(entry_type: MemoryMapEntryType) -> u8 {
+    match entry_type {
+        MemoryMapEntryType::UnmappedLow => 0,
+        MemoryMapEntryType::UnmappedHigh => 1,
+        MemoryMapEntryType::UnmappedOpenBus => 2,
+        MemoryMapEntryType::Read => 3,
+        MemoryMapEntryType::Write => 4,
+        MemoryMapEntryType::ReadWrite => 5,
+    }
+}
+
+fn entry_type_from_code(code: u8) -> Result<MemoryMapEntryType, MemoryMapError> {
+    Ok(match code {
+        0 => MemoryMapEntryType::UnmappedLow,
+        1 => MemoryMapEntryType::UnmappedHigh,
+        2 => MemoryMapEntryType::UnmappedOpenBus,
+        3 => MemoryMapEntryType::Read,
+        4 => MemoryMapEntryType::Write,
+        5 => MemoryMapEntryType::ReadWrite,
+        _ => return Err(MemoryMapError::InvalidLayout),
+    })
+}
+
+/// A region described as data, for building a whole map with [`MemoryMap::from_regions`].
+pub struct RegionSpec<A: BusAddress = Address> {
+    pub start: A,
+    pub end: A,
+    /// The region's type, kept as given even if a device is attached.
+    pub entry_type: MemoryMapEntryType,
+    /// Handles every access to the region. Without one the region only answers as unmapped space
+    /// of its type would.
+    pub device: Option<Box<dyn Device<A> + Send>>,
+    /// Whether the device sees offsets into the region rather than bus addresses, which suits
+    /// devices built for address 0 like `Ram::new`.
+    pub relative: bool,
+    pub name: Option<&'static str>,
+}
+
+impl<A: BusAddress> RegionSpec<A> {
+    pub fn new(start: A, end: A, entry_type: MemoryMapEntryType) -> RegionSpec<A> {
+        RegionSpec {
+            start,
+            end,
+            entry_type,
+            device: None,
+            relative: false,
+            name: None,
+        }
+    }
+
+    pub fn with_device<D>(mut self, device: D) -> RegionSpec<A>
+    where
+        D: Device<A> + Send + 'static,
+    {
+        self.device = Some(Box::new(device));
+        self
+    }
+
+    pub fn relative(mut self, relative: bool) -> RegionSpec<A> {
+        self.relative = relative;
+        self
+    }
+
+    pub fn named(mut self, name: &'static str) -> RegionSpec<A> {
+        self.name = Some(name);
+        self
+    }
+
+    pub fn layout(&self) -> RegionLayout<A> {
+        RegionLayout {
+            start: self.start,
+            end: self.end,
+            entry_type: self.entry_type,
+            name: self.name.map(Cow::Borrowed),
+            relative: self.relative,
+        }
+    }
+}
+
+/// A spec for the layout, with no device attached yet. Regions carry `'static` names, so a name
+/// decoded at run time is leaked, which suits layouts loaded once when a machine is set up.
+impl<A: BusAddress> From<RegionLayout<A>> for RegionSpec<A> {
+    fn from(layout: RegionLayout<A>) -> RegionSpec<A> {
+        RegionSpec {
+            start: layout.start,
+            end: layout.end,
+            entry_type: layout.entry_type,
+            device: None,
+            relative: layout.relative,
+            name: layout.name.map(|name| match name {
+                Cow::Borrowed(name) => name,
+                Cow::Owned(name) => Box::leak(name.into_boxed_str()),
+            }),
+        }
+    }
+}
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// Builds a map holding the given regions, refusing any that overlaps one before it with
+    /// `RegionOverlap`. The map comes back with the defaults of `new`, so later regions may
+    /// overlap unless [`MemoryMap::set_reject_overlaps`] says otherwise.
+    pub fn from_regions<I>(regions: I) -> Result<MemoryMap<A>, MemoryMapError>
+    where
+        I: IntoIterator<Item = RegionSpec<A>>,
+    {
+        let mut map = MemoryMap::default();
+        map.set_reject_overlaps(true);
+        for spec in regions {
+            let entry = map.try_map_region(spec.start, spec.end, spec.entry_type)?;
+            entry.set_relative_addressing(spec.relative);
+            entry.device = spec.device;
+            entry.name = spec.name;
+        }
+        map.set_reject_overlaps(false);
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{Bus, Ram, Rom};
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_from_regions() {
+        let specs: Vec<RegionSpec> = vec![
+            RegionSpec::new(0x0000, 0x00ff, MemoryMapEntryType::ReadWrite)
+                .with_device(Ram::new(0x100)),
+            RegionSpec::new(0x1000, 0x10ff, MemoryMapEntryType::ReadWrite)
+                .with_device(Ram::new(0x100))
+                .relative(true),
+            RegionSpec::new(0x8000, 0x800f, MemoryMapEntryType::Read)
+                .with_device(Rom::from_bytes(0x8000, vec![0xaa; 0x10])),
+        ];
+        let mut map = MemoryMap::from_regions(specs).unwrap();
+        assert_eq!(map.len(), 3);
+        map.write_u8_at(0x1001, 0x42);
+        assert_eq!(map.read_u8_at(0x1001), 0x42);
+        assert_eq!(map.read_u8_at(0x0001), 0x00);
+        assert_eq!(map.read_u8_at(0x8000), 0xaa);
+        assert_eq!(
+            map.region_at(0x8000).unwrap().entry_type,
+            MemoryMapEntryType::Read
+        );
+
+        let overlapping: Vec<RegionSpec> = vec![
+            RegionSpec::new(0x0000, 0x00ff, MemoryMapEntryType::ReadWrite),
+            RegionSpec::new(0x0080, 0x01ff, MemoryMapEntryType::Read),
+        ];
+        assert_eq!(
+            MemoryMap::from_regions(overlapping).err(),
+            Some(MemoryMapError::RegionOverlap {
+                start: 0x0000,
+                end: 0x00ff
+            })
+        );
+    }
+
+    #[test]
+    fn test_layout_round_trip() {
+        let specs: Vec<RegionSpec> = vec![
+            RegionSpec::new(0x0000, 0x0fff, MemoryMapEntryType::ReadWrite)
+                .with_device(Ram::new(0x1000))
+                .named("RAM"),
+            RegionSpec::new(0x4000, 0x40ff, MemoryMapEntryType::Write).relative(true),
+            RegionSpec::new(0xff00, 0xffff, MemoryMapEntryType::UnmappedOpenBus),
+        ];
+        let layouts: Vec<RegionLayout> = specs.iter().map(RegionSpec::layout).collect();
+        assert_eq!(
+            layouts[0],
+            RegionLayout {
+                start: 0x0000,
+                end: 0x0fff,
+                entry_type: MemoryMapEntryType::ReadWrite,
+                name: Some(Cow::Borrowed("RAM")),
+                relative: false
+            }
+        );
+
+        let bytes = RegionLayout::encode(&layouts);
+        let decoded = RegionLayout::decode(&bytes).unwrap();
+        assert_eq!(decoded, layouts);
+        let restored: Vec<RegionSpec> = decoded.into_iter().map(RegionSpec::from).collect();
+        assert!(restored[0].device.is_none());
+        assert_eq!(restored[1].entry_type, MemoryMapEntryType::Write);
+        assert!(restored[1].relative);
+        let map = MemoryMap::from_regions(restored).unwrap();
+        assert_eq!(map.region_at(0x0800).unwrap().name, Some("RAM"));
+
+        assert_eq!(
+            RegionLayout::<Address>::decode(&bytes[..bytes.len() - 1]),
+            Err(MemoryMapError::InvalidLayout)
+        );
+        assert_eq!(
+            RegionLayout::<u16>::decode(&RegionLayout::encode(&[RegionLayout {
+                start: 0x1_0000u128,
+                ..layouts[1].clone()
+            }])),
+            Err(MemoryMapError::InvalidLayout)
+        );
+    }
+}
@@ -0,0 +1,220 @@
+use super::{Address, Bus, BusAddress, Endianness};
+
+/// Routes each byte to one of two buses by a bit of its address, e.g. the even and odd chips of
+/// 8-bit memory on a 16-bit bus. The chosen bus sees the address with the selector bit taken
+/// out, so with bit 0 both chips see `addr >> 1`.
+///
+/// Wider accesses are split into bytes, ordered little endian unless set otherwise.
+pub struct SplitBus<E: Bus<A>, O: Bus<A>, A: BusAddress = Address> {
+    selector_bit: u32,
+    even: E,
+    odd: O,
+    addr: A,
+    endianness: Endianness,
+}
+
+impl<E: Bus<A>, O: Bus<A>, A: BusAddress> SplitBus<E, O, A> {
+    /// Sends bytes whose address has `selector_bit` clear to `even` and the rest to `odd`.
+    ///
+    /// Panics if the address type has no such bit.
+    pub fn new(selector_bit: u32, even: E, odd: O) -> SplitBus<E, O, A> {
+        if selector_bit as u128 >= A::MAX.to_u128().count_ones() as u128 {
+            panic!("Bit {} isn't part of the address.", selector_bit);
+        }
+        SplitBus {
+            selector_bit,
+            even,
+            odd,
+            addr: A::ZERO,
+            endianness: Endianness::Little,
+        }
+    }
+
+    pub fn with_endianness(mut self, endianness: Endianness) -> SplitBus<E, O, A> {
+        self.endianness = endianness;
+        self
+    }
+
+    pub fn even_mut(&mut self) -> &mut E {
+        &mut self.even
+    }
+
+    pub fn odd_mut(&mut self) -> &mut O {
+        &mut self.odd
+    }
+
+    pub fn into_inner(self) -> (E, O) {
+        (self.even, self.odd)
+    }
+
+    /// Whether `addr` goes to the odd bus, and the address that bus sees.
+    fn split(&self, addr: A) -> (bool, A) {
+        let addr = addr.to_u128();
+        let low = addr & ((1 << self.selector_bit) - 1);
+        // Shifting by the full width of a `u128` address would overflow.
+        let high = addr.checked_shr(self.selector_bit + 1).unwrap_or(0);
+        let inner = high << self.selector_bit | low;
+        (addr & (1 << self.selector_bit) != 0, A::from_u128(inner))
+    }
+
+    /// Selects `addr` on the bus answering for it, returning that bus.
+    fn route(&mut self, addr: A) -> &mut dyn Bus<A> {
+        let (odd, inner) = self.split(addr);
+        let bus: &mut dyn Bus<A> = if odd { &mut self.odd } else { &mut self.even };
+        bus.select_address(inner);
+        bus
+    }
+
+    /// The address of byte `i` of an access at the selected address and its shift in the value.
+    fn lane(&self, i: u8, width: u8) -> (A, u32) {
+        let shift = match self.endianness {
+            Endianness::Little => i as u32 * 8,
+            Endianness::Big => (width - 1 - i) as u32 * 8,
+        };
+        (self.addr.wrapping_add(A::from_u128(i as u128)), shift)
+    }
+
+    /// Reads `width` bytes a byte at a time, as instruction fetches if `fetch` is set.
+    fn read_width(&mut self, width: u8, fetch: bool) -> u128 {
+        let mut data = 0;
+        for i in 0..width {
+            let (addr, shift) = self.lane(i, width);
+            let bus = self.route(addr);
+            let byte = if fetch { bus.fetch_u8() } else { bus.read_u8() };
+            data |= (byte as u128) << shift;
+        }
+        data
+    }
+
+    fn write_width(&mut self, width: u8, data: u128) {
+        for i in 0..width {
+            let (addr, shift) = self.lane(i, width);
+            self.route(addr).write_u8((data >> shift) as u8);
+        }
+    }
+}
+
+macro_rules! split_accesses {
+    ($($read:ident, $fetch:ident, $write:ident, $ty:ty, $width:expr;)*) => {
+        $(
+            fn $read(&mut self) -> $ty {
+                self.read_width($width, false) as $ty
+            }
+
+            fn $fetch(&mut self) -> $ty {
+                self.read_width($width, true) as $ty
+            }
+
+            fn $write(&mut self, data: $ty) {
+                self.write_width($width, data as u128);
+            }
+        )*
+    };
+}
+
+impl<E: Bus<A>, O: Bus<A>, A: BusAddress> Bus<A> for SplitBus<E, O, A> {
+    fn select_address(&mut self, addr: A) {
+        self.addr = addr;
+    }
+
+    fn handles(&self, addr: A) -> bool {
+        match self.split(addr) {
+            (true, inner) => self.odd.handles(inner),
+            (false, inner) => self.even.handles(inner),
+        }
+    }
+
+    fn read_u8(&mut self) -> u8 {
+        let addr = self.addr;
+        self.route(addr).read_u8()
+    }
+
+    fn fetch_u8(&mut self) -> u8 {
+        let addr = self.addr;
+        self.route(addr).fetch_u8()
+    }
+
+    fn write_u8(&mut self, data: u8) {
+        let addr = self.addr;
+        self.route(addr).write_u8(data)
+    }
+
+    split_accesses! {
+        read_u16, fetch_u16, write_u16, u16, 2;
+        read_u32, fetch_u32, write_u32, u32, 4;
+        read_u64, fetch_u64, write_u64, u64, 8;
+        read_u128, fetch_u128, write_u128, u128, 16;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{MemoryMap, Permissions, Ram};
+    use alloc::boxed::Box;
+
+    #[test]
+    fn test_split_bus() {
+        let mut even = MemoryMap::new();
+        even.map_ram(0x0000, 0x00ff, Ram::new(0x100));
+        let mut odd = MemoryMap::new();
+        odd.map_ram(0x0000, 0x00ff, Ram::new(0x100));
+        let mut bus = SplitBus::new(0, even, odd).with_endianness(Endianness::Big);
+
+        bus.write_u16_at(0x0010, 0x1234);
+        assert_eq!(bus.read_u16_at(0x0010), 0x1234);
+        bus.write_u32_at(0x0021, 0xaabbccdd);
+        let (mut even, mut odd) = bus.into_inner();
+        assert_eq!(even.read_u8_at(0x0008), 0x12);
+        assert_eq!(odd.read_u8_at(0x0008), 0x34);
+        assert_eq!(odd.read_u8_at(0x0010), 0xaa);
+        assert_eq!(even.read_u8_at(0x0011), 0xbb);
+        assert_eq!(odd.read_u8_at(0x0011), 0xcc);
+        assert_eq!(even.read_u8_at(0x0012), 0xdd);
+    }
+
+    #[test]
+    fn test_split_bus_high_bit() {
+        let mut low = MemoryMap::new();
+        low.map_ram(0x0000, 0x00ff, Ram::new(0x100));
+        let high = MemoryMap::new();
+        let mut bus = SplitBus::new(8, low, high);
+        bus.write_u16_at(0x00ff, 0xbeef);
+        assert_eq!(bus.read_u16_at(0x00ff), 0x00ef);
+        assert!(bus.handles(0x0080));
+        // Past the first 0x100 bytes of each half.
+        assert_eq!(bus.read_u8_at(0x0200), 0x00);
+    }
+
+    #[test]
+    fn test_split_bus_fetches() {
+        let mut even = MemoryMap::new();
+        even.map_ram(0x0000, 0x00ff, Ram::new(0x100));
+        even.fill(0x0000, 0x00ff, 0x11);
+        let mut odd = MemoryMap::new();
+        odd.map_ram(0x0000, 0x00ff, Ram::new(0x100));
+        odd.fill(0x0000, 0x00ff, 0x22);
+        // Only the odd chip may be executed from.
+        even.set_permissions(0x0000, 0x00ff, Permissions::READ);
+        even.set_fault_handler(Box::new(|_| {}));
+        let mut bus = SplitBus::new(0, even, odd);
+
+        bus.select_address(0x0010);
+        assert_eq!(bus.read_u32(), 0x2211_2211);
+        assert_eq!(bus.fetch_u32(), 0x2200_2200);
+    }
+
+    #[test]
+    fn test_split_bus_top_bit() {
+        let mut low = MemoryMap::new();
+        low.map_ram(0x0000, 0x00ff, Ram::new(0x100));
+        let mut high = MemoryMap::new();
+        high.map_ram(0x0000, 0x00ff, Ram::new(0x100));
+        let mut bus = SplitBus::new(127, low, high);
+        bus.write_u8_at(1 << 127 | 0x10, 0x42);
+        assert_eq!(bus.read_u8_at(1 << 127 | 0x10), 0x42);
+        assert_eq!(bus.read_u8_at(0x10), 0x00);
+        let (_, mut high) = bus.into_inner();
+        assert_eq!(high.read_u8_at(0x10), 0x42);
+    }
+}
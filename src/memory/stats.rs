@@ -0,0 +1,118 @@
+use super::{search_entries, AccessKind, Address, BusAddress, MemoryMap};
+use alloc::vec::Vec;
+
+/// Access counts for one mapped region.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RegionStats<A = Address> {
+    pub start: A,
+    pub end: A,
+    /// Reads indexed by the log2 of the access width in bytes, so `reads[2]` counts 32-bit reads.
+    pub reads: [u64; 5],
+    /// Writes, indexed like `reads`.
+    pub writes: [u64; 5],
+}
+
+impl<A> RegionStats<A> {
+    pub fn total_reads(&self) -> u64 {
+        self.reads.iter().sum()
+    }
+
+    pub fn total_writes(&self) -> u64 {
+        self.writes.iter().sum()
+    }
+}
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// Turns per-region access counting on or off. Counting costs an extra region lookup per
+    /// access while enabled and nothing while disabled. Existing counts are kept either way.
+    pub fn enable_stats(&mut self, enabled: bool) {
+        self.stats_enabled = enabled;
+    }
+
+    /// The access counts of every mapped region in address order.
+    pub fn stats(&self) -> Vec<RegionStats<A>> {
+        let mut stats: Vec<_> = self.entries[1..]
+            .iter()
+            .map(|entry| RegionStats {
+                start: entry.start,
+                end: entry.end,
+                reads: entry.reads,
+                writes: entry.writes,
+            })
+            .collect();
+        if self.needs_sort {
+            stats.sort_by_key(|stats| stats.start);
+        }
+        stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        for entry in &mut self.entries {
+            entry.reads = [0; 5];
+            entry.writes = [0; 5];
+        }
+    }
+
+    /// Counts a completed access against the region answering for the current address.
+    pub(super) fn count_access(&mut self, width: u8, kind: AccessKind) {
+        let index = search_entries(self);
+        self.count_access_in(index, self.current_addr, width, kind);
+    }
+
+    /// Whether completed accesses need counting, for stats or for timing.
+    pub(super) fn accounting(&self) -> bool {
+        self.stats_enabled || self.timing_enabled
+    }
+
+    pub(super) fn count_access_in(&mut self, index: usize, addr: A, width: u8, kind: AccessKind) {
+        if self.timing_enabled {
+            self.charge(index, addr, width);
+        }
+        if !self.stats_enabled {
+            return;
+        }
+        let entry = &mut self.entries[index];
+        let counts = match kind {
+            AccessKind::Write => &mut entry.writes,
+            _ => &mut entry.reads,
+        };
+        counts[width.trailing_zeros() as usize] += 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{Bus, MemoryMapEntryType, Ram};
+
+    #[test]
+    fn test_stats() {
+        let mut map = MemoryMap::new();
+        map.map_region(0x100, 0x1ff, MemoryMapEntryType::UnmappedHigh);
+        map.map_ram(0x00, 0xff, Ram::new(0x100));
+        map.select_address(0x10);
+        map.read_u8();
+        assert_eq!(map.stats()[0].total_reads(), 0);
+
+        map.enable_stats(true);
+        map.read_u8();
+        map.read_u32();
+        map.write_u16(0);
+        map.select_address(0x100);
+        map.read_u8();
+        map.read_block(0x00, &mut [0; 3]);
+        map.enable_stats(false);
+        map.read_u8();
+
+        let stats = map.stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!((stats[0].start, stats[0].end), (0x00, 0xff));
+        assert_eq!(stats[0].reads, [4, 0, 1, 0, 0]);
+        assert_eq!(stats[0].writes, [0, 1, 0, 0, 0]);
+        assert_eq!(stats[1].reads, [1, 0, 0, 0, 0]);
+        assert_eq!(stats[1].total_writes(), 0);
+
+        map.reset_stats();
+        assert_eq!(map.stats()[0].total_reads(), 0);
+    }
+}
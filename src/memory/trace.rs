@@ -0,0 +1,141 @@
+use super::{AccessKind, Address, BusAddress, MemoryMap};
+use alloc::boxed::Box;
+use core::cell::RefCell;
+
+/// What issued an access, so tools can tell the guest's own accesses from the rest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessOrigin {
+    /// An ordinary access through the bus.
+    Guest,
+    /// A peek or poke.
+    Debugger,
+    /// A byte of a block transfer or `fill`.
+    Dma,
+}
+
+/// One completed bus transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceEvent<A = Address> {
+    pub addr: A,
+    /// The width of the access in bytes.
+    pub width: u8,
+    pub kind: AccessKind,
+    pub data: u128,
+    pub origin: AccessOrigin,
+}
+
+pub type TraceHook<A = Address> = Box<dyn FnMut(TraceEvent<A>) + Send>;
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// Installs a hook called with every completed read and write, or removes it with `None`.
+    /// Successful peeks and pokes are passed to it too, tagged as coming from the debugger.
+    pub fn set_trace(&mut self, hook: Option<TraceHook<A>>) {
+        self.trace = hook.map(RefCell::new);
+    }
+
+    /// Passes a completed access to the trace hook. Peeks only borrow the map, hence the cell,
+    /// which the hook can't reenter since it has no way to reach the map.
+    pub(super) fn trace_access(
+        &self,
+        addr: A,
+        width: u8,
+        kind: AccessKind,
+        data: u128,
+        origin: AccessOrigin,
+    ) {
+        if let Some(trace) = self.trace.as_ref() {
+            (trace.borrow_mut())(TraceEvent {
+                addr,
+                width,
+                kind,
+                data,
+                origin,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{Bus, MemoryMapEntryType, Ram};
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_trace() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorder = events.clone();
+        let mut map = MemoryMap::new();
+        map.map_ram(0x00, 0x0f, Ram::new(0x10));
+        map.map_region(0x10, 0x1f, MemoryMapEntryType::UnmappedHigh);
+        map.set_trace(Some(Box::new(move |event| {
+            recorder.lock().unwrap().push(event)
+        })));
+
+        map.select_address(0x04);
+        map.write_u16(0xbeef);
+        map.read_u32();
+        map.select_address(0x10);
+        map.read_u8();
+        map.write_u64(7);
+        map.set_trace(None);
+        map.read_u8();
+
+        let event = |addr, width, kind, data| TraceEvent {
+            addr,
+            width,
+            kind,
+            data,
+            origin: AccessOrigin::Guest,
+        };
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                event(0x04, 2, AccessKind::Write, 0xbeef),
+                event(0x04, 4, AccessKind::Read, 0xbeef),
+                event(0x10, 1, AccessKind::Read, 0xff),
+                event(0x10, 8, AccessKind::Write, 7),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_trace_origin() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorder = events.clone();
+        let mut map = MemoryMap::new();
+        map.map_ram(0x00, 0x0f, Ram::new(0x10));
+        map.add_alias(0x80, 0x8f, 0x00);
+        map.set_trace(Some(Box::new(move |event| {
+            recorder.lock().unwrap().push(event)
+        })));
+
+        map.write_u8_at(0x01, 0x42);
+        assert_eq!(map.peek_u8(0x81), Some(0x42));
+        assert!(map.poke_u8(0x02, 0x43));
+        map.read_block(0x01, &mut [0; 2]);
+        // Nothing to peek, so nothing to trace.
+        map.map_region(0x20, 0x2f, MemoryMapEntryType::Read)
+            .on_read_u8(|_| 0);
+        assert_eq!(map.peek_u8(0x20), None);
+
+        let event = |addr, kind, data, origin| TraceEvent {
+            addr,
+            width: 1,
+            kind,
+            data,
+            origin,
+        };
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                event(0x01, AccessKind::Write, 0x42, AccessOrigin::Guest),
+                event(0x81, AccessKind::Read, 0x42, AccessOrigin::Debugger),
+                event(0x02, AccessKind::Write, 0x43, AccessOrigin::Debugger),
+                event(0x01, AccessKind::Read, 0x42, AccessOrigin::Dma),
+                event(0x02, AccessKind::Read, 0x43, AccessOrigin::Dma),
+            ]
+        );
+    }
+}
@@ -0,0 +1,183 @@
+use super::{Address, Bus, BusAddress, Endianness, MemoryMap};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+/// A bus over a [`MemoryMap`] that holds back its writes until [`MemoryMap::transaction`]
+/// commits them, for instructions whose stores must all land or none. Reads see the writes made
+/// so far in the transaction on top of the map's contents.
+///
+/// The atomicity is only logical: reads go straight to the map, and nothing stops its devices
+/// from changing between the reads and the commit.
+pub struct Txn<'a, A: BusAddress = Address> {
+    map: &'a mut MemoryMap<A>,
+    addr: A,
+    /// The writes to commit, in order and at their original widths.
+    writes: Vec<(A, u8, u128)>,
+    /// The byte each buffered write leaves at each address.
+    pending: BTreeMap<A, u8>,
+    rolled_back: bool,
+}
+
+impl<'a, A: BusAddress> Txn<'a, A> {
+    /// Drops every write, both those made so far and any made later in the transaction.
+    pub fn rollback(&mut self) {
+        self.rolled_back = true;
+        self.writes.clear();
+        self.pending.clear();
+    }
+
+    pub fn is_rolled_back(&self) -> bool {
+        self.rolled_back
+    }
+
+    /// The address of byte `i` of a `width`-byte access at the selected address, and the shift
+    /// of that byte within the value.
+    fn lane(&self, i: u8, width: u8) -> (A, u32) {
+        let addr = self.addr.wrapping_add(A::from_u128(i as u128)) & self.map.global_addr_mask;
+        let shift = match self.map.endianness {
+            Endianness::Little => i as u32 * 8,
+            Endianness::Big => (width - 1 - i) as u32 * 8,
+        };
+        (addr, shift)
+    }
+
+    fn is_pending(&self, width: u8) -> bool {
+        !self.pending.is_empty()
+            && (0..width).any(|i| self.pending.contains_key(&self.lane(i, width).0))
+    }
+
+    /// Reads `width` bytes, taking the ones written in the transaction from the buffer and the
+    /// rest from the map one at a time.
+    fn read_pending(&mut self, width: u8) -> u128 {
+        let mut data = 0;
+        for i in 0..width {
+            let (addr, shift) = self.lane(i, width);
+            let byte = match self.pending.get(&addr) {
+                Some(&byte) => byte,
+                None => self.map.at_address(addr, |map| map.read_u8()),
+            };
+            data |= (byte as u128) << shift;
+        }
+        data
+    }
+
+    fn buffer(&mut self, width: u8, data: u128) {
+        if self.rolled_back {
+            return;
+        }
+        self.writes.push((self.addr, width, data));
+        for i in 0..width {
+            let (addr, shift) = self.lane(i, width);
+            self.pending.insert(addr, (data >> shift) as u8);
+        }
+    }
+}
+
+macro_rules! buffered_accesses {
+    ($($read:ident, $write:ident, $ty:ty, $width:expr;)*) => {
+        $(
+            fn $read(&mut self) -> $ty {
+                if self.is_pending($width) {
+                    return self.read_pending($width) as $ty;
+                }
+                let addr = self.addr;
+                self.map.at_address(addr, |map| map.$read())
+            }
+
+            fn $write(&mut self, data: $ty) {
+                self.buffer($width, data as u128);
+            }
+        )*
+    };
+}
+
+impl<'a, A: BusAddress> Bus<A> for Txn<'a, A> {
+    fn select_address(&mut self, addr: A) {
+        self.addr = addr;
+    }
+
+    fn handles(&self, addr: A) -> bool {
+        self.map.handles(addr)
+    }
+
+    buffered_accesses! {
+        read_u8, write_u8, u8, 1;
+        read_u16, write_u16, u16, 2;
+        read_u32, write_u32, u32, 4;
+        read_u64, write_u64, u64, 8;
+        read_u128, write_u128, u128, 16;
+    }
+}
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// Runs `f` over a [`Txn`] starting at the selected address, then performs its writes in the
+    /// order they were made unless it called [`Txn::rollback`]. Returns whether they were
+    /// performed. The selected address is left alone either way.
+    pub fn transaction<F>(&mut self, f: F) -> bool
+    where
+        F: FnOnce(&mut Txn<A>),
+    {
+        let mut txn = Txn {
+            addr: self.current_addr,
+            map: self,
+            writes: Vec::new(),
+            pending: BTreeMap::new(),
+            rolled_back: false,
+        };
+        f(&mut txn);
+        if txn.rolled_back {
+            return false;
+        }
+        let writes = core::mem::take(&mut txn.writes);
+        for (addr, width, data) in writes {
+            self.at_address(addr, |map| match width {
+                1 => map.write_u8(data as u8),
+                2 => map.write_u16(data as u16),
+                4 => map.write_u32(data as u32),
+                8 => map.write_u64(data as u64),
+                _ => map.write_u128(data),
+            });
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::Ram;
+
+    #[test]
+    fn test_transaction_commit() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x0fff, Ram::new(0x1000));
+        map.write_u32_at(0x0100, 0x1122_3344);
+        map.select_address(0x0800);
+        let committed = map.transaction(|txn| {
+            txn.write_u16_at(0x0101, 0xabcd);
+            assert_eq!(txn.read_u32_at(0x0100), 0x11ab_cd44);
+            txn.write_u8_at(0x0200, 0x55);
+        });
+        assert!(committed);
+        assert_eq!(map.current_address(), 0x0800);
+        assert_eq!(map.read_u32_at(0x0100), 0x11ab_cd44);
+        assert_eq!(map.read_u8_at(0x0200), 0x55);
+    }
+
+    #[test]
+    fn test_transaction_rollback() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x0fff, Ram::new(0x1000));
+        map.set_endianness(Endianness::Big);
+        map.write_u16_at(0x0100, 0x1234);
+        let committed = map.transaction(|txn| {
+            txn.write_u8_at(0x0101, 0xff);
+            assert_eq!(txn.read_u16_at(0x0100), 0x12ff);
+            txn.rollback();
+            assert_eq!(txn.read_u16_at(0x0100), 0x1234);
+            txn.write_u8_at(0x0100, 0xee);
+        });
+        assert!(!committed);
+        assert_eq!(map.read_u16_at(0x0100), 0x1234);
+    }
+}
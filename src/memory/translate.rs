@@ -0,0 +1,123 @@
+use super::{Address, Bus, BusAddress};
+use alloc::boxed::Box;
+
+pub type Translation<A = Address> = Box<dyn Fn(A) -> A + Send>;
+
+/// Runs every selected address through a translation, such as an MMU's page table walk, before
+/// handing it to the inner bus.
+pub struct TranslatingBus<B: Bus<A>, A: BusAddress = Address> {
+    inner: B,
+    translate: Translation<A>,
+}
+
+impl<B: Bus<A>, A: BusAddress> TranslatingBus<B, A> {
+    pub fn new(inner: B, translate: Translation<A>) -> TranslatingBus<B, A> {
+        TranslatingBus { inner, translate }
+    }
+
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut B {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+impl<B: Bus<A>, A: BusAddress> Bus<A> for TranslatingBus<B, A> {
+    fn select_address(&mut self, addr: A) {
+        let physical = (self.translate)(addr);
+        self.inner.select_address(physical);
+    }
+
+    fn handles(&self, addr: A) -> bool {
+        self.inner.handles((self.translate)(addr))
+    }
+
+    fn read_u8(&mut self) -> u8 {
+        self.inner.read_u8()
+    }
+
+    fn read_u16(&mut self) -> u16 {
+        self.inner.read_u16()
+    }
+
+    fn read_u32(&mut self) -> u32 {
+        self.inner.read_u32()
+    }
+
+    fn read_u64(&mut self) -> u64 {
+        self.inner.read_u64()
+    }
+
+    fn read_u128(&mut self) -> u128 {
+        self.inner.read_u128()
+    }
+
+    fn write_u8(&mut self, data: u8) {
+        self.inner.write_u8(data)
+    }
+
+    fn write_u16(&mut self, data: u16) {
+        self.inner.write_u16(data)
+    }
+
+    fn write_u32(&mut self, data: u32) {
+        self.inner.write_u32(data)
+    }
+
+    fn write_u64(&mut self, data: u64) {
+        self.inner.write_u64(data)
+    }
+
+    fn write_u128(&mut self, data: u128) {
+        self.inner.write_u128(data)
+    }
+
+    fn fetch_u8(&mut self) -> u8 {
+        self.inner.fetch_u8()
+    }
+
+    fn fetch_u16(&mut self) -> u16 {
+        self.inner.fetch_u16()
+    }
+
+    fn fetch_u32(&mut self) -> u32 {
+        self.inner.fetch_u32()
+    }
+
+    fn fetch_u64(&mut self) -> u64 {
+        self.inner.fetch_u64()
+    }
+
+    fn fetch_u128(&mut self) -> u128 {
+        self.inner.fetch_u128()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{MemoryMap, Ram};
+
+    #[test]
+    fn test_translating_bus() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x4000, 0x4fff, Ram::new(0x1000));
+        // Every virtual page lands on the single physical page at 0x4000.
+        let mut bus = TranslatingBus::new(map, Box::new(|addr| 0x4000 | (addr & 0xfff)));
+        bus.select_address(0xc0de_0123);
+        bus.write_u32(0x89abcdef);
+        bus.select_address(0x0123);
+        assert_eq!(bus.read_u32(), 0x89abcdef);
+        assert!(bus.handles(0xdead_0fff));
+
+        let mut map = bus.into_inner();
+        map.select_address(0x4123);
+        assert_eq!(map.read_u8(), 0xef);
+    }
+}
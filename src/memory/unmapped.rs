@@ -0,0 +1,79 @@
+use super::{Address, BusAddress, MemoryMap, MemoryMapEntryType};
+use alloc::boxed::Box;
+
+/// Answers reads of unmapped space, given the address and the width of the access in bytes.
+pub type UnmappedReadProvider<A = Address> = Box<dyn FnMut(A, u8) -> u128 + Send>;
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// Replaces the built-in unmapped behaviour, all zeroes for `UnmappedLow` and all ones for
+    /// `UnmappedHigh`, with `provider`. It sees each access at its full width unless the access
+    /// spans a mapped region too, in which case it sees the unmapped part of it.
+    pub fn set_unmapped_read(&mut self, provider: UnmappedReadProvider<A>) {
+        self.unmapped_read = Some(provider);
+    }
+
+    /// Answers a `width`-byte read of the unmapped entry at `index` from the bus latch for open
+    /// bus regions, or through the provider for the others if one is installed. Reads of a
+    /// write-only region with nothing to answer them see the open bus too.
+    pub(super) fn read_unmapped(&mut self, index: usize, addr: A, width: u8) -> Option<u128> {
+        let data = match self.entries[index].entry_type {
+            MemoryMapEntryType::UnmappedOpenBus => self.bus_latch,
+            MemoryMapEntryType::Write if !self.entries[index].has_read_handler() => self.bus_latch,
+            MemoryMapEntryType::UnmappedLow | MemoryMapEntryType::UnmappedHigh => {
+                let provider = self.unmapped_read.as_mut()?;
+                provider(addr, width)
+            }
+            _ => return None,
+        };
+        Some(if width == 16 {
+            data
+        } else {
+            data & ((1 << (width as u32 * 8)) - 1)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{Bus, Ram};
+
+    #[test]
+    fn test_unmapped_read_provider() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x1000, 0x1fff, Ram::new(0x1000));
+        map.map_region(0x2000, 0x2fff, MemoryMapEntryType::UnmappedHigh);
+        map.select_address(0x0034);
+        assert_eq!(map.read_u8(), 0x00);
+        map.select_address(0x2034);
+        assert_eq!(map.read_u16(), 0xffff);
+
+        map.set_unmapped_read(Box::new(|addr, _| addr & 0xff));
+        map.select_address(0x0034);
+        assert_eq!(map.read_u8(), 0x34);
+        map.select_address(0x2034);
+        assert_eq!(map.read_u16(), 0x34);
+        // Two bytes answered by the provider and two by RAM.
+        map.write_u16_at(0x1000, 0xbeef);
+        map.select_address(0x0ffe);
+        assert_eq!(map.read_u32(), 0xbeef_00fe);
+        let mut buf = [0; 2];
+        map.read_block(0x2010, &mut buf);
+        assert_eq!(buf, [0x10, 0x11]);
+    }
+
+    #[test]
+    fn test_open_bus() {
+        let mut map = MemoryMap::new();
+        map.map_region(0x0000, 0xffff, MemoryMapEntryType::UnmappedOpenBus);
+        map.map_ram(0x1000, 0x1fff, Ram::new(0x1000));
+        map.write_u8_at(0x1000, 0x42);
+        assert_eq!(map.read_u8_at(0x8000), 0x42);
+        map.write_u16_at(0x1002, 0xbeef);
+        assert_eq!(map.read_u16_at(0x8000), 0xbeef);
+        assert_eq!(map.read_u8_at(0x8000), 0xef);
+        // Reads of mapped memory drive the bus too.
+        assert_eq!(map.read_u8_at(0x1000), 0x42);
+        assert_eq!(map.read_u32_at(0x8000), 0x42);
+    }
+}
@@ -0,0 +1,107 @@
+use super::{AccessKind, AccessOrigin, Bus, BusAddress, MemoryMap, Permissions};
+
+macro_rules! updates {
+    ($($name:ident, $ty:ty;)*) => {
+        $(
+            /// Reads the value at `addr`, passes it to `f` and writes back what `f` returns, for
+            /// guest instructions like increments that modify memory in place. The region is
+            /// resolved once for both accesses, and the address stays selected afterwards.
+            ///
+            /// Otherwise it behaves like a read followed by a write: a failed read goes to the
+            /// fault handler and `f` sees what unmapped space would answer, and a failed write is
+            /// dropped.
+            pub fn $name<F>(&mut self, addr: A, f: F)
+            where
+                F: FnOnce($ty) -> $ty,
+            {
+                self.select_address(addr);
+                let width = core::mem::size_of::<$ty>() as u8;
+                self.update_width(width, |old| f(old as $ty) as u128);
+            }
+        )*
+    };
+}
+
+impl<A: BusAddress> MemoryMap<A> {
+    updates! {
+        update_u8, u8;
+        update_u16, u16;
+        update_u32, u32;
+        update_u64, u64;
+        update_u128, u128;
+    }
+
+    fn update_width<F>(&mut self, width: u8, f: F)
+    where
+        F: FnOnce(u128) -> u128,
+    {
+        // The alignment policy may wrap or refuse a misaligned access, and a refused resolve has
+        // an error to report, both of which the ordinary accesses take care of.
+        let resolved = match self.misalignment(width) {
+            0 => self.resolve(width).ok(),
+            _ => None,
+        };
+        let (index, contained) = match resolved {
+            Some(resolved) => resolved,
+            None => {
+                let old = self.try_read_width(width);
+                let old = self.unwrap_read(old, width);
+                let _ = self.try_write_width(width, f(old));
+                return;
+            }
+        };
+
+        let addr = self.current_addr;
+        let old = self
+            .check_permissions(width, Permissions::READ)
+            .and_then(|()| self.read_width_in(index, contained, width));
+        if let Ok(old) = old {
+            if self.accounting() {
+                self.count_access_in(index, addr, width, AccessKind::Read);
+            }
+            self.finish_access(addr, width, AccessKind::Read, old, AccessOrigin::Guest);
+        }
+        let new = f(self.unwrap_read(old, width));
+        let written = self
+            .check_permissions(width, Permissions::WRITE)
+            .and_then(|()| self.write_width_in(index, contained, width, new));
+        if written.is_ok() {
+            if self.accounting() {
+                self.count_access_in(index, addr, width, AccessKind::Write);
+            }
+            self.finish_access(addr, width, AccessKind::Write, new, AccessOrigin::Guest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{AlignmentPolicy, Ram};
+
+    #[test]
+    fn test_update() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x00ff, Ram::new(0x100));
+        map.enable_stats(true);
+        map.write_u8_at(0x0010, 0x41);
+        map.update_u8(0x0010, |value| value.wrapping_add(1));
+        assert_eq!(map.read_u8_at(0x0010), 0x42);
+        assert_eq!(map.current_address(), 0x0010);
+        // One write, then a read and a write for the update and a read to check it.
+        assert_eq!(map.stats()[0].reads, [2, 0, 0, 0, 0]);
+        assert_eq!(map.stats()[0].writes, [2, 0, 0, 0, 0]);
+
+        map.write_u8_at(0x00ff, 0xff);
+        map.update_u8(0x00ff, |value| value.wrapping_add(1));
+        assert_eq!(map.read_u8_at(0x00ff), 0x00);
+
+        map.set_alignment_policy(AlignmentPolicy::Wrap);
+        map.write_u32_at(0x0020, 0x1234_5678);
+        map.update_u32(0x0020, |value| value ^ 0xffff_0000);
+        assert_eq!(map.read_u32_at(0x0020), 0xedcb_5678);
+        // Wraps round to the start of the aligned block, like a plain read and write do.
+        map.update_u16(0x0023, |value| value ^ 0x00ff);
+        assert_eq!(map.read_u32_at(0x0020), 0x12cb_5678);
+    }
+}
@@ -0,0 +1,160 @@
+use super::{Bus, BusAddress};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// The width of a bus access, for interpreters that decode it at run time from the opcode and
+/// use [`Bus::read_dynamic`] and [`Bus::write_dynamic`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AccessWidth {
+    W8,
+    W16,
+    W32,
+    W64,
+    W128,
+}
+
+impl AccessWidth {
+    pub const fn byte_count(self) -> u8 {
+        match self {
+            AccessWidth::W8 => 1,
+            AccessWidth::W16 => 2,
+            AccessWidth::W32 => 4,
+            AccessWidth::W64 => 8,
+            AccessWidth::W128 => 16,
+        }
+    }
+
+    /// The width of a `bytes`-byte access, or `None` if there is no access that wide.
+    pub const fn from_byte_count(bytes: u8) -> Option<AccessWidth> {
+        match bytes {
+            1 => Some(AccessWidth::W8),
+            2 => Some(AccessWidth::W16),
+            4 => Some(AccessWidth::W32),
+            8 => Some(AccessWidth::W64),
+            16 => Some(AccessWidth::W128),
+            _ => None,
+        }
+    }
+}
+
+/// A value one bus access carries: `u8`, `u16`, `u32`, `u64` or `u128`, or the signed integer
+/// of the same width. Generic code can use
+/// [`Bus::read`] and [`Bus::write`] instead of picking one of the named accessors.
+pub trait BusValue: Copy + sealed::Sealed + 'static {
+    /// The width of the access in bytes.
+    const WIDTH: u8;
+
+    fn widen(self) -> u128;
+
+    /// Narrows `data` to this type, discarding any bits that don't fit.
+    fn narrow(data: u128) -> Self;
+
+    /// Performs a read of this width through the bus's named accessor.
+    fn read_from<A: BusAddress, B: Bus<A> + ?Sized>(bus: &mut B) -> Self;
+
+    fn write_to<A: BusAddress, B: Bus<A> + ?Sized>(self, bus: &mut B);
+}
+
+macro_rules! impl_bus_value {
+    ($($ty:ty, $unsigned:ty, $read:ident, $write:ident;)*) => {
+        $(
+            impl sealed::Sealed for $ty {}
+
+            impl BusValue for $ty {
+                const WIDTH: u8 = core::mem::size_of::<$ty>() as u8;
+
+                fn widen(self) -> u128 {
+                    self as $unsigned as u128
+                }
+
+                fn narrow(data: u128) -> Self {
+                    data as $ty
+                }
+
+                fn read_from<A: BusAddress, B: Bus<A> + ?Sized>(bus: &mut B) -> Self {
+                    bus.$read()
+                }
+
+                fn write_to<A: BusAddress, B: Bus<A> + ?Sized>(self, bus: &mut B) {
+                    bus.$write(self)
+                }
+            }
+        )*
+    };
+}
+
+impl_bus_value! {
+    u8, u8, read_u8, write_u8;
+    u16, u16, read_u16, write_u16;
+    u32, u32, read_u32, write_u32;
+    u64, u64, read_u64, write_u64;
+    u128, u128, read_u128, write_u128;
+    i8, u8, read_i8, write_i8;
+    i16, u16, read_i16, write_i16;
+    i32, u32, read_i32, write_i32;
+    i64, u64, read_i64, write_i64;
+    i128, u128, read_i128, write_i128;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{MemoryMap, Ram};
+
+    fn copy<T: BusValue, B: Bus>(bus: &mut B, from: u128, to: u128) {
+        bus.select_address(from);
+        let value: T = bus.read();
+        bus.select_address(to);
+        bus.write(value);
+    }
+
+    #[test]
+    fn test_generic_access() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x00ff, Ram::new(0x100));
+        map.write_u64_at(0x0000, 0x0123_4567_89ab_cdef);
+        copy::<u32, _>(&mut map, 0x0000, 0x0010);
+        assert_eq!(map.read_u64_at(0x0010), 0x89ab_cdef);
+
+        map.select_address(0x0000);
+        assert_eq!(map.try_read::<u16>(), Ok(0xcdef));
+        assert_eq!(map.try_write(0xffu8), Ok(()));
+        assert_eq!(map.read::<u128>(), 0x0123_4567_89ab_cdff);
+        assert_eq!(u16::WIDTH, 2);
+        assert_eq!(u16::narrow(0x1_2345), 0x2345);
+        assert_eq!((-1i16).widen(), 0xffff);
+        map.select_address(0x0000);
+        assert_eq!(map.try_read::<i8>(), Ok(-1));
+    }
+
+    #[test]
+    fn test_dynamic_access() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x00ff, Ram::new(0x100));
+        let widths = [
+            AccessWidth::W8,
+            AccessWidth::W16,
+            AccessWidth::W32,
+            AccessWidth::W64,
+            AccessWidth::W128,
+        ];
+        for width in widths {
+            let bytes = width.byte_count();
+            assert_eq!(AccessWidth::from_byte_count(bytes), Some(width));
+            let addr = bytes as u128 * 0x08;
+            map.select_address(addr);
+            // Bits past the width are dropped.
+            map.write_dynamic(width, u128::MAX);
+            map.select_address(addr);
+            assert_eq!(
+                map.read_dynamic(width),
+                u128::MAX >> (128 - bytes as u32 * 8)
+            );
+            map.select_address(addr);
+            assert_eq!(map.read_u128(), u128::MAX >> (128 - bytes as u32 * 8));
+        }
+        assert_eq!(AccessWidth::from_byte_count(3), None);
+    }
+}
@@ -0,0 +1,180 @@
+use super::{Address, BusAddress, BusEvent, MemoryMap};
+use alloc::boxed::Box;
+
+/// The direction of a bus access.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessKind {
+    Read,
+    Write,
+    /// Only meaningful as a watchpoint filter, where it matches both reads and writes.
+    ReadWrite,
+}
+
+impl AccessKind {
+    fn matches(self, access: AccessKind) -> bool {
+        self == AccessKind::ReadWrite || self == access
+    }
+}
+
+/// Identifies a watchpoint so it can be removed later.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WatchpointId(usize);
+
+pub type WatchpointCallback<A = Address> = Box<dyn FnMut(A, AccessKind, u128) + Send>;
+
+/// Decides from the value of an access whether a watchpoint fires.
+pub type WatchpointPredicate = Box<dyn FnMut(u128) -> bool + Send>;
+
+pub(super) struct Watchpoint<A> {
+    id: WatchpointId,
+    start: A,
+    end: A,
+    kind: AccessKind,
+    predicate: Option<WatchpointPredicate>,
+    callback: WatchpointCallback<A>,
+}
+
+impl<A: BusAddress> MemoryMap<A> {
+    /// Calls `callback` with the address, direction and value of every access of the given kind
+    /// that touches `start..=end`. The callback runs after the access completes and cannot change
+    /// its result.
+    pub fn add_watchpoint(
+        &mut self,
+        start: A,
+        end: A,
+        kind: AccessKind,
+        callback: WatchpointCallback<A>,
+    ) -> WatchpointId {
+        let id = WatchpointId(self.next_watchpoint_id);
+        self.next_watchpoint_id += 1;
+        self.watchpoints.push(Watchpoint {
+            id,
+            start,
+            end,
+            kind,
+            predicate: None,
+            callback,
+        });
+        id
+    }
+
+    /// Like [`MemoryMap::add_watchpoint`] for writes touching `addr`, but only calls `callback`
+    /// when `predicate` accepts the value written, e.g. to break once a flag reaches some state.
+    /// The predicate sees the whole value of a wider write.
+    pub fn add_value_watchpoint(
+        &mut self,
+        addr: A,
+        predicate: WatchpointPredicate,
+        callback: WatchpointCallback<A>,
+    ) -> WatchpointId {
+        let id = self.add_watchpoint(addr, addr, AccessKind::Write, callback);
+        self.watchpoints.last_mut().unwrap().predicate = Some(predicate);
+        id
+    }
+
+    /// Removes a watchpoint, returning whether it existed.
+    pub fn remove_watchpoint(&mut self, id: WatchpointId) -> bool {
+        let len = self.watchpoints.len();
+        self.watchpoints.retain(|watchpoint| watchpoint.id != id);
+        self.watchpoints.len() != len
+    }
+
+    /// Fires the watchpoints overlapping a completed `width`-byte access at `addr`.
+    pub(super) fn fire_watchpoints(&mut self, addr: A, width: u8, kind: AccessKind, data: u128) {
+        let last = addr.saturating_add(A::from_u128(width as u128 - 1));
+        for watchpoint in &mut self.watchpoints {
+            if !watchpoint.kind.matches(kind) || watchpoint.start > last || watchpoint.end < addr {
+                continue;
+            }
+            if let Some(predicate) = watchpoint.predicate.as_mut() {
+                if !predicate(data) {
+                    continue;
+                }
+            }
+            (watchpoint.callback)(addr, kind, data);
+            if let Some(hook) = self.event_hook.as_mut() {
+                hook(&BusEvent::WatchpointHit {
+                    id: watchpoint.id,
+                    addr,
+                    kind,
+                    data,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{Bus, Ram};
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_watchpoints() {
+        let hits = Arc::new(Mutex::new(Vec::new()));
+        let mut map = MemoryMap::new();
+        map.map_ram(0x00, 0xff, Ram::new(0x100));
+        let recorder = hits.clone();
+        let id = map.add_watchpoint(
+            0x10,
+            0x13,
+            AccessKind::Write,
+            Box::new(move |addr, kind, data| recorder.lock().unwrap().push((addr, kind, data))),
+        );
+        let recorder = hits.clone();
+        map.add_watchpoint(
+            0x20,
+            0x20,
+            AccessKind::ReadWrite,
+            Box::new(move |addr, kind, data| recorder.lock().unwrap().push((addr, kind, data))),
+        );
+
+        map.select_address(0x0e);
+        map.write_u32(0xaabbccdd);
+        assert_eq!(map.read_u32(), 0xaabbccdd);
+        map.select_address(0x20);
+        map.write_u8(0x42);
+        assert_eq!(map.read_u8(), 0x42);
+        map.write_block(0x12, &[1, 2, 3]);
+        assert_eq!(
+            *hits.lock().unwrap(),
+            [
+                (0x0e, AccessKind::Write, 0xaabbccdd),
+                (0x20, AccessKind::Write, 0x42),
+                (0x20, AccessKind::Read, 0x42),
+                (0x12, AccessKind::Write, 1),
+                (0x13, AccessKind::Write, 2),
+            ]
+        );
+
+        assert!(map.remove_watchpoint(id));
+        assert!(!map.remove_watchpoint(id));
+        hits.lock().unwrap().clear();
+        map.select_address(0x10);
+        map.write_u8(0);
+        assert!(hits.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_value_watchpoint() {
+        let hits = Arc::new(Mutex::new(Vec::new()));
+        let mut map = MemoryMap::new();
+        map.map_ram(0x00, 0xff, Ram::new(0x100));
+        let recorder = hits.clone();
+        map.add_value_watchpoint(
+            0x40,
+            Box::new(|data| data != 0),
+            Box::new(move |addr, _, data| recorder.lock().unwrap().push((addr, data))),
+        );
+
+        map.write_u8_at(0x40, 0);
+        map.write_u8_at(0x41, 1);
+        assert_eq!(map.read_u8_at(0x40), 0);
+        assert!(hits.lock().unwrap().is_empty());
+        map.write_u8_at(0x40, 1);
+        map.write_u16_at(0x3f, 0x0100);
+        assert_eq!(*hits.lock().unwrap(), [(0x40, 1), (0x3f, 0x0100)]);
+    }
+}
@@ -0,0 +1,65 @@
+use super::{BusAddress, Endianness, MemoryMap};
+use alloc::vec;
+
+macro_rules! word_blocks {
+    ($($read:ident, $ty:ty;)*) => {
+        $(
+            /// Fills `out` with consecutive words from `start` on in the map's byte order, e.g. to
+            /// load a vector table. The bytes are read like [`MemoryMap::read_block`] reads them,
+            /// so regions are resolved once per run rather than once per word.
+            pub fn $read(&mut self, start: A, out: &mut [$ty]) {
+                const SIZE: usize = core::mem::size_of::<$ty>();
+                let mut bytes = vec![0; out.len() * SIZE];
+                self.read_block(start, &mut bytes);
+                for (word, chunk) in out.iter_mut().zip(bytes.chunks_exact(SIZE)) {
+                    let mut array = [0; SIZE];
+                    array.copy_from_slice(chunk);
+                    *word = match self.endianness {
+                        Endianness::Little => <$ty>::from_le_bytes(array),
+                        Endianness::Big => <$ty>::from_be_bytes(array),
+                    };
+                }
+            }
+        )*
+    };
+}
+
+impl<A: BusAddress> MemoryMap<A> {
+    word_blocks! {
+        read_block_u16, u16;
+        read_block_u32, u32;
+        read_block_u64, u64;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{Bus, Ram};
+
+    #[test]
+    fn test_read_block_words() {
+        let mut map = MemoryMap::new();
+        map.map_ram(0x0000, 0x00ff, Ram::new(0x100));
+        map.write_block(
+            0x0000,
+            &[
+                0x00, 0x10, 0x00, 0x00, 0x34, 0x12, 0x00, 0x80, 0xef, 0xbe, 0xad, 0xde,
+            ],
+        );
+        let mut vectors = [0u32; 3];
+        map.read_block_u32(0x0000, &mut vectors);
+        assert_eq!(vectors, [0x0000_1000, 0x8000_1234, 0xdead_beef]);
+
+        // Reaching past the RAM into unmapped space.
+        let mut words = [0u64; 2];
+        map.read_block_u64(0x00f8, &mut words);
+        assert_eq!(words, [0, 0]);
+
+        map.set_endianness(Endianness::Big);
+        let mut halves = [0u16; 2];
+        map.read_block_u16(0x0008, &mut halves);
+        assert_eq!(halves, [0xefbe, 0xadde]);
+        assert_eq!(map.read_u16_at(0x0008), 0xefbe);
+    }
+}